@@ -0,0 +1,66 @@
+//! Chunk meshing benchmarks.
+//!
+//! Run with `cargo bench --bench meshing`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use mixcraft::world::{
+    blocks::BlockId,
+    chunk::{Chunk, ChunkCoord, CHUNK_SIZE},
+    mesher,
+    seed::WorldSeed,
+    World,
+};
+
+/// A chunk filled with stone, which only has faces on its boundary.
+fn solid() -> Chunk {
+    let mut chunk = Chunk::new();
+    for y in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.set(x, y, z, BlockId::STONE);
+            }
+        }
+    }
+    chunk
+}
+
+/// Alternating stone and air, the worst case where every face is visible.
+fn checkerboard() -> Chunk {
+    let mut chunk = Chunk::new();
+    for y in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                if (x + y + z) % 2 == 0 {
+                    chunk.set(x, y, z, BlockId::STONE);
+                }
+            }
+        }
+    }
+    chunk
+}
+
+/// A chunk of generated terrain, with grass, dirt, stone and flowers.
+fn terrain() -> Chunk {
+    World::generate(WorldSeed(0), 0, 1)
+        .chunk(ChunkCoord::default())
+        .expect("origin chunk is generated")
+        .clone()
+}
+
+fn build_mesh(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_mesh");
+    for (name, chunk) in [
+        ("solid", solid()),
+        ("checkerboard", checkerboard()),
+        ("terrain", terrain()),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| mesher::build_mesh(black_box(&chunk), [0.0; 3]))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build_mesh);
+criterion_main!(benches);
@@ -0,0 +1,97 @@
+// Vertex shader
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    fog_color: vec4<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    chunk_tint: f32,
+    time: f32,
+    grass_color: vec4<f32>,
+    // Direction towards the sun, and the strength of sunlight in w
+    sun: vec4<f32>,
+    // 1 if the target has no sRGB encoding, so colors are encoded here
+    encode_srgb: f32,
+    // Mip levels added when sampling block textures
+    mip_bias: f32,
+    // Fraction of daylight reaching faces turned away from the sun
+    ambient: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> globals: Globals;
+
+struct InstanceInput {
+    // World-space center of the quad
+    @location(0) position: vec3<f32>,
+    // Width and height of the quad
+    @location(1) size: f32,
+    @location(2) layer: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) texture: vec2<f32>,
+    @location(1) view_depth: f32,
+    @location(2) @interpolate(flat) layer: u32,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) index: u32,
+    in: InstanceInput,
+) -> VertexOutput {
+    // Corners of a triangle strip, in texture space
+    let uv = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    // The rows of the view rotation are the camera's axes in world space
+    let right = vec3<f32>(globals.view[0].x, globals.view[1].x, globals.view[2].x);
+    let up = vec3<f32>(globals.view[0].y, globals.view[1].y, globals.view[2].y);
+    // Texture V points down
+    let position = in.position + ((uv.x - 0.5) * right + (0.5 - uv.y) * up) * in.size;
+
+    var out: VertexOutput;
+    out.texture = uv;
+    out.layer = in.layer;
+    out.clip_position = globals.view_proj * vec4<f32>(position, 1.0);
+    // The camera looks down -Z in view space
+    out.view_depth = -(globals.view * vec4<f32>(position, 1.0)).z;
+    return out;
+}
+
+// Fragment shader
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d_array<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+// Fraction of the ambient light left at night, like on block faces
+let NIGHT_AMBIENT: f32 = 0.27;
+// Fragments less opaque than this are discarded, like cutout blocks
+let CUTOUT_ALPHA: f32 = 0.5;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleBias(t_diffuse, s_diffuse, in.texture, i32(in.layer), globals.mip_bias);
+    if (color.a < CUTOUT_ALPHA) {
+        discard;
+    }
+
+    // Billboards have no normal to light, so they only follow the time of day
+    let light = mix(globals.ambient * NIGHT_AMBIENT, 1.0, globals.sun.w);
+    var rgb = color.rgb * light;
+
+    // Linear fog
+    let fog = clamp(
+        (in.view_depth - globals.fog_start) / (globals.fog_end - globals.fog_start),
+        0.0,
+        1.0,
+    );
+    rgb = mix(rgb, globals.fog_color.rgb, fog);
+
+    if (globals.encode_srgb > 0.5) {
+        return vec4<f32>(linear_to_srgb(rgb), 1.0);
+    }
+    return vec4<f32>(rgb, 1.0);
+}
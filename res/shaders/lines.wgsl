@@ -0,0 +1,46 @@
+// Vertex shader
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    fog_color: vec4<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    chunk_tint: f32,
+    time: f32,
+    grass_color: vec4<f32>,
+    sun: vec4<f32>,
+    // 1 if the target has no sRGB encoding, so colors are encoded here
+    encode_srgb: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = in.color;
+    out.clip_position = globals.view_proj * vec4<f32>(in.position, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (globals.encode_srgb > 0.5) {
+        return vec4<f32>(linear_to_srgb(in.color.rgb), in.color.a);
+    }
+    return in.color;
+}
@@ -0,0 +1,348 @@
+// Chunk mesh generation
+//
+// Each invocation visits one block of a chunk. The `count_main` pass only
+// counts the quads a chunk needs, so that output buffers can be sized exactly,
+// and `mesh_main` then writes them out.
+
+let CHUNK_SIZE: u32 = 16u;
+let CHUNK_VOLUME: u32 = 4096u;
+// Number of 4 byte words in a vertex
+let VERTEX_STRIDE: u32 = 18u;
+// Highest block light level, must match `MAX_LIGHT`
+let MAX_LIGHT: f32 = 15.0;
+
+// Bits of `BlockInfo::flags`
+let FLAG_PRESENT: u32 = 1u;
+let FLAG_CROSS: u32 = 2u;
+let FLAG_TINT_TOP: u32 = 4u;
+let FLAG_TINT_ALL: u32 = 8u;
+
+struct BlockInfo {
+    top: u32,
+    bottom: u32,
+    sides: u32,
+    flags: u32,
+    emissive: f32,
+};
+
+struct Params {
+    // World-space position of the chunk's minimum corner
+    origin: vec4<f32>,
+};
+
+// Laid out like `wgpu::util::DrawIndexedIndirect`
+struct DrawArgs {
+    index_count: atomic<u32>,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+// Block id in the low 16 bits, then 4 bits each of block light and skylight.
+// The chunk's blocks are followed by the slice of each neighbour touching it,
+// ordered like `Face::ALL`, with air open to the sky where none is loaded.
+@group(0) @binding(1)
+var<storage, read> blocks: array<u32>;
+@group(0) @binding(2)
+var<storage, read> block_types: array<BlockInfo>;
+@group(0) @binding(3)
+var<storage, read_write> draw: DrawArgs;
+@group(0) @binding(4)
+var<storage, read_write> vertices: array<f32>;
+@group(0) @binding(5)
+var<storage, read_write> indices: array<u32>;
+
+fn block_info(id: u32) -> BlockInfo {
+    if (id >= arrayLength(&block_types)) {
+        return BlockInfo(0u, 0u, 0u, 0u, 0.0);
+    }
+    return block_types[id];
+}
+
+fn local_index(pos: vec3<i32>) -> u32 {
+    return (u32(pos.y) * CHUNK_SIZE + u32(pos.z)) * CHUNK_SIZE + u32(pos.x);
+}
+
+fn block_id(i: u32) -> u32 {
+    return blocks[i] & 0xffffu;
+}
+
+fn is_opaque(info: BlockInfo) -> bool {
+    return (info.flags & FLAG_PRESENT) != 0u && (info.flags & FLAG_CROSS) == 0u;
+}
+
+// Packed block at a position at most one step outside the chunk, looked up
+// in the edge slice of the neighbour on that side
+fn block_at(pos: vec3<i32>) -> u32 {
+    let size = i32(CHUNK_SIZE);
+    var face = 6u;
+    if (pos.x >= size) {
+        face = 0u;
+    } else if (pos.x < 0) {
+        face = 1u;
+    } else if (pos.y >= size) {
+        face = 2u;
+    } else if (pos.y < 0) {
+        face = 3u;
+    } else if (pos.z >= size) {
+        face = 4u;
+    } else if (pos.z < 0) {
+        face = 5u;
+    }
+    if (face == 6u) {
+        return blocks[local_index(pos)];
+    }
+
+    // Slices are indexed by the other two axes, in increasing order
+    let local = vec3<u32>((pos + vec3<i32>(size)) % vec3<i32>(size));
+    var uv = local.yz;
+    if (face / 2u == 1u) {
+        uv = local.xz;
+    } else if (face / 2u == 2u) {
+        uv = local.xy;
+    }
+    return blocks[CHUNK_VOLUME + (face * CHUNK_SIZE + uv.x) * CHUNK_SIZE + uv.y];
+}
+
+// Whether the block at a position that may lie outside the chunk is opaque
+fn is_opaque_at(pos: vec3<i32>) -> bool {
+    return is_opaque(block_info(block_at(pos) & 0xffffu));
+}
+
+// Brightness of the block light and skylight at a position that may lie
+// outside the chunk
+fn light_at(pos: vec3<i32>) -> vec2<f32> {
+    let block = block_at(pos);
+    return vec2<f32>(f32((block >> 16u) & 0xfu), f32((block >> 20u) & 0xfu)) / MAX_LIGHT;
+}
+
+fn face_normal(face: u32) -> vec3<i32> {
+    var normals = array<vec3<i32>, 6>(
+        vec3<i32>(1, 0, 0),
+        vec3<i32>(-1, 0, 0),
+        vec3<i32>(0, 1, 0),
+        vec3<i32>(0, -1, 0),
+        vec3<i32>(0, 0, 1),
+        vec3<i32>(0, 0, -1),
+    );
+    return normals[face];
+}
+
+// Corners of a face, ordered like `face_corners` in the CPU mesher
+fn face_corner(face: u32, corner: u32) -> vec3<f32> {
+    var corners = array<vec3<f32>, 24>(
+        // +X
+        vec3<f32>(1., 1., 1.), vec3<f32>(1., 0., 1.), vec3<f32>(1., 0., 0.), vec3<f32>(1., 1., 0.),
+        // -X
+        vec3<f32>(0., 1., 0.), vec3<f32>(0., 0., 0.), vec3<f32>(0., 0., 1.), vec3<f32>(0., 1., 1.),
+        // +Y
+        vec3<f32>(0., 1., 0.), vec3<f32>(0., 1., 1.), vec3<f32>(1., 1., 1.), vec3<f32>(1., 1., 0.),
+        // -Y
+        vec3<f32>(0., 0., 1.), vec3<f32>(0., 0., 0.), vec3<f32>(1., 0., 0.), vec3<f32>(1., 0., 1.),
+        // +Z
+        vec3<f32>(0., 1., 1.), vec3<f32>(0., 0., 1.), vec3<f32>(1., 0., 1.), vec3<f32>(1., 1., 1.),
+        // -Z
+        vec3<f32>(1., 1., 0.), vec3<f32>(1., 0., 0.), vec3<f32>(0., 0., 0.), vec3<f32>(0., 1., 0.),
+    );
+    return corners[face * 4u + corner];
+}
+
+// Corners of the quads of a cross-shaped block, ordered like `CROSS_CORNERS`
+// in the CPU mesher: each diagonal quad followed by its back
+fn cross_corner(quad: u32, corner: u32) -> vec3<f32> {
+    var corners = array<vec3<f32>, 16>(
+        vec3<f32>(0., 1., 0.), vec3<f32>(0., 0., 0.), vec3<f32>(1., 0., 1.), vec3<f32>(1., 1., 1.),
+        vec3<f32>(1., 1., 1.), vec3<f32>(1., 0., 1.), vec3<f32>(0., 0., 0.), vec3<f32>(0., 1., 0.),
+        vec3<f32>(1., 1., 0.), vec3<f32>(1., 0., 0.), vec3<f32>(0., 0., 1.), vec3<f32>(0., 1., 1.),
+        vec3<f32>(0., 1., 1.), vec3<f32>(0., 0., 1.), vec3<f32>(1., 0., 0.), vec3<f32>(1., 1., 0.),
+    );
+    return corners[quad * 4u + corner];
+}
+
+fn quad_uv(corner: u32) -> vec2<f32> {
+    // Top-left, bottom-left, bottom-right, top-right
+    let u = select(0.0, 1.0, corner >= 2u);
+    let v = select(0.0, 1.0, corner == 1u || corner == 2u);
+    return vec2<f32>(u, v);
+}
+
+fn local_position(i: u32) -> vec3<u32> {
+    return vec3<u32>(i % CHUNK_SIZE, i / (CHUNK_SIZE * CHUNK_SIZE), (i / CHUNK_SIZE) % CHUNK_SIZE);
+}
+
+// Number of quads generated by the block at index `i`
+fn quad_count(i: u32) -> u32 {
+    let info = block_info(block_id(i));
+    if ((info.flags & FLAG_PRESENT) == 0u) {
+        return 0u;
+    }
+    if ((info.flags & FLAG_CROSS) != 0u) {
+        return 4u;
+    }
+
+    let pos = vec3<i32>(local_position(i));
+    var count = 0u;
+    for (var face = 0u; face < 6u; face = face + 1u) {
+        if (!is_opaque_at(pos + face_normal(face))) {
+            count = count + 1u;
+        }
+    }
+    return count;
+}
+
+fn write_vertex(index: u32, position: vec3<f32>, uv: vec2<f32>, tint: f32, normal: vec3<f32>, layer: u32, tangent: vec4<f32>, emissive: f32, light: vec2<f32>, shade: f32) {
+    let base = index * VERTEX_STRIDE;
+    vertices[base] = position.x;
+    vertices[base + 1u] = position.y;
+    vertices[base + 2u] = position.z;
+    vertices[base + 3u] = uv.x;
+    vertices[base + 4u] = uv.y;
+    vertices[base + 5u] = tint;
+    vertices[base + 6u] = normal.x;
+    vertices[base + 7u] = normal.y;
+    vertices[base + 8u] = normal.z;
+    vertices[base + 9u] = bitcast<f32>(layer);
+    vertices[base + 10u] = tangent.x;
+    vertices[base + 11u] = tangent.y;
+    vertices[base + 12u] = tangent.z;
+    vertices[base + 13u] = tangent.w;
+    vertices[base + 14u] = emissive;
+    vertices[base + 15u] = light.x;
+    vertices[base + 16u] = light.y;
+    vertices[base + 17u] = shade;
+}
+
+// Brightness of each face, as `face_shade` in the CPU mesher gives it
+fn face_shade(face: u32) -> f32 {
+    var shades = array<f32, 6>(0.6, 0.6, 1.0, 0.5, 0.8, 0.8);
+    return shades[face];
+}
+
+// Tangents of each face, as `quad_tangent` in the CPU mesher computes them.
+// Tabulated so that drivers can't reorder the math and flip the sign of zeros.
+fn face_tangent(face: u32) -> vec4<f32> {
+    var tangents = array<vec4<f32>, 6>(
+        vec4<f32>(0., 0., -1., -1.),
+        vec4<f32>(0., 0., 1., -1.),
+        vec4<f32>(1., 0., 0., -1.),
+        vec4<f32>(1., 0., 0., -1.),
+        vec4<f32>(1., 0., 0., -1.),
+        vec4<f32>(-1., 0., 0., -1.),
+    );
+    return tangents[face];
+}
+
+// Tangents of the quads of a cross-shaped block
+fn cross_tangent(quad: u32) -> vec4<f32> {
+    var tangents = array<vec4<f32>, 4>(
+        vec4<f32>(0.70710677, 0., 0.70710677, 1.),
+        vec4<f32>(-0.70710677, 0., -0.70710677, 1.),
+        vec4<f32>(-0.70710677, 0., 0.70710677, 1.),
+        vec4<f32>(0.70710677, 0., -0.70710677, 1.),
+    );
+    return tangents[quad];
+}
+
+// Reserve space for a quad, returning the index of its first vertex
+fn allocate_quad() -> u32 {
+    let first_index = atomicAdd(&draw.index_count, 6u);
+    let base = first_index / 6u * 4u;
+
+    indices[first_index] = base;
+    indices[first_index + 1u] = base + 1u;
+    indices[first_index + 2u] = base + 2u;
+    indices[first_index + 3u] = base;
+    indices[first_index + 4u] = base + 2u;
+    indices[first_index + 5u] = base + 3u;
+
+    return base;
+}
+
+@compute @workgroup_size(64)
+fn count_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= CHUNK_VOLUME) {
+        return;
+    }
+    let count = quad_count(id.x);
+    if (count > 0u) {
+        atomicAdd(&draw.index_count, count * 6u);
+    }
+}
+
+@compute @workgroup_size(64)
+fn mesh_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= CHUNK_VOLUME) {
+        return;
+    }
+    let info = block_info(block_id(id.x));
+    if ((info.flags & FLAG_PRESENT) == 0u) {
+        return;
+    }
+
+    let local = local_position(id.x);
+    let offset = params.origin.xyz + vec3<f32>(local);
+
+    if ((info.flags & FLAG_CROSS) != 0u) {
+        let tint = select(0.0, 1.0, (info.flags & (FLAG_TINT_TOP | FLAG_TINT_ALL)) != 0u);
+        let normal = vec3<f32>(0.0, 1.0, 0.0);
+        let light = light_at(vec3<i32>(local));
+        for (var quad = 0u; quad < 4u; quad = quad + 1u) {
+            let base = allocate_quad();
+            let tangent = cross_tangent(quad);
+            for (var corner = 0u; corner < 4u; corner = corner + 1u) {
+                write_vertex(
+                    base + corner,
+                    offset + cross_corner(quad, corner),
+                    quad_uv(corner),
+                    tint,
+                    normal,
+                    info.sides,
+                    tangent,
+                    info.emissive,
+                    light,
+                    face_shade(2u),
+                );
+            }
+        }
+        return;
+    }
+
+    for (var face = 0u; face < 6u; face = face + 1u) {
+        let normal = face_normal(face);
+        if (is_opaque_at(vec3<i32>(local) + normal)) {
+            continue;
+        }
+
+        // Faces are ordered +X, -X, +Y, -Y, +Z, -Z
+        var tile = info.sides;
+        if (face == 2u) {
+            tile = info.top;
+        } else if (face == 3u) {
+            tile = info.bottom;
+        }
+        let tinted = (info.flags & FLAG_TINT_ALL) != 0u
+            || ((info.flags & FLAG_TINT_TOP) != 0u && face == 2u);
+        let tint = select(0.0, 1.0, tinted);
+
+        let base = allocate_quad();
+        let tangent = face_tangent(face);
+        let light = light_at(vec3<i32>(local) + normal);
+        for (var corner = 0u; corner < 4u; corner = corner + 1u) {
+            write_vertex(
+                base + corner,
+                offset + face_corner(face, corner),
+                quad_uv(corner),
+                tint,
+                vec3<f32>(normal),
+                tile,
+                tangent,
+                info.emissive,
+                light,
+                face_shade(face),
+            );
+        }
+    }
+}
@@ -1,31 +1,281 @@
 // Vertex shader
 
+struct Globals {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    fog_color: vec4<f32>,
+    fog_start: f32,
+    fog_end: f32,
+    chunk_tint: f32,
+    // Seconds since the renderer started
+    time: f32,
+    grass_color: vec4<f32>,
+    // Direction towards the sun, and the strength of sunlight in w
+    sun: vec4<f32>,
+    // 1 if the target has no sRGB encoding, so colors are encoded here
+    encode_srgb: f32,
+    // Mip levels added when sampling block textures
+    mip_bias: f32,
+    // Fraction of daylight reaching faces turned away from the sun
+    ambient: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> globals: Globals;
+
+// Must match `MAX_POINT_LIGHTS`
+let MAX_POINT_LIGHTS: u32 = 16u;
+
+struct PointLight {
+    position: vec3<f32>,
+    range: f32,
+    color: vec4<f32>,
+};
+
+struct PointLights {
+    count: u32,
+    lights: array<PointLight, MAX_POINT_LIGHTS>,
+};
+
+@group(1) @binding(1)
+var<uniform> point_lights: PointLights;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) texture: vec2<f32>,
+    @location(2) tint: f32,
+    @location(3) normal: vec3<f32>,
+    @location(4) layer: u32,
+    // Direction of increasing U, and the handedness of the tangent frame in w
+    @location(5) tangent: vec4<f32>,
+    // Light given off by the surface, added after lighting
+    @location(6) emissive: f32,
+    // Block light reaching the surface, from 0 to 1
+    @location(7) light: f32,
+    // Skylight reaching the surface, from 0 to 1
+    @location(8) sky_light: f32,
+    // Fixed brightness of the face's direction
+    @location(9) shade: f32,
 };
 
 struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
+    // Invariant so that the depth prepass and color pass produce identical depths
+    @builtin(position) @invariant clip_position: vec4<f32>,
     @location(0) texture: vec2<f32>,
+    @location(1) view_depth: f32,
+    @location(2) tint: f32,
+    @location(3) world_position: vec3<f32>,
+    @location(4) normal: vec3<f32>,
+    @location(5) @interpolate(flat) layer: u32,
+    @location(6) tangent: vec4<f32>,
+    @location(7) emissive: f32,
+    @location(8) block_light: f32,
+    @location(9) sky_light: f32,
+    @location(10) shade: f32,
+};
+
+// Must match `MAX_TEXTURE_LAYERS`
+let MAX_TEXTURE_LAYERS: u32 = 64u;
+
+struct TextureAnimation {
+    // Number of frames, in consecutive layers starting at this one
+    frames: u32,
+    fps: f32,
+    padding: vec2<u32>,
+};
+
+struct TextureAnimations {
+    layers: array<TextureAnimation, MAX_TEXTURE_LAYERS>,
+};
+
+@group(0) @binding(3)
+var<uniform> animations: TextureAnimations;
+
+// Layer holding the current frame of a texture starting at `layer`
+fn animated_layer(layer: u32) -> u32 {
+    let animation = animations.layers[min(layer, MAX_TEXTURE_LAYERS - 1u)];
+    if (animation.frames < 2u) {
+        return layer;
+    }
+    return layer + u32(globals.time * animation.fps) % animation.frames;
+}
+
+struct ChunkUniform {
+    // Transform from mesh positions to world space
+    model: mat4x4<f32>,
+    // Debug color of the chunk being drawn
+    tint: vec4<f32>,
 };
 
+@group(2) @binding(0)
+var<uniform> chunk: ChunkUniform;
+
+// World-space position of a vertex of the chunk being drawn
+fn world_position(in: VertexInput) -> vec3<f32> {
+    return (chunk.model * vec4<f32>(in.position, 1.0)).xyz;
+}
+
+// Transform a vertex, which may have been moved to `position`
+fn transform(in: VertexInput, position: vec3<f32>, uv: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.texture = uv;
+    out.tint = in.tint;
+    out.world_position = position;
+    out.normal = in.normal;
+    out.tangent = in.tangent;
+    out.emissive = in.emissive;
+    out.block_light = in.light;
+    out.sky_light = in.sky_light;
+    out.shade = in.shade;
+    out.layer = animated_layer(in.layer);
+    out.clip_position = globals.view_proj * vec4<f32>(position, 1.0);
+    // The camera looks down -Z in view space
+    out.view_depth = -(globals.view * vec4<f32>(position, 1.0)).z;
+    return out;
+}
+
 @vertex
 fn vs_main(
     in: VertexInput,
 ) -> VertexOutput {
-    var out: VertexOutput;
-    out.texture = in.texture;
-    out.clip_position = vec4<f32>(in.position, 1.0);
-    return out;
+    return transform(in, world_position(in), in.texture);
+}
+
+// Depth of the waves on the surface of liquids
+let WAVE_HEIGHT: f32 = 0.05;
+// Texture coordinates liquids scroll by per second
+let LIQUID_FLOW: vec2<f32> = vec2<f32>(0.03, 0.01);
+
+@vertex
+fn vs_liquid(
+    in: VertexInput,
+) -> VertexOutput {
+    var position = world_position(in);
+    // Lowered surfaces are the only corners not on a block boundary. Moving
+    // just those keeps liquids attached to the ground and each other.
+    if (fract(position.y) > 0.5) {
+        let waves = sin(position.x * 1.3 + globals.time * 1.7) + sin(position.z * 0.9 + globals.time * 1.1);
+        position.y = position.y + WAVE_HEIGHT * (waves - 2.0) * 0.25;
+    }
+    return transform(in, position, in.texture + LIQUID_FLOW * globals.time);
 }
 
 @group(0) @binding(0)
-var t_diffuse: texture_2d<f32>;
+var t_diffuse: texture_2d_array<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
+@group(0) @binding(2)
+var t_normal: texture_2d_array<f32>;
+
+// Fraction of the ambient light left at night
+let NIGHT_AMBIENT: f32 = 0.27;
+// Fraction of light that reaches faces the sky doesn't, like deep in caves
+let CAVE_AMBIENT: f32 = 0.04;
+// Color of block light at full brightness, a warm glow like fire
+let BLOCK_LIGHT_COLOR: vec3<f32> = vec3<f32>(1.0, 0.85, 0.6);
+
+// Build the tangent frame of a surface from its vertex tangent
+fn tangent_frame(normal: vec3<f32>, tangent: vec4<f32>) -> mat3x3<f32> {
+    let t = normalize(tangent.xyz);
+    // Points along increasing V
+    let bitangent = cross(normal, t) * tangent.w;
+    // Texture V points down, while normal maps store green pointing up
+    return mat3x3<f32>(t, -bitangent, normal);
+}
+
+// Light and fog a fragment, returning the color encoded for the target
+fn shade(in: VertexOutput) -> vec4<f32> {
+    var color = textureSampleBias(t_diffuse, s_diffuse, in.texture, i32(in.layer), globals.mip_bias);
+
+    // Perturb the surface normal by the normal map
+    let mapped = textureSampleBias(t_normal, s_diffuse, in.texture, i32(in.layer), globals.mip_bias).xyz * 2.0 - 1.0;
+    let tbn = tangent_frame(normalize(in.normal), in.tangent);
+    let normal = normalize(tbn * mapped);
+
+    // Simple directional lighting, fading out at night
+    let daylight = globals.sun.w;
+    let diffuse = max(dot(normal, normalize(globals.sun.xyz)), 0.0) * daylight;
+    let ambient = globals.ambient * mix(NIGHT_AMBIENT, 1.0, daylight);
+    let sky_lit = ambient + (1.0 - globals.ambient) * diffuse;
+    // The sun and sky only light what skylight reaches, fading like block light
+    var light = vec3<f32>(mix(CAVE_AMBIENT, sky_lit, in.sky_light * in.sky_light));
+
+    // Block light fades faster than its level, so it pools around its source
+    light = light + BLOCK_LIGHT_COLOR * in.block_light * in.block_light;
+
+    // Point lights fade out smoothly towards the edge of their range
+    for (var i = 0u; i < min(point_lights.count, MAX_POINT_LIGHTS); i = i + 1u) {
+        let point_light = point_lights.lights[i];
+        let to_light = point_light.position - in.world_position;
+        let distance = length(to_light);
+        let falloff = clamp(1.0 - distance / point_light.range, 0.0, 1.0);
+        let facing = max(dot(normal, to_light / max(distance, 1e-4)), 0.0);
+        light = light + point_light.color.rgb * falloff * falloff * facing;
+    }
+
+    // Tops stay brightest and bottoms darkest, however flat the lighting is
+    light = light * in.shade;
+
+    // Untinted faces are multiplied by white
+    let tint = mix(vec3<f32>(1.0), globals.grass_color.rgb, in.tint);
+    // Emissive surfaces glow regardless of the sun and point lights
+    color = vec4<f32>(color.rgb * tint * (light + in.emissive), color.a);
+    color = vec4<f32>(color.rgb * mix(vec3<f32>(1.0), chunk.tint.rgb, globals.chunk_tint), color.a);
+
+    // Linear fog
+    let fog = clamp(
+        (in.view_depth - globals.fog_start) / (globals.fog_end - globals.fog_start),
+        0.0,
+        1.0,
+    );
+    color = vec4<f32>(mix(color.rgb, globals.fog_color.rgb, fog), color.a);
+
+    // Lighting is done in linear space, which only sRGB targets encode for display
+    if (globals.encode_srgb > 0.5) {
+        return vec4<f32>(linear_to_srgb(color.rgb), color.a);
+    }
+    return color;
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.texture);
+    return shade(in);
+}
+
+// Fragments of cutout textures less opaque than this are discarded
+let CUTOUT_ALPHA: f32 = 0.5;
+
+// Opaque geometry with see-through gaps in cutout textures, like plants
+@fragment
+fn fs_cutout(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = shade(in);
+    if (color.a < CUTOUT_ALPHA) {
+        discard;
+    }
+    return vec4<f32>(color.rgb, 1.0);
+}
+
+// Writes only the depth of fragments that fs_cutout keeps
+@fragment
+fn fs_depth_cutout(in: VertexOutput) {
+    let alpha = textureSampleBias(t_diffuse, s_diffuse, in.texture, i32(in.layer), globals.mip_bias).a;
+    if (alpha < CUTOUT_ALPHA) {
+        discard;
+    }
+}
+
+// Runs for the fragments of a chunk's box that pass the depth test, which
+// occlusion queries count
+@fragment
+fn fs_occlusion() {
+}
+
+// Cracks blended unlit over a block being broken
+@fragment
+fn fs_crack(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.texture, i32(in.layer));
+    if (globals.encode_srgb > 0.5) {
+        return vec4<f32>(linear_to_srgb(color.rgb), color.a);
+    }
+    return color;
 }
@@ -0,0 +1,9 @@
+// Shared by the shaders that draw to targets without sRGB encoding.
+// Prepended to their source by `shader::create`, so it isn't a shader of its own.
+
+// Encode a linear color with the sRGB transfer function
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
@@ -0,0 +1,40 @@
+// Screen-space overlay
+
+struct UiGlobals {
+    // Multiplies UI units into normalized device coordinates
+    scale: vec2<f32>,
+    // 1 if the target has no sRGB encoding, so colors are encoded here
+    encode_srgb: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> ui: UiGlobals;
+
+struct VertexInput {
+    // Position in UI units, relative to the center of the screen
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    in: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = in.color;
+    out.clip_position = vec4<f32>(in.position * ui.scale, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if (ui.encode_srgb > 0.5) {
+        return vec4<f32>(linear_to_srgb(in.color.rgb), in.color.a);
+    }
+    return in.color;
+}
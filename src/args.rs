@@ -0,0 +1,194 @@
+//! Options given on the command line, overriding the settings file.
+
+use std::fmt;
+
+use mixcraft::renderer::RendererConfig;
+use mixcraft::world::seed::WorldSeed;
+
+/// Printed for `--help`, and after invalid arguments.
+pub const USAGE: &str = "\
+Usage: mixcraft [OPTIONS]
+
+Options:
+    --width <PIXELS>            Width of the window, in logical pixels
+    --height <PIXELS>           Height of the window, in logical pixels
+    --seed <SEED>               World seed, as a number or any text
+    --render-distance <CHUNKS>  Chunks loaded on each side of the origin
+    --backend <BACKENDS>        Graphics APIs to use, separated by commas, from
+                                vulkan, metal, dx12, dx11 and gl
+    -h, --help                  Print this message
+
+Options override mixcraft.toml, and the settings aren't saved on exit when any
+are given. WGPU_BACKEND takes precedence over --backend.";
+
+/// Options that take a value.
+const VALUE_OPTIONS: &[&str] = &[
+    "--width",
+    "--height",
+    "--seed",
+    "--render-distance",
+    "--backend",
+];
+
+/// Error parsing the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    UnknownArgument(String),
+    /// An option was given without its value.
+    MissingValue(&'static str),
+    InvalidValue {
+        option: &'static str,
+        value: String,
+    },
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownArgument(arg) => write!(f, "unknown argument {arg:?}"),
+            Self::MissingValue(option) => write!(f, "{option} needs a value"),
+            Self::InvalidValue { option, value } => {
+                write!(f, "invalid value {value:?} for {option}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+/// Settings chosen on the command line. Options that weren't given are `None`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchOptions {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub seed: Option<WorldSeed>,
+    pub render_distance: Option<u32>,
+    pub backends: Option<wgpu::Backends>,
+    /// Whether to print the usage instead of starting.
+    pub help: bool,
+}
+
+impl LaunchOptions {
+    /// Parse arguments, not including the program name.
+    ///
+    /// Values follow their option either as the next argument or after `=`,
+    /// like `--seed 42` or `--seed=42`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (arg.clone(), None),
+            };
+            if matches!(name.as_str(), "-h" | "--help") && inline_value.is_none() {
+                options.help = true;
+                continue;
+            }
+            let Some(&option) = VALUE_OPTIONS.iter().find(|&&option| option == name) else {
+                return Err(ArgsError::UnknownArgument(arg));
+            };
+
+            let value = inline_value
+                .or_else(|| args.next())
+                .ok_or(ArgsError::MissingValue(option))?;
+            let invalid = || ArgsError::InvalidValue {
+                option,
+                value: value.clone(),
+            };
+            let size = || {
+                value
+                    .parse()
+                    .ok()
+                    .filter(|&n: &u32| n > 0)
+                    .ok_or_else(invalid)
+            };
+            match option {
+                "--width" => options.width = Some(size()?),
+                "--height" => options.height = Some(size()?),
+                "--seed" => options.seed = Some(WorldSeed::from_string(&value)),
+                "--render-distance" => {
+                    options.render_distance = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "--backend" => {
+                    let backends =
+                        wgpu::util::parse_backends_from_comma_list(&value.to_lowercase());
+                    if backends.is_empty() {
+                        return Err(invalid());
+                    }
+                    options.backends = Some(backends);
+                }
+                _ => unreachable!("every option taking a value is handled"),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether no settings were overridden.
+    pub fn is_empty(&self) -> bool {
+        Self {
+            help: self.help,
+            ..Self::default()
+        } == *self
+    }
+
+    /// Override settings with the options that were given.
+    pub fn apply(&self, config: &mut RendererConfig) {
+        if let Some(width) = self.width {
+            config.window.width = width;
+        }
+        if let Some(height) = self.height {
+            config.window.height = height;
+        }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+        }
+        if let Some(distance) = self.render_distance {
+            config.render_distance = distance;
+        }
+        if let Some(backends) = self.backends {
+            config.backends = backends;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<LaunchOptions, ArgsError> {
+        LaunchOptions::parse(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    #[test]
+    fn parses_values_after_a_space_or_equals() {
+        let options = parse(&["--width", "800", "--height=600", "--backend=Vulkan,gl"]).unwrap();
+        assert_eq!(options.width, Some(800));
+        assert_eq!(options.height, Some(600));
+        assert_eq!(
+            options.backends,
+            Some(wgpu::Backends::VULKAN | wgpu::Backends::GL)
+        );
+        assert!(!options.is_empty());
+
+        assert!(parse(&[]).unwrap().is_empty());
+        assert!(parse(&["--help"]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert_eq!(
+            parse(&["--fov"]),
+            Err(ArgsError::UnknownArgument("--fov".to_owned()))
+        );
+        assert_eq!(parse(&["--seed"]), Err(ArgsError::MissingValue("--seed")));
+        assert_eq!(
+            parse(&["--width", "0"]),
+            Err(ArgsError::InvalidValue {
+                option: "--width",
+                value: "0".to_owned(),
+            })
+        );
+        assert!(parse(&["--backend", "glide"]).is_err());
+    }
+}
@@ -0,0 +1,4 @@
+//! A Minecraft clone.
+
+pub mod renderer;
+pub mod world;
@@ -1,64 +1,276 @@
 //! A Minecraft clone.
 
-pub mod renderer;
+mod args;
 
+use std::time::{Duration, Instant};
+
+use nalgebra_glm as glm;
 use winit::{
+    dpi::LogicalSize,
     event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget},
+    window::{Fullscreen, Window, WindowBuilder},
+};
+
+use args::{LaunchOptions, USAGE};
+use mixcraft::renderer::{
+    camera::Camera,
+    config::{WindowConfig, WindowMode},
+    input::InputState,
+    viewport::ViewportId,
+    Renderer, RendererConfig,
 };
 
-use renderer::Renderer;
+/// How often the window title is refreshed with live statistics.
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// File settings are loaded from at startup, and saved to on exit.
+const CONFIG_PATH: &str = "mixcraft.toml";
+
+/// Open the main window as configured.
+fn build_window(config: &WindowConfig, target: &EventLoopWindowTarget<()>) -> Window {
+    WindowBuilder::new()
+        .with_title(&config.title)
+        .with_inner_size(LogicalSize::new(config.width, config.height))
+        .with_maximized(config.mode == WindowMode::Maximized)
+        .with_fullscreen(
+            (config.mode == WindowMode::Fullscreen).then_some(Fullscreen::Borderless(None)),
+        )
+        .build(target)
+        .unwrap()
+}
+
+/// Switch between windowed and borderless fullscreen.
+///
+/// The window is resized in response, which reconfigures the surface.
+fn toggle_fullscreen(window: &Window) {
+    match window.fullscreen() {
+        Some(_) => window.set_fullscreen(None),
+        None => window.set_fullscreen(Some(Fullscreen::Borderless(None))),
+    }
+}
+
+/// Stop the event loop, saving the settings if `save` is set.
+///
+/// The window is reopened the way it was left, fullscreen or not.
+fn exit(state: &Renderer, window: &Window, save: bool, control_flow: &mut ControlFlow) {
+    *control_flow = ControlFlow::Exit;
+    if !save {
+        return;
+    }
+
+    let mut config = state.config();
+    config.window.mode = match (window.fullscreen(), config.window.mode) {
+        (Some(_), _) => WindowMode::Fullscreen,
+        (None, WindowMode::Fullscreen) => WindowMode::Windowed,
+        (None, mode) => mode,
+    };
+    if let Err(e) = config.save(CONFIG_PATH) {
+        tracing::warn!("failed to save settings: {e}");
+    }
+}
 
-async fn run() -> ! {
+/// A second window watching the world from a fixed camera, for debugging.
+struct SecurityCamera {
+    window: Window,
+    viewport: ViewportId,
+}
+
+impl SecurityCamera {
+    /// Open the window, looking at the origin from above one corner.
+    fn open(state: &mut Renderer, target: &EventLoopWindowTarget<()>) -> Option<Self> {
+        let window = WindowBuilder::new()
+            .with_title("mixcraft — security camera")
+            .build(target)
+            .ok()?;
+        let size = window.inner_size();
+        let camera = Camera::new(
+            glm::vec3(32.0, 28.0, 32.0),
+            -3.0 * std::f32::consts::FRAC_PI_4,
+            -0.5,
+            70.0,
+            size.width as f32 / size.height.max(1) as f32,
+        );
+        // SAFETY: the viewport is removed before the window is dropped, whenever
+        // the security camera is closed
+        let viewport = unsafe { state.add_viewport(&window, camera)? };
+        Some(Self { window, viewport })
+    }
+}
+
+async fn run(options: LaunchOptions) -> ! {
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut config = RendererConfig::load(CONFIG_PATH);
+    options.apply(&mut config);
+    // Don't write one-off overrides back to the settings file
+    let save_settings = options.is_empty();
+    let window = build_window(&config.window, &event_loop);
+    let title = config.window.title.clone();
 
-    let mut state = Renderer::new(&window).await;
+    let mut state = Renderer::with_config(&window, config).await;
+    let mut last_render_time = Instant::now();
+    let mut last_title_update = Instant::now();
+    // Toggled with F6
+    let mut security_camera: Option<SecurityCamera> = None;
 
-    event_loop.run(move |event, _, control_flow| match event {
+    event_loop.run(move |event, target, control_flow| match event {
+        Event::DeviceEvent {
+            event: DeviceEvent::MouseMotion { delta },
+            ..
+        } => state.mouse_motion(delta.0, delta.1),
         Event::WindowEvent {
             ref event,
             window_id,
-        } => {
-            if window_id == window.id() && !state.input(event) {
-                match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
+        } if window_id == window.id() && !state.input(event) => match event {
+            // Escape pauses, and Q quits from the pause menu
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Q),
                         ..
-                    } => *control_flow = ControlFlow::Exit,
-                    WindowEvent::Resized(size) => {
-                        state.resize(*size);
-                    }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        state.resize(**new_inner_size);
+                    },
+                ..
+            } if state.input_state() == InputState::Paused => {
+                exit(&state, &window, save_settings, control_flow)
+            }
+            WindowEvent::CloseRequested => exit(&state, &window, save_settings, control_flow),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F11),
+                        ..
+                    },
+                ..
+            } => toggle_fullscreen(&window),
+            #[cfg(feature = "clipboard")]
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F2),
+                        ..
+                    },
+                ..
+            } => match state.screenshot_to_clipboard() {
+                Ok(()) => tracing::info!("copied a screenshot to the clipboard"),
+                Err(e) => tracing::error!("failed to copy a screenshot to the clipboard: {e}"),
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F6),
+                        ..
+                    },
+                ..
+            } => {
+                security_camera = match security_camera.take() {
+                    Some(camera) => {
+                        state.remove_viewport(camera.viewport);
+                        None
                     }
-                    _ => {}
+                    None => SecurityCamera::open(&mut state, target),
+                };
+            }
+            // The game was paused while unfocused, so don't count that time
+            WindowEvent::Focused(true) => last_render_time = Instant::now(),
+            WindowEvent::Resized(size) => {
+                state.resize(*size);
+            }
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                state.resize(**new_inner_size);
+            }
+            _ => {}
+        },
+        Event::WindowEvent {
+            ref event,
+            window_id,
+        } if security_camera
+            .as_ref()
+            .is_some_and(|camera| camera.window.id() == window_id) =>
+        {
+            let viewport = security_camera.as_ref().unwrap().viewport;
+            match event {
+                WindowEvent::CloseRequested => {
+                    state.remove_viewport(viewport);
+                    security_camera = None;
                 }
+                WindowEvent::Resized(size) => state.resize_viewport(viewport, *size),
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    state.resize_viewport(viewport, **new_inner_size)
+                }
+                _ => {}
+            }
+        }
+        Event::RedrawRequested(window_id)
+            if security_camera
+                .as_ref()
+                .is_some_and(|camera| camera.window.id() == window_id) =>
+        {
+            let viewport = security_camera.as_ref().unwrap().viewport;
+            if let Err(e) = state.render_viewport(viewport) {
+                eprintln!("{:?}", e);
             }
         }
         Event::RedrawRequested(window_id) if window_id == window.id() => {
-            state.update();
+            state.wait_for_next_frame();
+
+            let now = Instant::now();
+            state.update(now - last_render_time);
+            last_render_time = now;
+
+            if now - last_title_update >= TITLE_UPDATE_INTERVAL {
+                let pos = state.camera_position();
+                window.set_title(&format!(
+                    "{} — {:.0} fps — ({:.1}, {:.1}, {:.1})",
+                    title,
+                    state.stats().fps(),
+                    pos.x,
+                    pos.y,
+                    pos.z
+                ));
+                last_title_update = now;
+            }
+
             match state.render() {
                 Ok(_) => {}
-                // Reconfigure the surface if lost
-                Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+                // The surface may have been destroyed along with what it showed on
+                Err(wgpu::SurfaceError::Lost) => {
+                    // SAFETY: the window lives as long as the renderer, in this closure
+                    unsafe { state.recreate_surface(&window) };
+                }
+                // Reconfigure the surface if it no longer matches the window
+                Err(wgpu::SurfaceError::Outdated) => state.reconfigure(),
                 // The system is out of memory, we should probably quit
                 Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                // All other errors (Outdated, Timeout) should be resolved by the next frame
+                // All other errors (Timeout) should be resolved by the next frame
                 Err(e) => eprintln!("{:?}", e),
             }
         }
+        // Surfaces must not outlive the native window, which may be destroyed
+        // while the app is in the background
+        Event::Suspended => state.release_surface(),
+        Event::Resumed if !state.has_surface() => {
+            // SAFETY: the window lives as long as the renderer, in this closure
+            unsafe { state.recreate_surface(&window) };
+        }
         Event::MainEventsCleared => {
+            // Sleep until the next event while unfocused, rather than
+            // rendering frames nobody is playing
+            if !state.is_focused() {
+                *control_flow = ControlFlow::Wait;
+                return;
+            }
+            *control_flow = ControlFlow::Poll;
+
             // RedrawRequested will only trigger once, unless we manually
             // request it.
             window.request_redraw();
+            if let Some(camera) = &security_camera {
+                camera.window.request_redraw();
+            }
         }
         _ => {}
     })
@@ -66,10 +278,22 @@ async fn run() -> ! {
 
 #[tokio::main]
 async fn main() {
+    let options = match LaunchOptions::parse(std::env::args().skip(1)) {
+        Ok(options) if options.help => {
+            println!("{USAGE}");
+            return;
+        }
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
     tracing_subscriber::fmt()
         .with_timer(tracing_subscriber::fmt::time::uptime())
         .with_level(true)
         .init();
 
-    run().await;
+    run(options).await;
 }
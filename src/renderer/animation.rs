@@ -0,0 +1,133 @@
+//! Block textures cycling through frames over time, like flowing lava.
+
+use image::{DynamicImage, GenericImageView};
+
+use super::types::{TextureAnimation, TextureAnimations, MAX_TEXTURE_LAYERS};
+
+/// A texture made of square frames, shown one after the other.
+///
+/// Frames are stored in consecutive layers of the block texture array. The
+/// shader picks the layer of the current frame from the time.
+#[derive(Debug, Clone)]
+pub struct AnimatedTexture {
+    frames: Vec<DynamicImage>,
+    /// Frames shown per second.
+    fps: f32,
+}
+
+impl AnimatedTexture {
+    /// Split a tall strip into square frames, stacked from top to bottom.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the height of the strip isn't a multiple of its width.
+    pub fn from_strip(strip: &DynamicImage, fps: f32) -> Self {
+        let (width, height) = strip.dimensions();
+        assert!(
+            width > 0 && height % width == 0,
+            "animation strip must be a whole number of square frames"
+        );
+
+        let frames = (0..height / width)
+            .map(|i| strip.crop_imm(0, i * width, width, width))
+            .collect();
+        Self { frames, fps }
+    }
+
+    /// The frames, in order.
+    #[inline]
+    pub fn frames(&self) -> &[DynamicImage] {
+        &self.frames
+    }
+
+    /// Number of frames.
+    #[inline]
+    pub fn frame_count(&self) -> u32 {
+        self.frames.len() as u32
+    }
+
+    /// Frames shown per second.
+    #[inline]
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+/// Get the uniform data animating `textures`, whose frames are stored in order
+/// from `first_layer` onwards.
+///
+/// Blocks refer to an animated texture by the layer of its first frame.
+///
+/// ## Panics
+///
+/// Panics if the frames don't fit in [`MAX_TEXTURE_LAYERS`].
+pub fn texture_animations(first_layer: u32, textures: &[AnimatedTexture]) -> TextureAnimations {
+    let mut uniform: TextureAnimations = bytemuck::Zeroable::zeroed();
+    let mut layer = first_layer as usize;
+    for texture in textures {
+        assert!(
+            layer + texture.frames.len() <= MAX_TEXTURE_LAYERS,
+            "too many texture layers"
+        );
+        uniform.layers[layer] = TextureAnimation {
+            frames: texture.frame_count(),
+            fps: texture.fps,
+            _padding: [0; 2],
+        };
+        layer += texture.frames.len();
+    }
+    uniform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::mesher;
+
+    /// A strip of `frames` square frames of `size` pixels, each a different shade.
+    fn strip(size: u32, frames: u32) -> DynamicImage {
+        let image = image::RgbaImage::from_fn(size, size * frames, |_, y| {
+            image::Rgba([(y / size) as u8, 0, 0, 255])
+        });
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn splits_strips_into_square_frames() {
+        let texture = AnimatedTexture::from_strip(&strip(16, 6), 4.0);
+        assert_eq!(texture.frame_count(), 6);
+        for (i, frame) in texture.frames().iter().enumerate() {
+            assert_eq!(frame.dimensions(), (16, 16));
+            assert_eq!(frame.get_pixel(0, 0).0, [i as u8, 0, 0, 255]);
+            assert_eq!(frame.get_pixel(15, 15).0, [i as u8, 0, 0, 255]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "whole number of square frames")]
+    fn rejects_partial_frames() {
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::new(16, 40));
+        AnimatedTexture::from_strip(&image, 4.0);
+    }
+
+    #[test]
+    fn animations_start_after_the_atlas_tiles() {
+        let textures = [
+            AnimatedTexture::from_strip(&strip(16, 6), 4.0),
+            AnimatedTexture::from_strip(&strip(16, 2), 1.0),
+        ];
+        let first = mesher::ANIMATED_LAYERS as usize;
+        assert_eq!(first, (mesher::ATLAS_TILES * mesher::ATLAS_TILES) as usize);
+
+        let uniform = texture_animations(first as u32, &textures);
+        let animated: Vec<_> = uniform
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.frames > 0)
+            .map(|(i, layer)| (i, layer.frames, layer.fps))
+            .collect();
+        // The second texture's frames follow all of the first's
+        assert_eq!(animated, [(first, 6, 4.0), (first + 6, 2, 1.0)]);
+    }
+}
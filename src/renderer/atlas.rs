@@ -0,0 +1,200 @@
+//! Assembling block textures into the layers of a texture array.
+
+use std::fmt;
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+
+/// Error adding an image to an [`AtlasBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AtlasError {
+    /// A tile isn't the same size as the tiles before it.
+    SizeMismatch {
+        /// Where the tile came from, like a file name.
+        source: String,
+        /// Layer the tile would have been stored at.
+        layer: u32,
+        /// Pixel format the tile was decoded as, before conversion.
+        color: image::ColorType,
+        found: (u32, u32),
+        expected: (u32, u32),
+    },
+    /// An atlas can't be split evenly into tiles.
+    UnevenGrid {
+        source: String,
+        size: (u32, u32),
+        /// Tiles per row and column.
+        tiles: u32,
+    },
+}
+
+impl fmt::Display for AtlasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SizeMismatch {
+                source,
+                layer,
+                color,
+                found: (w, h),
+                expected: (ew, eh),
+            } => write!(
+                f,
+                "texture layer {layer} from {source} is {w}x{h} ({color:?}), \
+                 but earlier layers are {ew}x{eh}"
+            ),
+            Self::UnevenGrid {
+                source,
+                size: (w, h),
+                tiles,
+            } => write!(
+                f,
+                "atlas {source} is {w}x{h}, which doesn't split into a square grid \
+                 of {tiles}x{tiles} tiles"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AtlasError {}
+
+/// Tiles of the block texture array, normalized to RGBA8.
+///
+/// Images are converted from whatever format they were decoded as, like
+/// grayscale or 16 bits per channel, so that every layer can be uploaded the
+/// same way. All tiles must be the same size as the first one.
+#[derive(Debug, Default)]
+pub struct AtlasBuilder {
+    tiles: Vec<RgbaImage>,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an image as the next layer, returning its layer.
+    pub fn push(&mut self, source: &str, image: &DynamicImage) -> Result<u32, AtlasError> {
+        let layer = self.len();
+        let found = image.dimensions();
+        if let Some(expected) = self.tile_size() {
+            if found != expected {
+                return Err(AtlasError::SizeMismatch {
+                    source: source.to_owned(),
+                    layer,
+                    color: image.color(),
+                    found,
+                    expected,
+                });
+            }
+        }
+
+        self.tiles.push(image.to_rgba8());
+        Ok(layer)
+    }
+
+    /// Add images as consecutive layers, returning the layer of the first one.
+    pub fn push_all<'a>(
+        &mut self,
+        source: &str,
+        images: impl IntoIterator<Item = &'a DynamicImage>,
+    ) -> Result<u32, AtlasError> {
+        let first = self.len();
+        for image in images {
+            self.push(source, image)?;
+        }
+        Ok(first)
+    }
+
+    /// Split an atlas into a square grid of `tiles` by `tiles`, and add them
+    /// row by row, returning the layer of the first one.
+    pub fn push_grid(
+        &mut self,
+        source: &str,
+        atlas: &DynamicImage,
+        tiles: u32,
+    ) -> Result<u32, AtlasError> {
+        let (width, height) = atlas.dimensions();
+        if width != height || width % tiles != 0 || width == 0 {
+            return Err(AtlasError::UnevenGrid {
+                source: source.to_owned(),
+                size: (width, height),
+                tiles,
+            });
+        }
+
+        let size = width / tiles;
+        let first = self.len();
+        for i in 0..tiles * tiles {
+            let tile = atlas.crop_imm((i % tiles) * size, (i / tiles) * size, size, size);
+            self.push(source, &tile)?;
+        }
+        Ok(first)
+    }
+
+    /// Size of every tile, or `None` before the first one is added.
+    pub fn tile_size(&self) -> Option<(u32, u32)> {
+        self.tiles.first().map(|t| t.dimensions())
+    }
+
+    /// Number of layers added so far.
+    pub fn len(&self) -> u32 {
+        self.tiles.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn tiles(&self) -> &[RgbaImage] {
+        &self.tiles
+    }
+
+    pub fn into_tiles(self) -> Vec<RgbaImage> {
+        self.tiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_formats_to_rgba8() {
+        let mut atlas = AtlasBuilder::new();
+        let gray = DynamicImage::ImageLuma8(image::GrayImage::from_pixel(2, 2, image::Luma([7])));
+        let rgb = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(2, 2, image::Rgb([1, 2, 3])));
+
+        assert_eq!(atlas.push("gray", &gray), Ok(0));
+        assert_eq!(atlas.push("rgb", &rgb), Ok(1));
+        assert_eq!(
+            atlas.tiles()[0].get_pixel(0, 0),
+            &image::Rgba([7, 7, 7, 255])
+        );
+        assert_eq!(
+            atlas.tiles()[1].get_pixel(1, 1),
+            &image::Rgba([1, 2, 3, 255])
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_sizes() {
+        let mut atlas = AtlasBuilder::new();
+        let grid = DynamicImage::new_rgba8(8, 8);
+        assert_eq!(atlas.push_grid("atlas", &grid, 2), Ok(0));
+        assert_eq!(atlas.tile_size(), Some((4, 4)));
+
+        let error = atlas
+            .push("frame", &DynamicImage::new_luma_a8(4, 8))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            AtlasError::SizeMismatch {
+                source: "frame".to_owned(),
+                layer: 4,
+                color: image::ColorType::La8,
+                found: (4, 8),
+                expected: (4, 4),
+            }
+        );
+        assert_eq!(atlas.len(), 4);
+    }
+}
@@ -0,0 +1,178 @@
+//! Quads that always face the camera, like dropped items.
+
+use nalgebra_glm as glm;
+
+use super::pipeline::depth_compare;
+use super::shader;
+use super::types::texture::Texture;
+
+/// A quad facing the camera, centered on a point in the world.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BillboardInstance {
+    pub position: [f32; 3],
+    /// Width and height of the quad, in blocks.
+    pub size: f32,
+    /// Layer of the block texture array shown on the quad.
+    pub layer: u32,
+}
+
+impl BillboardInstance {
+    const ATTRS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Uint32];
+
+    pub const BUFFER_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &Self::ATTRS,
+    };
+}
+
+/// Batches billboards and draws them in a single instanced call.
+///
+/// Billboards are queued with [`BillboardRenderer::draw_billboard`], uploaded
+/// with [`BillboardRenderer::prepare`], and discarded after they have been
+/// drawn. Each one is turned towards the camera in the vertex shader, so the
+/// same batch can be drawn from any viewpoint.
+pub struct BillboardRenderer {
+    pipeline: wgpu::RenderPipeline,
+    /// Billboards queued for the current frame.
+    instances: Vec<BillboardInstance>,
+    /// Instance buffer, grown to fit the largest batch seen so far.
+    buffer: wgpu::Buffer,
+    /// Number of instances the buffer can hold.
+    capacity: usize,
+    /// Number of instances uploaded by the last call to `prepare`.
+    uploaded: u32,
+}
+
+impl BillboardRenderer {
+    const INITIAL_CAPACITY: usize = 64;
+
+    /// Create a billboard renderer drawing into targets of the given format.
+    ///
+    /// `diffuse_layout` is the layout of the block textures' bind group,
+    /// `globals_layout` that of the camera's uniforms, and `reversed_z` whether
+    /// its camera uses reversed Z.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        diffuse_layout: &wgpu::BindGroupLayout,
+        globals_layout: &wgpu::BindGroupLayout,
+        reversed_z: bool,
+    ) -> Self {
+        let shader = shader::create(
+            device,
+            "billboard.wgsl",
+            include_str!("../../res/shaders/billboard.wgsl"),
+        );
+
+        // Same group indices as the block pipelines
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Billboard Pipeline Layout"),
+            bind_group_layouts: &[diffuse_layout, globals_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Billboard Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[BillboardInstance::BUFFER_LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // Always facing the camera, so there is no back to cull
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: depth_compare(wgpu::CompareFunction::Less, reversed_z),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            instances: Vec::new(),
+            buffer: Self::create_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            uploaded: 0,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Billboard Instance Buffer"),
+            size: (capacity * std::mem::size_of::<BillboardInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Queue a square billboard of `size` blocks centered on `position`, showing
+    /// a layer of the block texture array.
+    pub fn draw_billboard(&mut self, position: glm::Vec3, layer: u32, size: f32) {
+        self.instances.push(BillboardInstance {
+            position: position.into(),
+            size,
+            layer,
+        });
+    }
+
+    /// Upload queued billboards to the GPU.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.instances.len() > self.capacity {
+            self.capacity = self.instances.len().next_power_of_two();
+            self.buffer = Self::create_buffer(device, self.capacity);
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.instances));
+        self.uploaded = self.instances.len() as u32;
+        self.instances.clear();
+    }
+
+    /// Draw the billboards uploaded by the last call to [`BillboardRenderer::prepare`].
+    pub fn render<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        diffuse: &'a wgpu::BindGroup,
+        globals: &'a wgpu::BindGroup,
+    ) {
+        if self.uploaded == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, diffuse, &[]);
+        render_pass.set_bind_group(1, globals, &[]);
+        render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+        // Four corners per quad, as a triangle strip
+        render_pass.draw(0..4, 0..self.uploaded);
+    }
+}
@@ -0,0 +1,222 @@
+//! Copying an offscreen frame onto the surface, for dynamic resolution.
+
+use super::types::target::{RenderTarget, RenderTargetDescriptor};
+use super::types::texture::Texture;
+
+/// Draws a texture over a whole target, filtering it to the target's size.
+pub struct Blitter {
+    pipeline: wgpu::RenderPipeline,
+    /// Draws into multisampled targets, if MSAA is enabled.
+    msaa_pipeline: Option<wgpu::RenderPipeline>,
+    layout: wgpu::BindGroupLayout,
+}
+
+impl Blitter {
+    /// Create a blitter drawing into targets of the given format, and into
+    /// multisampled ones with `sample_count` samples if it is more than 1.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let shader =
+            device.create_shader_module(wgpu::include_wgsl!("../../res/shaders/blit.wgsl"));
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Blit Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label, count| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count,
+                    ..Default::default()
+                },
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("Blit Pipeline", 1);
+        let msaa_pipeline =
+            (sample_count > 1).then(|| create_pipeline("Blit MSAA Pipeline", sample_count));
+
+        Self {
+            pipeline,
+            msaa_pipeline,
+            layout,
+        }
+    }
+
+    /// Create a bind group sampling `texture`, to blit from.
+    fn bind_group(&self, device: &wgpu::Device, texture: &Texture) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Blit Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(texture.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(texture.sampler()),
+                },
+            ],
+        })
+    }
+
+    /// Draw a frame over the whole of `view`, replacing what was there.
+    pub fn blit(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &ScaledFrame,
+        view: &wgpu::TextureView,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &frame.bind_group, &[]);
+        // A single triangle covering the screen, see `blit.wgsl`
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Draw `source` over the whole of a multisampled `view`, so that drawing
+    /// into it continues from what `source` holds.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the blitter was created without multisampling.
+    pub fn blit_multisampled(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &Texture,
+        view: &wgpu::TextureView,
+    ) {
+        let pipeline = self
+            .msaa_pipeline
+            .as_ref()
+            .expect("blitter was created without multisampling");
+        let bind_group = self.bind_group(device, source);
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit MSAA Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// A color target the world is drawn into at a different resolution than the
+/// surface, before being blitted onto it.
+pub struct ScaledFrame {
+    target: RenderTarget,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ScaledFrame {
+    pub fn new(
+        device: &wgpu::Device,
+        blitter: &Blitter,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let target = RenderTarget::new(
+            device,
+            &RenderTargetDescriptor {
+                label: Some("scaled_frame"),
+                width,
+                height,
+                format,
+                depth: false,
+            },
+        );
+
+        let bind_group = blitter.bind_group(device, target.color());
+
+        Self { target, bind_group }
+    }
+
+    #[inline]
+    pub const fn target(&self) -> &RenderTarget {
+        &self.target
+    }
+}
+
+/// Scale a surface size by `scale`, keeping each dimension at least 1 pixel.
+pub fn scaled_size(width: u32, height: u32, scale: f32) -> (u32, u32) {
+    let scale = |x: u32| ((x as f32 * scale).round() as u32).max(1);
+    (scale(width), scale(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_size_rounds_and_never_vanishes() {
+        assert_eq!(scaled_size(1280, 720, 1.0), (1280, 720));
+        assert_eq!(scaled_size(1279, 719, 0.5), (640, 360));
+        assert_eq!(scaled_size(3, 1, 0.25), (1, 1));
+    }
+}
@@ -0,0 +1,150 @@
+//! Progress of mining the targeted block.
+
+use crate::world::blocks::{self, BlockId};
+
+/// Number of crack textures shown while a block is being broken.
+pub const BREAK_STAGES: u32 = 10;
+
+/// Seconds of mining it takes to break a block of hardness 1.
+pub const SECONDS_PER_HARDNESS: f32 = 1.5;
+
+/// Seconds of mining it takes to break a block, derived from its
+/// [`hardness`](blocks::BlockType::hardness).
+///
+/// Returns `None` for blocks that can't be broken, and ids with no block type.
+pub fn break_time(block: BlockId) -> Option<f32> {
+    blocks::block_type(block)
+        .filter(|ty| ty.is_breakable())
+        .map(|ty| ty.hardness * SECONDS_PER_HARDNESS)
+}
+
+/// Tracks how long a block has been mined for.
+///
+/// Progress is lost when mining stops or moves to another block.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BreakProgress {
+    /// The block being mined, and the seconds it takes to break.
+    block: Option<([i32; 3], f32)>,
+    /// Seconds spent mining `block`.
+    elapsed: f32,
+}
+
+impl BreakProgress {
+    /// Mine `block` for another `dt` seconds, starting over if it wasn't the
+    /// block being mined.
+    ///
+    /// Returns `true` once the block has been mined for `duration` seconds, after
+    /// which progress is reset.
+    pub fn advance(&mut self, block: [i32; 3], duration: f32, dt: f32) -> bool {
+        if self.block.map(|(b, _)| b) != Some(block) {
+            self.block = Some((block, duration));
+            self.elapsed = 0.0;
+        }
+
+        self.elapsed += dt;
+        if self.elapsed >= duration {
+            self.reset();
+            return true;
+        }
+        false
+    }
+
+    /// Mine `block`, whose id is `id`, for another `dt` seconds, taking as long
+    /// as its [`break_time`].
+    ///
+    /// Returns `true` once the block breaks. Blocks that can't be broken never
+    /// do, and mining them loses any progress.
+    pub fn mine(&mut self, block: [i32; 3], id: BlockId, dt: f32) -> bool {
+        match break_time(id) {
+            Some(duration) => self.advance(block, duration, dt),
+            None => {
+                self.reset();
+                false
+            }
+        }
+    }
+
+    /// Stop mining, losing any progress.
+    #[inline]
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// The block being mined, and the crack stage to show on it, from 0 to
+    /// [`BREAK_STAGES`] - 1.
+    pub fn stage(&self) -> Option<([i32; 3], u32)> {
+        let (block, duration) = self.block?;
+        let fraction = if duration > 0.0 {
+            self.elapsed / duration
+        } else {
+            1.0
+        };
+        let stage = (fraction * BREAK_STAGES as f32) as u32;
+        Some((block, stage.min(BREAK_STAGES - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_blocks_take_longer_to_break() {
+        assert_eq!(break_time(BlockId::FLOWER), Some(0.0));
+        assert!(break_time(BlockId::STONE) > break_time(BlockId::DIRT));
+        assert_eq!(break_time(BlockId::WATER), None);
+        assert_eq!(break_time(BlockId::AIR), None);
+        // Solid, but unbreakable
+        assert!(blocks::block_type(BlockId::BEDROCK).unwrap().is_opaque());
+        assert_eq!(break_time(BlockId::BEDROCK), None);
+    }
+
+    #[test]
+    fn advances_through_the_stages_in_order() {
+        let mut progress = BreakProgress::default();
+        let block = [1, 2, 3];
+        let duration = 1.0;
+        let dt = duration / (BREAK_STAGES * 4) as f32;
+
+        let mut stages = Vec::new();
+        while !progress.advance(block, duration, dt) {
+            let (mined, stage) = progress.stage().unwrap();
+            assert_eq!(mined, block);
+            if stages.last() != Some(&stage) {
+                stages.push(stage);
+            }
+        }
+        assert_eq!(stages, (0..BREAK_STAGES).collect::<Vec<_>>());
+        // Progress starts over once the block breaks
+        assert_eq!(progress.stage(), None);
+    }
+
+    #[test]
+    fn releasing_or_changing_target_resets() {
+        let mut progress = BreakProgress::default();
+        for _ in 0..5 {
+            progress.advance([0, 0, 0], 1.0, 0.1);
+        }
+        assert_eq!(progress.stage(), Some(([0, 0, 0], 5)));
+
+        progress.advance([1, 0, 0], 1.0, 0.1);
+        assert_eq!(progress.stage(), Some(([1, 0, 0], 1)));
+
+        progress.reset();
+        assert_eq!(progress.stage(), None);
+        progress.advance([1, 0, 0], 1.0, 0.1);
+        assert_eq!(progress.stage(), Some(([1, 0, 0], 1)));
+    }
+
+    #[test]
+    fn unbreakable_blocks_never_progress() {
+        let mut progress = BreakProgress::default();
+        progress.mine([0, 0, 0], BlockId::DIRT, 0.1);
+        assert!(progress.stage().is_some());
+
+        for _ in 0..1000 {
+            assert!(!progress.mine([0, 0, 0], BlockId::BEDROCK, 1.0));
+            assert_eq!(progress.stage(), None);
+        }
+    }
+}
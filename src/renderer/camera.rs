@@ -0,0 +1,452 @@
+//! Viewpoint into the world.
+
+use nalgebra_glm as glm;
+use winit::event::{ElementState, VirtualKeyCode};
+
+use super::input::{Action, KeyBindings};
+use crate::world::player::Player;
+
+/// How a [`Camera`] projects the world onto the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Distant things look smaller, following the field of view.
+    Perspective,
+    /// Parallel projection without foreshortening, like a map.
+    Orthographic {
+        /// Height of the view in world units. The width follows from the aspect ratio.
+        height: f32,
+    },
+}
+
+/// A camera described by a position and a yaw/pitch orientation.
+pub struct Camera {
+    /// Position of the eye in world space.
+    pub position: glm::Vec3,
+    /// Rotation around the Y axis, in radians.
+    pub yaw: f32,
+    /// Rotation above or below the horizon, in radians.
+    pub pitch: f32,
+    /// Width / height of the surface.
+    pub aspect: f32,
+    /// Distance to the near clipping plane.
+    ///
+    /// Depth precision is concentrated near this plane, so pulling it in makes
+    /// distant geometry more prone to z-fighting.
+    pub znear: f32,
+    /// Distance to the far clipping plane, beyond which nothing is drawn.
+    pub zfar: f32,
+    /// Whether depth goes from 1 at the near plane to 0 at the far plane.
+    ///
+    /// Paired with a floating point depth buffer, this spreads precision evenly
+    /// over distance instead of spending it all close to the camera. Depth
+    /// tests and clears must be flipped to match, see [`super::pipeline::depth_compare`].
+    pub reversed_z: bool,
+    /// Vertical field of view, in degrees.
+    fov: f32,
+    /// Magnification applied on top of the field of view.
+    zoom: f32,
+    pub projection: Projection,
+}
+
+impl Camera {
+    /// Narrowest allowed field of view, in degrees.
+    pub const MIN_FOV: f32 = 10.0;
+    /// Widest allowed field of view, in degrees.
+    pub const MAX_FOV: f32 = 120.0;
+
+    pub fn new(position: glm::Vec3, yaw: f32, pitch: f32, fov: f32, aspect: f32) -> Self {
+        Self {
+            position,
+            yaw,
+            pitch,
+            aspect,
+            znear: 0.1,
+            zfar: 1000.0,
+            reversed_z: false,
+            fov: fov.clamp(Self::MIN_FOV, Self::MAX_FOV),
+            zoom: 1.0,
+            projection: Projection::Perspective,
+        }
+    }
+
+    /// An orthographic camera looking straight down from `position`, with
+    /// north (-Z) at the top.
+    ///
+    /// It sees `radius` units to each side on the shorter axis of the view.
+    pub fn top_down(position: glm::Vec3, radius: f32, aspect: f32) -> Self {
+        Self {
+            projection: Projection::Orthographic {
+                height: radius * 2.0 * aspect.max(1.0) / aspect,
+            },
+            ..Self::new(
+                position,
+                -std::f32::consts::FRAC_PI_2,
+                -std::f32::consts::FRAC_PI_2,
+                Self::MAX_FOV,
+                aspect,
+            )
+        }
+    }
+
+    /// A perspective camera at `eye`, looking towards `target`.
+    ///
+    /// The view is always upright, since the camera has no roll. `up` only
+    /// decides which way the top of the view faces when looking straight up or
+    /// down, where the direction alone leaves the yaw undefined.
+    pub fn look_at(
+        eye: glm::Vec3,
+        target: glm::Vec3,
+        up: glm::Vec3,
+        fov: f32,
+        aspect: f32,
+    ) -> Self {
+        let mut camera = Self::new(eye, 0.0, 0.0, fov, aspect);
+        camera.set_look_at(target, up);
+        camera
+    }
+
+    /// Turn the camera towards `target`, keeping its position.
+    ///
+    /// See [`Camera::look_at`] for the meaning of `up`.
+    pub fn set_look_at(&mut self, target: glm::Vec3, up: glm::Vec3) {
+        let direction = target - self.position;
+        if direction.norm() <= f32::EPSILON {
+            return;
+        }
+        let direction = direction.normalize();
+        self.pitch = direction.y.clamp(-1.0, 1.0).asin();
+
+        let horizontal = glm::vec2(direction.x, direction.z);
+        self.yaw = if horizontal.norm() > 1e-6 {
+            direction.z.atan2(direction.x)
+        } else if direction.y < 0.0 {
+            // Looking down, the top of the view faces along the yaw
+            up.z.atan2(up.x)
+        } else {
+            // Looking up, it faces away from it
+            (-up.z).atan2(-up.x)
+        };
+    }
+
+    /// Get the configured vertical field of view, in degrees.
+    #[inline]
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    /// Set the vertical field of view, in degrees.
+    ///
+    /// The value is clamped to [`Camera::MIN_FOV`]..=[`Camera::MAX_FOV`].
+    #[inline]
+    pub fn set_fov(&mut self, degrees: f32) {
+        self.fov = degrees.clamp(Self::MIN_FOV, Self::MAX_FOV);
+    }
+
+    /// Set the magnification applied on top of the field of view.
+    #[inline]
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(1.0);
+    }
+
+    /// Field of view actually used for projection, after zoom is applied.
+    #[inline]
+    pub fn effective_fov(&self) -> f32 {
+        (self.fov / self.zoom).clamp(Self::MIN_FOV, Self::MAX_FOV)
+    }
+
+    /// Update the aspect ratio to match a new surface size.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    /// Unit vector pointing in the direction the camera is looking.
+    pub fn forward(&self) -> glm::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        glm::vec3(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+    }
+
+    /// Unit vector pointing towards the top of the view.
+    ///
+    /// Follows the yaw even when looking straight up or down.
+    pub fn up(&self) -> glm::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let right = glm::vec3(-sin_yaw, 0.0, cos_yaw);
+        right.cross(&self.forward()).normalize()
+    }
+
+    pub fn view_matrix(&self) -> glm::Mat4 {
+        glm::look_at_rh(
+            &self.position,
+            &(self.position + self.forward()),
+            &self.up(),
+        )
+    }
+
+    pub fn projection_matrix(&self) -> glm::Mat4 {
+        // Swapping the planes maps the near one to depth 1 and the far one to 0
+        let (znear, zfar) = if self.reversed_z {
+            (self.zfar, self.znear)
+        } else {
+            (self.znear, self.zfar)
+        };
+
+        match self.projection {
+            Projection::Perspective => {
+                glm::perspective_rh_zo(self.aspect, self.effective_fov().to_radians(), znear, zfar)
+            }
+            Projection::Orthographic { height } => {
+                let (half_width, half_height) = (height * self.aspect / 2.0, height / 2.0);
+                glm::ortho_rh_zo(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    znear,
+                    zfar,
+                )
+            }
+        }
+    }
+
+    /// Combined view and projection transform.
+    pub fn view_projection(&self) -> glm::Mat4 {
+        self.projection_matrix() * self.view_matrix()
+    }
+}
+
+/// Moves a [`Camera`] around in response to user input.
+pub struct CameraController {
+    /// Base movement speed in units per second.
+    pub speed: f32,
+    /// Factor applied to the movement speed while sprinting.
+    pub sprint_multiplier: f32,
+    /// How quickly the velocity converges to its target, per second.
+    ///
+    /// Higher values make movement feel snappier.
+    pub acceleration: f32,
+    /// Rotation speed in radians per unit of raw mouse movement.
+    ///
+    /// Raw movement is counted by the mouse itself rather than in pixels, so
+    /// turning speed doesn't change with the display's scale factor.
+    pub sensitivity: f32,
+    /// Whether moving the mouse up looks down, like a flight stick.
+    pub invert_y: bool,
+    /// Upwards speed of a jump, in units per second.
+    pub jump_speed: f32,
+    /// Magnification applied while the zoom key is held.
+    pub zoom: f32,
+    /// How quickly the zoom converges to its target, per second.
+    pub zoom_rate: f32,
+    /// Keys controlling the camera.
+    pub bindings: KeyBindings,
+    forward: f32,
+    backward: f32,
+    left: f32,
+    right: f32,
+    up: f32,
+    down: f32,
+    sprinting: bool,
+    velocity: glm::Vec3,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    zooming: bool,
+    current_zoom: f32,
+}
+
+impl CameraController {
+    /// Default rotation speed in radians per unit of raw mouse movement.
+    ///
+    /// Sweeping the mouse across a 1080p screen turns the view by about 90°.
+    pub const DEFAULT_SENSITIVITY: f32 = 0.0008;
+
+    pub fn new(speed: f32, sensitivity: f32) -> Self {
+        Self {
+            speed,
+            sprint_multiplier: 2.0,
+            acceleration: 10.0,
+            sensitivity,
+            invert_y: false,
+            jump_speed: 9.0,
+            zoom: 4.0,
+            zoom_rate: 12.0,
+            bindings: KeyBindings::default(),
+            forward: 0.0,
+            backward: 0.0,
+            left: 0.0,
+            right: 0.0,
+            up: 0.0,
+            down: 0.0,
+            sprinting: false,
+            velocity: glm::Vec3::zeros(),
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            zooming: false,
+            current_zoom: 1.0,
+        }
+    }
+
+    /// Handle a key press or release.
+    ///
+    /// Returns `true` if the key was consumed by the controller.
+    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+        let amount = match state {
+            ElementState::Pressed => 1.0,
+            ElementState::Released => 0.0,
+        };
+        let action = match self.bindings.action(key) {
+            Some(action) => action,
+            None => return false,
+        };
+        match action {
+            Action::Forward => self.forward = amount,
+            Action::Back => self.backward = amount,
+            Action::Left => self.left = amount,
+            Action::Right => self.right = amount,
+            Action::Up => self.up = amount,
+            Action::Down => self.down = amount,
+            Action::Sprint => self.sprinting = state == ElementState::Pressed,
+            Action::Zoom => self.zooming = state == ElementState::Pressed,
+            // Handled by the renderer
+            Action::ToggleFly => return false,
+        }
+        true
+    }
+
+    /// Set the rotation speed in radians per unit of raw mouse movement.
+    ///
+    /// Negative values are treated as zero.
+    #[inline]
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity.max(0.0);
+    }
+
+    /// Set whether moving the mouse up looks down.
+    #[inline]
+    pub fn set_invert_y(&mut self, invert_y: bool) {
+        self.invert_y = invert_y;
+    }
+
+    /// Forget held keys and pending mouse movement.
+    ///
+    /// Used when input stops reaching the controller, so that keys released in
+    /// the meantime don't stay held.
+    pub fn release_all(&mut self) {
+        self.forward = 0.0;
+        self.backward = 0.0;
+        self.left = 0.0;
+        self.right = 0.0;
+        self.up = 0.0;
+        self.down = 0.0;
+        self.sprinting = false;
+        self.zooming = false;
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+    }
+
+    /// Stop moving and zooming, as if the camera had been standing still.
+    pub fn reset(&mut self) {
+        self.release_all();
+        self.velocity = glm::Vec3::zeros();
+        self.current_zoom = 1.0;
+    }
+
+    /// Handle relative mouse movement.
+    pub fn process_mouse(&mut self, dx: f64, dy: f64) {
+        self.rotate_horizontal += dx as f32;
+        self.rotate_vertical += dy as f32;
+    }
+
+    /// Fly the camera freely according to the accumulated input.
+    ///
+    /// `dt` is the time elapsed since the last update, in seconds.
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        let direction =
+            self.horizontal_direction(camera.yaw) + glm::Vec3::y() * (self.up - self.down);
+
+        // Ease towards the target velocity so movement doesn't start and stop instantly
+        let target = direction * self.current_speed();
+        self.velocity += (target - self.velocity) * (1.0 - (-self.acceleration * dt).exp());
+        camera.position += self.velocity * dt;
+
+        self.update_view(camera, dt);
+    }
+
+    /// Walk a player according to the accumulated input, facing along `yaw`.
+    ///
+    /// Only sets the player's horizontal velocity; the caller is responsible for
+    /// stepping the player's physics, looking around with
+    /// [`CameraController::update_view`] and moving the camera to its eyes.
+    pub fn steer_player(&mut self, player: &mut Player, yaw: f32, dt: f32) {
+        let target = self.horizontal_direction(yaw) * self.current_speed();
+        let current = glm::vec3(player.velocity.x, 0.0, player.velocity.z);
+        let velocity = current + (target - current) * (1.0 - (-self.acceleration * dt).exp());
+        player.velocity.x = velocity.x;
+        player.velocity.z = velocity.z;
+
+        if self.up > 0.0 {
+            player.jump(self.jump_speed);
+        }
+
+        // Carry momentum over if switching back to flying
+        self.velocity = player.velocity;
+    }
+
+    /// Direction of horizontal movement, regardless of pitch.
+    fn horizontal_direction(&self, yaw: f32) -> glm::Vec3 {
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let forward = glm::vec3(cos_yaw, 0.0, sin_yaw);
+        let right = glm::vec3(-sin_yaw, 0.0, cos_yaw);
+
+        forward * (self.forward - self.backward) + right * (self.right - self.left)
+    }
+
+    /// Movement speed, accounting for sprinting.
+    fn current_speed(&self) -> f32 {
+        if self.sprinting {
+            self.speed * self.sprint_multiplier
+        } else {
+            self.speed
+        }
+    }
+
+    /// Apply accumulated rotation and zoom to the camera.
+    pub fn update_view(&mut self, camera: &mut Camera, dt: f32) {
+        camera.yaw += self.rotate_horizontal * self.sensitivity;
+        let vertical = if self.invert_y { -1.0 } else { 1.0 };
+        camera.pitch -= self.rotate_vertical * self.sensitivity * vertical;
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+
+        // Keep the camera from flipping over
+        const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.001;
+        camera.pitch = camera.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+
+        // Ease towards the target zoom so it doesn't snap in and out
+        let target = if self.zooming { self.zoom } else { 1.0 };
+        self.current_zoom += (target - self.current_zoom) * (1.0 - (-self.zoom_rate * dt).exp());
+        camera.set_zoom(self.current_zoom);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: glm::Vec3, b: glm::Vec3) {
+        assert!((a - b).norm() < 1e-5, "{a} != {b}");
+    }
+
+    #[test]
+    fn look_at_faces_the_target() {
+        let eye = glm::vec3(1.0, 2.0, 3.0);
+        let target = glm::vec3(-4.0, 0.0, 7.0);
+        let camera = Camera::look_at(eye, target, glm::Vec3::y(), 70.0, 1.0);
+        assert_near(camera.forward(), (target - eye).normalize());
+
+        // Straight down, with north at the top like the minimap
+        let camera = Camera::look_at(eye, eye - glm::Vec3::y(), -glm::Vec3::z(), 70.0, 1.0);
+        assert_near(camera.forward(), -glm::Vec3::y());
+        assert_near(camera.up(), -glm::Vec3::z());
+    }
+}
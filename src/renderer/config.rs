@@ -0,0 +1,481 @@
+//! Renderer settings.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::camera::CameraController;
+use super::input::KeyBindings;
+use crate::world::seed::WorldSeed;
+
+/// Tunable settings for the [`Renderer`](super::Renderer).
+///
+/// Can be stored in a TOML file with [`RendererConfig::save`]. Settings missing
+/// from a file keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RendererConfig {
+    /// Which kind of adapter to prefer.
+    ///
+    /// Can be changed while running with
+    /// [`Renderer::set_power_preference`](super::Renderer::set_power_preference).
+    #[serde(with = "PowerPreferenceDef")]
+    pub power_preference: wgpu::PowerPreference,
+    /// Graphics APIs adapters may be picked from, stored as a list of names like
+    /// `["vulkan", "metal", "dx12", "dx11", "gl"]`.
+    ///
+    /// Overridden by the `WGPU_BACKEND` environment variable, a comma-separated
+    /// list of the same names. Forcing a backend can work around driver bugs in
+    /// another.
+    #[serde(with = "backend_names")]
+    pub backends: wgpu::Backends,
+    /// Optional device features to enable where the adapter supports them.
+    ///
+    /// Check [`Renderer::features`](super::Renderer::features) for the ones that
+    /// were actually enabled. Stored as the raw bits of [`wgpu::Features`].
+    #[serde(with = "features_bits")]
+    pub features: wgpu::Features,
+    /// How frames are presented to the surface.
+    #[serde(with = "PresentModeDef")]
+    pub present_mode: wgpu::PresentMode,
+    /// Upper limit on frames rendered per second, or `None` for no limit.
+    ///
+    /// [`wgpu::PresentMode::Fifo`] already limits frames to the display's refresh
+    /// rate, but other present modes render as fast as possible without this.
+    pub max_fps: Option<u32>,
+    /// Number of samples per pixel. Values above 1 enable MSAA.
+    pub sample_count: u32,
+    /// Whether to derive MSAA coverage from the alpha of block textures.
+    ///
+    /// Gives cutout textures like plants and leaves smooth edges. Only has an
+    /// effect when `sample_count` is above 1.
+    pub alpha_to_coverage: bool,
+    /// Color the frame is cleared to before drawing, during the day.
+    ///
+    /// It darkens towards night with the day/night cycle.
+    #[serde(with = "ColorDef")]
+    pub clear_color: wgpu::Color,
+    /// Number of chunks loaded on each side of the origin.
+    pub render_distance: u32,
+    /// Number of chunks stacked in each column of the world.
+    ///
+    /// Terrain stays within the bottom chunk, leaving the ones above to build in.
+    pub world_height: u32,
+    /// Most chunk meshes uploaded to the GPU per frame.
+    ///
+    /// Meshes finished beyond this wait for later frames, so loading many chunks
+    /// at once doesn't stall a single frame.
+    pub mesh_uploads_per_frame: usize,
+    /// Seed the world is generated from.
+    pub seed: WorldSeed,
+    /// Whether to render opaque geometry to the depth buffer before shading it.
+    ///
+    /// Each visible pixel is then shaded only once, which saves fragment work
+    /// in scenes with a lot of overdraw at the cost of drawing everything twice.
+    pub depth_prepass: bool,
+    /// Normal map laid out like the block texture atlas.
+    ///
+    /// Normals are stored in tangent space, with green pointing up the texture.
+    /// Blocks are lit with flat faces when this is `None` or fails to load.
+    pub normal_map: Option<PathBuf>,
+    /// Mip levels added when sampling block textures.
+    ///
+    /// Positive values blur distant terrain slightly, which reduces the shimmer
+    /// of pixel art far away.
+    pub mip_bias: f32,
+    /// Steps per second the player's movement and physics are simulated in.
+    ///
+    /// The camera is interpolated between steps, so motion stays smooth at
+    /// frame rates above this.
+    pub tick_rate: u32,
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+    /// Distance to the near clipping plane.
+    pub znear: f32,
+    /// Distance to the far clipping plane, beyond which nothing is drawn.
+    pub zfar: f32,
+    /// Whether depth is stored reversed, from 1 at the near plane to 0 at the far one.
+    ///
+    /// The standard depth range spends most of its precision right in front of
+    /// the camera, so distant chunks z-fight and flicker at large render
+    /// distances unless the near plane is pushed out. Reversed, the precision of
+    /// the floating point depth buffer is spread evenly over distance instead,
+    /// at no cost on the GPU. It only changes how depth is encoded, so anything
+    /// reading depth back must account for it.
+    pub reversed_z: bool,
+    /// Mouse look speed in radians per unit of raw mouse movement, which doesn't
+    /// depend on the display's scale factor.
+    pub mouse_sensitivity: f32,
+    /// Whether moving the mouse up looks down.
+    pub invert_y: bool,
+    /// Keys controlling the camera.
+    pub key_bindings: KeyBindings,
+    /// Size of overlay elements like the crosshair, on top of the window's scale factor.
+    pub ui_scale: f32,
+    /// Resolution the world is rendered at, relative to the window.
+    ///
+    /// Below 1 the world is drawn at a lower resolution and stretched over the
+    /// window, trading sharpness for speed on slow GPUs. Above 1 it is
+    /// supersampled. Clamped between [`Renderer::MIN_RENDER_SCALE`] and
+    /// [`Renderer::MAX_RENDER_SCALE`].
+    ///
+    /// [`Renderer::MIN_RENDER_SCALE`]: super::Renderer::MIN_RENDER_SCALE
+    /// [`Renderer::MAX_RENDER_SCALE`]: super::Renderer::MAX_RENDER_SCALE
+    pub render_scale: f32,
+    /// Fraction of daylight reaching faces turned away from the sun, from 0
+    /// to 1.
+    ///
+    /// Keeps the unlit sides of blocks from going black. It dims along with the
+    /// sun at night, and doesn't reach faces the sky can't, like in caves.
+    pub ambient: f32,
+    /// How the window is opened.
+    pub window: WindowConfig,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            backends: wgpu::Backends::all(),
+            // Batches fall back to one draw per mesh, frames simply go untimed
+            // without timestamp queries, and hidden chunks are drawn without
+            // pipeline statistics queries
+            features: wgpu::Features::MULTI_DRAW_INDIRECT
+                | wgpu::Features::TIMESTAMP_QUERY
+                | wgpu::Features::PIPELINE_STATISTICS_QUERY,
+            present_mode: wgpu::PresentMode::Fifo,
+            max_fps: None,
+            sample_count: 1,
+            alpha_to_coverage: true,
+            clear_color: wgpu::Color {
+                r: 0.09,
+                g: 0.03,
+                b: 0.01,
+                a: 1.00,
+            },
+            render_distance: 2,
+            world_height: 1,
+            mesh_uploads_per_frame: 8,
+            seed: WorldSeed::default(),
+            depth_prepass: false,
+            normal_map: None,
+            mip_bias: 0.0,
+            tick_rate: 60,
+            fov: 70.0,
+            znear: 0.1,
+            zfar: 1000.0,
+            reversed_z: false,
+            mouse_sensitivity: CameraController::DEFAULT_SENSITIVITY,
+            invert_y: false,
+            key_bindings: KeyBindings::default(),
+            ui_scale: 1.0,
+            render_scale: 1.0,
+            ambient: 0.2,
+            window: WindowConfig::default(),
+        }
+    }
+}
+
+/// How the window is presented on the desktop.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WindowMode {
+    /// A normal window of the configured size.
+    #[default]
+    Windowed,
+    /// A window filling the desktop, leaving its decorations and the taskbar.
+    Maximized,
+    /// A borderless window covering the whole monitor.
+    Fullscreen,
+}
+
+/// Settings for the window the game is shown in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Width of the window's contents when windowed, in logical pixels.
+    pub width: u32,
+    /// Height of the window's contents when windowed, in logical pixels.
+    pub height: u32,
+    pub title: String,
+    /// How the window is opened. Fullscreen can be toggled with F11 at runtime.
+    pub mode: WindowMode,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            title: "mixcraft".to_owned(),
+            mode: WindowMode::Windowed,
+        }
+    }
+}
+
+impl RendererConfig {
+    /// Load settings from a TOML file.
+    ///
+    /// If the file doesn't exist, it is created with the default settings. If it
+    /// can't be read or parsed, a warning is logged and the defaults are used.
+    /// Settings out of their valid range are reset with
+    /// [`RendererConfig::validate`].
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let config = Self::default();
+                if let Err(e) = config.save(path) {
+                    tracing::warn!("failed to create config file {}: {e}", path.display());
+                }
+                return config;
+            }
+            Err(e) => {
+                tracing::warn!("failed to read config file {}: {e}", path.display());
+                return Self::default();
+            }
+        };
+
+        let mut config: Self = toml::from_str(&text).unwrap_or_else(|e| {
+            tracing::warn!(
+                "invalid config file {}, using defaults: {e}",
+                path.display()
+            );
+            Self::default()
+        });
+        config.validate();
+        config
+    }
+
+    /// Reset settings outside their valid range to their defaults, logging a
+    /// warning for each, and clamp [`RendererConfig::ambient`] to `0..=1`.
+    ///
+    /// Creating a [`Renderer`](super::Renderer) validates its settings too.
+    pub fn validate(&mut self) {
+        let defaults = Self::default();
+
+        // Only 1 and 4 samples are guaranteed to work on every adapter
+        reset_invalid(
+            "sample_count",
+            &mut self.sample_count,
+            defaults.sample_count,
+            |&n| n == 1 || n == 4,
+        );
+        reset_invalid(
+            "render_distance",
+            &mut self.render_distance,
+            defaults.render_distance,
+            |&n| n > 0,
+        );
+        reset_invalid(
+            "world_height",
+            &mut self.world_height,
+            defaults.world_height,
+            |&n| n > 0,
+        );
+        reset_invalid(
+            "mesh_uploads_per_frame",
+            &mut self.mesh_uploads_per_frame,
+            defaults.mesh_uploads_per_frame,
+            |&n| n > 0,
+        );
+        reset_invalid("tick_rate", &mut self.tick_rate, defaults.tick_rate, |&n| {
+            n > 0
+        });
+        reset_invalid("fov", &mut self.fov, defaults.fov, |&fov| {
+            fov > 0.0 && fov < 180.0
+        });
+        reset_invalid("znear", &mut self.znear, defaults.znear, |&znear| {
+            znear > 0.0 && znear.is_finite()
+        });
+        let znear = self.znear;
+        reset_invalid("zfar", &mut self.zfar, defaults.zfar, |&zfar| {
+            zfar > znear && zfar.is_finite()
+        });
+        // Still too close if the near plane is beyond the default far plane
+        let zfar = self.zfar;
+        reset_invalid("znear", &mut self.znear, defaults.znear, |&znear| {
+            znear < zfar
+        });
+        reset_invalid(
+            "ui_scale",
+            &mut self.ui_scale,
+            defaults.ui_scale,
+            |&scale| scale > 0.0 && scale.is_finite(),
+        );
+        reset_invalid(
+            "render_scale",
+            &mut self.render_scale,
+            defaults.render_scale,
+            |&scale| scale > 0.0 && scale.is_finite(),
+        );
+        reset_invalid("ambient", &mut self.ambient, defaults.ambient, |ambient| {
+            !ambient.is_nan()
+        });
+        self.ambient = self.ambient.clamp(0.0, 1.0);
+    }
+
+    /// Save settings to a TOML file, replacing it if it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        // Going through a `Value` orders tables after plain values, as TOML requires
+        let text = toml::Value::try_from(self)
+            .and_then(|value| toml::to_string_pretty(&value))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, text)
+    }
+}
+
+/// Replace `value` with `default` if it isn't `valid`, warning about it.
+fn reset_invalid<T: std::fmt::Debug>(
+    name: &str,
+    value: &mut T,
+    default: T,
+    valid: impl FnOnce(&T) -> bool,
+) {
+    if !valid(value) {
+        tracing::warn!("invalid {name} {value:?} in config, using {default:?}");
+        *value = default;
+    }
+}
+
+/// Serde definitions mirroring wgpu types, which aren't serializable themselves.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "wgpu::PowerPreference")]
+enum PowerPreferenceDef {
+    LowPower,
+    HighPerformance,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "wgpu::PresentMode")]
+enum PresentModeDef {
+    AutoVsync,
+    AutoNoVsync,
+    Fifo,
+    FifoRelaxed,
+    Immediate,
+    Mailbox,
+}
+
+/// Stores [`wgpu::Features`] as its bits, ignoring unknown ones.
+mod features_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(features: &wgpu::Features, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(features.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<wgpu::Features, D::Error> {
+        u64::deserialize(d).map(wgpu::Features::from_bits_truncate)
+    }
+}
+
+/// Stores [`wgpu::Backends`] as a list of backend names, ignoring unknown ones.
+mod backend_names {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    const NAMES: [(&str, wgpu::Backends); 6] = [
+        ("vulkan", wgpu::Backends::VULKAN),
+        ("metal", wgpu::Backends::METAL),
+        ("dx12", wgpu::Backends::DX12),
+        ("dx11", wgpu::Backends::DX11),
+        ("gl", wgpu::Backends::GL),
+        ("webgpu", wgpu::Backends::BROWSER_WEBGPU),
+    ];
+
+    pub fn serialize<S: Serializer>(backends: &wgpu::Backends, s: S) -> Result<S::Ok, S::Error> {
+        let names: Vec<&str> = NAMES
+            .iter()
+            .filter(|(_, backend)| backends.contains(*backend))
+            .map(|(name, _)| *name)
+            .collect();
+        names.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<wgpu::Backends, D::Error> {
+        let names = Vec::<String>::deserialize(d)?;
+        Ok(names
+            .iter()
+            .map(|name| wgpu::util::parse_backends_from_comma_list(&name.to_lowercase()))
+            .fold(wgpu::Backends::empty(), |a, b| a | b))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "wgpu::Color")]
+struct ColorDef {
+    r: f64,
+    g: f64,
+    b: f64,
+    a: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ambient_is_clamped_when_loading() {
+        let path =
+            std::env::temp_dir().join(format!("mixcraft-ambient-{}.toml", std::process::id()));
+        for (ambient, expected) in [(3.0, 1.0), (-0.5, 0.0), (0.3, 0.3)] {
+            let config = RendererConfig {
+                ambient,
+                ..Default::default()
+            };
+            config.save(&path).unwrap();
+            assert_eq!(RendererConfig::load(&path).ambient, expected);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_settings_fall_back_to_defaults() {
+        let defaults = RendererConfig::default();
+        for sample_count in [0, 3, 8] {
+            let mut config = RendererConfig {
+                sample_count,
+                ..Default::default()
+            };
+            config.validate();
+            assert_eq!(config.sample_count, defaults.sample_count);
+        }
+
+        let mut config = RendererConfig {
+            sample_count: 4,
+            fov: 180.0,
+            znear: 0.0,
+            zfar: -1.0,
+            ui_scale: 0.0,
+            render_scale: f32::NAN,
+            tick_rate: 0,
+            ..Default::default()
+        };
+        config.validate();
+        assert_eq!(config.sample_count, 4);
+        assert_eq!(config.fov, defaults.fov);
+        assert_eq!(config.znear, defaults.znear);
+        assert_eq!(config.zfar, defaults.zfar);
+        assert_eq!(config.ui_scale, defaults.ui_scale);
+        assert_eq!(config.render_scale, defaults.render_scale);
+        assert_eq!(config.tick_rate, defaults.tick_rate);
+
+        // A near plane beyond the far one resets both
+        let mut config = RendererConfig {
+            znear: 5000.0,
+            zfar: 2000.0,
+            ..Default::default()
+        };
+        config.validate();
+        assert_eq!((config.znear, config.zfar), (defaults.znear, defaults.zfar));
+
+        let mut config = RendererConfig {
+            fov: 90.0,
+            znear: 0.5,
+            zfar: 500.0,
+            ..Default::default()
+        };
+        config.validate();
+        assert_eq!((config.fov, config.znear, config.zfar), (90.0, 0.5, 500.0));
+    }
+}
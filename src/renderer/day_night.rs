@@ -0,0 +1,112 @@
+//! Time of day, moving the sun and tinting the sky.
+
+use std::f32::consts::TAU;
+
+use nalgebra_glm as glm;
+
+/// Sky color at midnight.
+const NIGHT_SKY: wgpu::Color = wgpu::Color {
+    r: 0.01,
+    g: 0.01,
+    b: 0.04,
+    a: 1.0,
+};
+
+/// Sky color while the sun crosses the horizon.
+const DUSK_SKY: wgpu::Color = wgpu::Color {
+    r: 0.85,
+    g: 0.42,
+    b: 0.22,
+    a: 1.0,
+};
+
+/// Advances the time of day, which moves the sun across the sky.
+///
+/// Time runs from 0 to 1 over a day: the sun rises in the east at 0.25, is
+/// highest at 0.5 and sets in the west at 0.75.
+#[derive(Debug, Clone, Copy)]
+pub struct DayNightCycle {
+    time: f32,
+    /// Fraction of a day passing per second.
+    speed: f32,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self {
+            // Start in the morning
+            time: 0.35,
+            speed: Self::DEFAULT_SPEED,
+        }
+    }
+}
+
+impl DayNightCycle {
+    /// A full day every 20 minutes.
+    pub const DEFAULT_SPEED: f32 = 1.0 / 1200.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move time forward by `dt` seconds.
+    #[inline]
+    pub fn advance(&mut self, dt: f32) {
+        self.set_time_of_day(self.time + dt * self.speed);
+    }
+
+    #[inline]
+    pub const fn time_of_day(&self) -> f32 {
+        self.time
+    }
+
+    /// Jump to a time of day, wrapping around outside `0.0..1.0`.
+    #[inline]
+    pub fn set_time_of_day(&mut self, time: f32) {
+        self.time = time.rem_euclid(1.0);
+    }
+
+    /// Fraction of a day passing per second.
+    #[inline]
+    pub const fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Set the fraction of a day passing per second. Zero stops time.
+    #[inline]
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Unit vector pointing towards the sun.
+    pub fn sun_direction(&self) -> glm::Vec3 {
+        let angle = (self.time - 0.25) * TAU;
+        // Tilted slightly south, so the sun never passes straight overhead
+        glm::vec3(angle.cos(), angle.sin(), 0.3).normalize()
+    }
+
+    /// How much sunlight reaches the world, from 0 at night to 1 during the day.
+    pub fn daylight(&self) -> f32 {
+        // Fades over a short while around sunrise and sunset
+        let t = ((self.sun_direction().y + 0.1) / 0.3).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Tint a daytime sky color for the current time of day.
+    pub fn sky_color(&self, day: wgpu::Color) -> wgpu::Color {
+        let sky = lerp_color(NIGHT_SKY, day, self.daylight() as f64);
+        // Strongest while the sun is on the horizon
+        let dusk = (1.0 - self.sun_direction().y.abs() / 0.3).clamp(0.0, 1.0) as f64;
+        lerp_color(sky, DUSK_SKY, dusk * dusk * 0.6)
+    }
+}
+
+#[inline]
+fn lerp_color(a: wgpu::Color, b: wgpu::Color, t: f64) -> wgpu::Color {
+    wgpu::Color {
+        r: a.r + (b.r - a.r) * t,
+        g: a.g + (b.g - a.g) * t,
+        b: a.b + (b.b - a.b) * t,
+        a: a.a + (b.a - a.a) * t,
+    }
+}
@@ -0,0 +1,315 @@
+//! In-game debug overlay.
+
+use std::time::{Duration, Instant};
+
+use nalgebra_glm as glm;
+use winit::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+use super::camera::Camera;
+
+/// Points scrolled per line of mouse wheel movement.
+const SCROLL_LINE_HEIGHT: f32 = 50.0;
+
+/// Read-only values shown by the overlay.
+#[derive(Debug, Clone)]
+pub struct DebugStats {
+    pub fps: f32,
+    pub frame_time: Duration,
+    /// Time the GPU spent drawing the scene, if it can be measured.
+    pub gpu_time: Option<Duration>,
+    /// Part of `gpu_time` spent on the depth prepass, if there is one.
+    pub gpu_prepass_time: Option<Duration>,
+    pub position: glm::Vec3,
+    /// Number of loaded chunks.
+    pub chunks: usize,
+    /// Number of chunk draws issued per pass.
+    pub draws: usize,
+    /// Estimated GPU memory in use, in bytes.
+    pub gpu_memory: usize,
+}
+
+/// Settings that can be tuned from the overlay.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugSettings {
+    pub render_distance: u32,
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+    pub fog_start: f32,
+    pub fog_end: f32,
+    /// Fraction of daylight reaching faces turned away from the sun.
+    pub ambient: f32,
+}
+
+/// An [`egui`] overlay showing statistics and tunable settings.
+///
+/// Toggled with F1.
+pub struct DebugUi {
+    context: egui::Context,
+    renderer: egui_wgpu::renderer::RenderPass,
+    /// Input collected since the last frame.
+    input: egui::RawInput,
+    /// Last known cursor position, in points.
+    pointer: egui::Pos2,
+    modifiers: egui::Modifiers,
+    scale_factor: f32,
+    start: Instant,
+    visible: bool,
+}
+
+impl DebugUi {
+    /// Create an overlay drawing into targets of the given format.
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, scale_factor: f32) -> Self {
+        Self {
+            context: egui::Context::default(),
+            renderer: egui_wgpu::renderer::RenderPass::new(device, format, 1),
+            input: egui::RawInput::default(),
+            pointer: egui::Pos2::ZERO,
+            modifiers: egui::Modifiers::default(),
+            scale_factor,
+            start: Instant::now(),
+            visible: true,
+        }
+    }
+
+    /// Handle a window event.
+    ///
+    /// Returns `true` if the event was consumed by the overlay, and shouldn't
+    /// also control the camera. Releases are never consumed, so that keys and
+    /// buttons pressed before the overlay took focus don't get stuck.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            // Tracked while hidden too, so the overlay isn't scaled wrong when shown
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale_factor = *scale_factor as f32;
+                false
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::F1),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                self.visible = !self.visible;
+                true
+            }
+            _ if !self.visible => false,
+            WindowEvent::ModifiersChanged(state) => {
+                self.modifiers = Self::modifiers(*state);
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer = egui::pos2(
+                    position.x as f32 / self.scale_factor,
+                    position.y as f32 / self.scale_factor,
+                );
+                self.push(egui::Event::PointerMoved(self.pointer));
+                self.context.is_using_pointer()
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.push(egui::Event::PointerGone);
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => egui::PointerButton::Primary,
+                    MouseButton::Right => egui::PointerButton::Secondary,
+                    MouseButton::Middle => egui::PointerButton::Middle,
+                    MouseButton::Other(_) => return false,
+                };
+                let pressed = *state == ElementState::Pressed;
+                self.push(egui::Event::PointerButton {
+                    pos: self.pointer,
+                    button,
+                    pressed,
+                    modifiers: self.modifiers,
+                });
+                pressed && self.context.wants_pointer_input()
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => egui::vec2(*x, *y) * SCROLL_LINE_HEIGHT,
+                    MouseScrollDelta::PixelDelta(p) => {
+                        egui::vec2(p.x as f32, p.y as f32) / self.scale_factor
+                    }
+                };
+                self.push(egui::Event::Scroll(delta));
+                self.context.wants_pointer_input()
+            }
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() => {
+                self.push(egui::Event::Text(c.to_string()));
+                self.context.wants_keyboard_input()
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                if let Some(key) = Self::key(*key) {
+                    self.push(egui::Event::Key {
+                        key,
+                        pressed,
+                        modifiers: self.modifiers,
+                    });
+                }
+                pressed && self.context.wants_keyboard_input()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether relative mouse movement should be ignored by the camera.
+    #[inline]
+    pub fn wants_pointer(&self) -> bool {
+        self.visible && self.context.is_using_pointer()
+    }
+
+    fn push(&mut self, event: egui::Event) {
+        self.input.events.push(event);
+    }
+
+    fn modifiers(state: ModifiersState) -> egui::Modifiers {
+        egui::Modifiers {
+            alt: state.alt(),
+            ctrl: state.ctrl(),
+            shift: state.shift(),
+            mac_cmd: cfg!(target_os = "macos") && state.logo(),
+            command: if cfg!(target_os = "macos") {
+                state.logo()
+            } else {
+                state.ctrl()
+            },
+        }
+    }
+
+    /// Translate the keys used to edit values in the overlay.
+    fn key(key: VirtualKeyCode) -> Option<egui::Key> {
+        use egui::Key;
+        Some(match key {
+            VirtualKeyCode::Left => Key::ArrowLeft,
+            VirtualKeyCode::Right => Key::ArrowRight,
+            VirtualKeyCode::Up => Key::ArrowUp,
+            VirtualKeyCode::Down => Key::ArrowDown,
+            VirtualKeyCode::Escape => Key::Escape,
+            VirtualKeyCode::Tab => Key::Tab,
+            VirtualKeyCode::Back => Key::Backspace,
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Key::Enter,
+            VirtualKeyCode::Space => Key::Space,
+            VirtualKeyCode::Delete => Key::Delete,
+            VirtualKeyCode::Home => Key::Home,
+            VirtualKeyCode::End => Key::End,
+            VirtualKeyCode::A => Key::A,
+            VirtualKeyCode::C => Key::C,
+            VirtualKeyCode::V => Key::V,
+            VirtualKeyCode::X => Key::X,
+            VirtualKeyCode::Z => Key::Z,
+            _ => return None,
+        })
+    }
+
+    /// Lay out the overlay and draw it on top of `view`.
+    ///
+    /// `settings` is updated with any changes made through the overlay.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: [u32; 2],
+        stats: &DebugStats,
+        settings: &mut DebugSettings,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        let mut input = std::mem::take(&mut self.input);
+        input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(size[0] as f32, size[1] as f32) / self.scale_factor,
+        ));
+        input.pixels_per_point = Some(self.scale_factor);
+        input.time = Some(self.start.elapsed().as_secs_f64());
+        input.modifiers = self.modifiers;
+
+        let output = self
+            .context
+            .run(input, |ctx| Self::ui(ctx, stats, settings));
+        let paint_jobs = self.context.tessellate(output.shapes);
+
+        let screen = egui_wgpu::renderer::ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: self.scale_factor,
+        };
+        for (id, delta) in &output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        self.renderer
+            .update_buffers(device, queue, &paint_jobs, &screen);
+        self.renderer
+            .execute(encoder, view, &paint_jobs, &screen, None);
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+
+    fn ui(ctx: &egui::Context, stats: &DebugStats, settings: &mut DebugSettings) {
+        egui::Window::new("Debug")
+            .default_pos(egui::pos2(8.0, 8.0))
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{:.0} fps ({:.2} ms)",
+                    stats.fps,
+                    stats.frame_time.as_secs_f32() * 1000.0
+                ));
+                if let Some(gpu_time) = stats.gpu_time {
+                    ui.label(format!("GPU: {:.2} ms", gpu_time.as_secs_f32() * 1000.0));
+                }
+                if let Some(prepass) = stats.gpu_prepass_time {
+                    ui.label(format!(
+                        "Depth prepass: {:.2} ms",
+                        prepass.as_secs_f32() * 1000.0
+                    ));
+                }
+                ui.label(format!(
+                    "Position: {:.1}, {:.1}, {:.1}",
+                    stats.position.x, stats.position.y, stats.position.z
+                ));
+                ui.label(format!(
+                    "Chunks: {} loaded, {} draws",
+                    stats.chunks, stats.draws
+                ));
+                ui.label(format!(
+                    "GPU memory: {:.1} MiB",
+                    stats.gpu_memory as f64 / (1024.0 * 1024.0)
+                ));
+
+                ui.separator();
+
+                ui.add(
+                    egui::Slider::new(&mut settings.render_distance, 1..=12)
+                        .text("Render distance"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut settings.fov, Camera::MIN_FOV..=Camera::MAX_FOV)
+                        .text("FOV"),
+                );
+                ui.add(egui::Slider::new(&mut settings.fog_start, 0.0..=512.0).text("Fog start"));
+                ui.add(egui::Slider::new(&mut settings.fog_end, 0.0..=512.0).text("Fog end"));
+                ui.add(egui::Slider::new(&mut settings.ambient, 0.0..=1.0).text("Ambient"));
+            });
+    }
+}
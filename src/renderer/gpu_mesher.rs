@@ -0,0 +1,540 @@
+//! Chunk meshing with compute shaders.
+
+use crate::world::{
+    blocks::{self, Face, ModelKind, Tint},
+    chunk::{Chunk, ChunkCoord, CHUNK_SIZE, CHUNK_VOLUME},
+    light::MAX_LIGHT,
+    mesher::Neighbours,
+};
+
+use super::types::{
+    buffer::{Buffer, BufferInitDescriptor},
+    mesh::Mesh,
+    Vertex,
+};
+
+/// Number of blocks visited by each workgroup. Must match the shader.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Number of storage buffers bound while meshing.
+const STORAGE_BUFFERS: u32 = 5;
+
+/// Packed block of a neighbour that isn't loaded: air, open to the sky.
+const UNLOADED_BLOCK: u32 = (MAX_LIGHT as u32) << 20;
+
+/// Bits of [`BlockInfo::flags`].
+const FLAG_PRESENT: u32 = 1;
+const FLAG_CROSS: u32 = 2;
+const FLAG_TINT_TOP: u32 = 4;
+const FLAG_TINT_ALL: u32 = 8;
+
+/// A block type, as seen by the shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlockInfo {
+    top: u32,
+    bottom: u32,
+    sides: u32,
+    flags: u32,
+    emissive: f32,
+}
+
+/// Per-chunk shader parameters.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    /// World-space position of the chunk's minimum corner.
+    origin: [f32; 4],
+}
+
+/// Buffers used while meshing a single chunk.
+struct Job {
+    pos: ChunkCoord,
+    /// Prefix of the labels of everything created for this chunk.
+    label: String,
+    params: Buffer,
+    blocks: Buffer,
+    draw: Buffer,
+}
+
+/// Generates chunk meshes on the GPU.
+///
+/// Produces the same faces as
+/// [`mesher::build_mesh_with_neighbours`](crate::world::mesher::build_mesh_with_neighbours),
+/// though not necessarily in the same order. Meshes are drawn indirectly, with
+/// their index count written by the GPU.
+///
+/// Block states aren't uploaded, so rotated blocks are meshed upright.
+pub struct GpuMesher {
+    count_pipeline: wgpu::ComputePipeline,
+    mesh_pipeline: wgpu::ComputePipeline,
+    /// Every registered block type, indexed by block id.
+    block_types: Buffer,
+}
+
+impl GpuMesher {
+    /// Create a mesher, if the adapter supports compute shaders and indirect drawing.
+    pub fn new(adapter: &wgpu::Adapter, device: &wgpu::Device) -> Option<Self> {
+        let required =
+            wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::INDIRECT_EXECUTION;
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(required)
+            || device.limits().max_storage_buffers_per_shader_stage < STORAGE_BUFFERS
+        {
+            tracing::info!("compute meshing unsupported, using the CPU mesher");
+            return None;
+        }
+
+        let shader =
+            device.create_shader_module(wgpu::include_wgsl!("../../res/shaders/mesher.wgsl"));
+
+        // Layouts are derived from the shader, since the count pass doesn't touch
+        // the output buffers
+        let count_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Mesher Count Pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "count_main",
+        });
+        let mesh_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Mesher Pipeline"),
+            layout: None,
+            module: &shader,
+            entry_point: "mesh_main",
+        });
+
+        let block_types: Vec<_> = blocks::registry()
+            .iter()
+            .map(|block| match block {
+                // Liquids are drawn translucent, from meshes built on the CPU
+                Some(b) if b.model == ModelKind::Liquid => bytemuck::Zeroable::zeroed(),
+                Some(b) => BlockInfo {
+                    top: b.top,
+                    bottom: b.bottom,
+                    sides: b.sides,
+                    flags: FLAG_PRESENT
+                        | match b.model {
+                            ModelKind::Cube => 0,
+                            ModelKind::Cross => FLAG_CROSS,
+                            ModelKind::Liquid => unreachable!(),
+                        }
+                        | match b.tint {
+                            Tint::None => 0,
+                            Tint::Top => FLAG_TINT_TOP,
+                            Tint::All => FLAG_TINT_ALL,
+                        },
+                    emissive: b.emissive,
+                },
+                None => bytemuck::Zeroable::zeroed(),
+            })
+            .collect();
+        let block_types = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some("Mesher Block Types"),
+                usage: wgpu::BufferUsages::STORAGE,
+                contents: &block_types,
+            },
+        );
+
+        Some(Self {
+            count_pipeline,
+            mesh_pipeline,
+            block_types,
+        })
+    }
+
+    /// Mesh a batch of chunks.
+    ///
+    /// Blocks until the GPU has counted the faces of every chunk, so that
+    /// output buffers can be sized exactly. Chunks without any faces are skipped.
+    #[profiling::function]
+    pub fn build_meshes<'a>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        chunks: impl IntoIterator<Item = (ChunkCoord, &'a Chunk, Neighbours<'a>)>,
+    ) -> Vec<Mesh> {
+        let jobs: Vec<_> = chunks
+            .into_iter()
+            .map(|(pos, chunk, neighbours)| Self::create_job(device, pos, chunk, &neighbours))
+            .collect();
+        if jobs.is_empty() {
+            return Vec::new();
+        }
+
+        // Count the faces of every chunk
+        let counts = Buffer::zeroed::<u32>(
+            device,
+            Some("Mesher Counts"),
+            jobs.len() as u32,
+            wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+        );
+        let bind_groups: Vec<_> = jobs
+            .iter()
+            .map(|job| self.count_bind_group(device, job))
+            .collect();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mesher Count Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Mesher Count Pass"),
+            });
+            pass.set_pipeline(&self.count_pipeline);
+            for bind_group in &bind_groups {
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.dispatch_workgroups(CHUNK_VOLUME as u32 / WORKGROUP_SIZE, 1, 1);
+            }
+        }
+        for (i, job) in jobs.iter().enumerate() {
+            // The index count is the first field of the draw arguments
+            encoder.copy_buffer_to_buffer(job.draw.inner(), 0, counts.inner(), i as u64 * 4, 4);
+        }
+        queue.submit([encoder.finish()]);
+        let counts = counts.read_back::<u32>(device, queue);
+
+        // Generate the faces into buffers of the right size
+        let mut outputs = Vec::new();
+        for (job, index_count) in jobs.into_iter().zip(counts) {
+            if index_count == 0 {
+                continue;
+            }
+            // The count pass left its total behind, start over from zero
+            job.draw.write(queue, 0, &[0u32]);
+
+            let vertex_count = index_count / 6 * 4;
+            let vbo = Buffer::zeroed::<Vertex>(
+                device,
+                Some(&format!("{}_vertices", job.label)),
+                vertex_count,
+                wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC,
+            );
+            let ibo = Buffer::zeroed::<u32>(
+                device,
+                Some(&format!("{}_indices", job.label)),
+                index_count,
+                wgpu::BufferUsages::INDEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC,
+            );
+            let bind_group = self.mesh_bind_group(device, &job, &vbo, &ibo);
+            outputs.push((job, vbo, ibo, bind_group));
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mesher Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Mesher Pass"),
+            });
+            pass.set_pipeline(&self.mesh_pipeline);
+            for (_, _, _, bind_group) in &outputs {
+                pass.set_bind_group(0, bind_group, &[]);
+                pass.dispatch_workgroups(CHUNK_VOLUME as u32 / WORKGROUP_SIZE, 1, 1);
+            }
+        }
+        queue.submit([encoder.finish()]);
+
+        outputs
+            .into_iter()
+            .map(|(job, vbo, ibo, _)| {
+                Mesh::from_buffers(vbo, ibo, Some(job.draw), job.pos.bounds())
+            })
+            .collect()
+    }
+
+    /// Upload the inputs for meshing a chunk.
+    fn create_job(
+        device: &wgpu::Device,
+        pos: ChunkCoord,
+        chunk: &Chunk,
+        neighbours: &Neighbours,
+    ) -> Job {
+        let label = format!("gpu_chunk_mesh{pos}");
+        let origin = pos.to_world_origin();
+        let params = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some(&format!("{label}_params")),
+                usage: wgpu::BufferUsages::UNIFORM,
+                contents: &[Params {
+                    origin: [origin.x, origin.y, origin.z, 0.0],
+                }],
+            },
+        );
+
+        // Blocks in the same order as `Chunk::index`, followed by the slice of
+        // each neighbour touching the chunk, ordered like `Face::ALL`
+        let mut ids = Vec::with_capacity(CHUNK_VOLUME + 6 * CHUNK_SIZE * CHUNK_SIZE);
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    ids.push(pack_block(chunk, x, y, z));
+                }
+            }
+        }
+        for face in Face::ALL {
+            let normal = face.normal();
+            let axis = normal.iter().position(|&n| n != 0).unwrap();
+            // The other two axes, in increasing order, index the slice
+            let [u_axis, v_axis] = match axis {
+                0 => [1, 2],
+                1 => [0, 2],
+                _ => [0, 1],
+            };
+            let mut local = [0; 3];
+            local[axis] = if normal[axis] > 0 { 0 } else { CHUNK_SIZE - 1 };
+            for u in 0..CHUNK_SIZE {
+                for v in 0..CHUNK_SIZE {
+                    local[u_axis] = u;
+                    local[v_axis] = v;
+                    let [x, y, z] = local;
+                    ids.push(
+                        neighbours[face as usize]
+                            .map_or(UNLOADED_BLOCK, |n| pack_block(n, x, y, z)),
+                    );
+                }
+            }
+        }
+        let blocks = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some(&format!("{label}_blocks")),
+                usage: wgpu::BufferUsages::STORAGE,
+                contents: &ids,
+            },
+        );
+
+        let draw = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some(&format!("{label}_indirect")),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::INDIRECT
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                // index_count, instance_count, first_index, base_vertex, first_instance
+                contents: &[0u32, 1, 0, 0, 0],
+            },
+        );
+
+        Job {
+            pos,
+            label,
+            params,
+            blocks,
+            draw,
+        }
+    }
+
+    fn count_bind_group(&self, device: &wgpu::Device, job: &Job) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{}_count_group", job.label)),
+            layout: &self.count_pipeline.get_bind_group_layout(0),
+            // Counting doesn't depend on the chunk's position
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: job.blocks.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.block_types.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: job.draw.inner().as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    fn mesh_bind_group(
+        &self,
+        device: &wgpu::Device,
+        job: &Job,
+        vbo: &Buffer,
+        ibo: &Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("{}_mesh_group", job.label)),
+            layout: &self.mesh_pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: job.params.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: job.blocks.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.block_types.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: job.draw.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: vbo.inner().as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: ibo.inner().as_entire_binding(),
+                },
+            ],
+        })
+    }
+}
+
+/// Pack a block of a chunk for the shader, with its block light and skylight
+/// above the id.
+#[inline]
+fn pack_block(chunk: &Chunk, x: usize, y: usize, z: usize) -> u32 {
+    chunk.get(x, y, z).0 as u32
+        | (chunk.light(x, y, z) as u32) << 16
+        | (chunk.sky_light(x, y, z) as u32) << 20
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::types::test_device;
+    use crate::world::{blocks::BlockId, mesher};
+
+    /// Split a mesh into its quads, sorted so that meshes can be compared
+    /// regardless of face order.
+    fn sorted_quads(vertices: &[Vertex], indices: &[u32]) -> Vec<Vec<[u32; 18]>> {
+        let mut quads: Vec<_> = indices
+            .chunks(6)
+            .map(|quad| {
+                // Each quad's four corners are its first, second, third and sixth index
+                [quad[0], quad[1], quad[2], quad[5]]
+                    .iter()
+                    .map(|&i| bytemuck::cast(vertices[i as usize]))
+                    .collect::<Vec<[u32; 18]>>()
+            })
+            .collect();
+        quads.sort();
+        quads
+    }
+
+    /// Mesh a chunk on both the GPU and the CPU, and check they give the same quads.
+    fn assert_matches_cpu(
+        (mesher, device, queue): (&GpuMesher, &wgpu::Device, &wgpu::Queue),
+        pos: ChunkCoord,
+        chunk: &Chunk,
+        neighbours: Neighbours,
+    ) {
+        let (cpu_vertices, cpu_indices) =
+            mesher::build_mesh_with_neighbours(chunk, &neighbours, pos.to_world_origin().into());
+
+        let meshes = mesher.build_meshes(device, queue, [(pos, chunk, neighbours)]);
+        assert_eq!(meshes.len(), 1);
+        let mesh = &meshes[0];
+        let gpu_vertices = mesh.vbo().read_back::<Vertex>(device, queue);
+        let gpu_indices = mesh.ibo().read_back::<u32>(device, queue);
+
+        assert_eq!(gpu_indices.len(), cpu_indices.len());
+        assert_eq!(
+            sorted_quads(&gpu_vertices, &gpu_indices),
+            sorted_quads(&cpu_vertices, &cpu_indices)
+        );
+    }
+
+    #[test]
+    fn matches_cpu_mesher() {
+        let (adapter, device, queue) = test_device();
+        let mesher = GpuMesher::new(&adapter, &device).expect("compute meshing unsupported");
+
+        // A mix of buried, exposed, tinted and cross-shaped blocks
+        let mut chunk = Chunk::new();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for y in 0..4 {
+                    chunk.set(x, y, z, BlockId::STONE);
+                }
+                chunk.set(x, 4, z, BlockId::GRASS);
+            }
+        }
+        chunk.set(3, 5, 7, BlockId::FLOWER);
+        chunk.set(8, 5, 8, BlockId::DIRT);
+        chunk.set(15, 15, 15, BlockId::STONE);
+        // Light on the faces of a block and on a plant
+        chunk.set_light(8, 6, 8, 12);
+        chunk.set_light(9, 5, 8, 7);
+        chunk.set_light(3, 5, 7, 9);
+        chunk.set_sky_light(3, 5, 7, 4);
+        chunk.set_sky_light(8, 5, 7, 15);
+        chunk.set_sky_light(0, 5, 0, 11);
+
+        let pos = ChunkCoord::new(-1, 0, 2);
+        assert_matches_cpu((&mesher, &device, &queue), pos, &chunk, [None; 6]);
+    }
+
+    #[test]
+    fn culls_against_neighbours_like_cpu_mesher() {
+        let (adapter, device, queue) = test_device();
+        let mesher = GpuMesher::new(&adapter, &device).expect("compute meshing unsupported");
+
+        // Blocks on every edge of the chunk, with neighbours hiding some of
+        // their faces and lighting others
+        let mut chunk = Chunk::new();
+        for i in 0..CHUNK_SIZE {
+            chunk.set(0, i, 3, BlockId::STONE);
+            chunk.set(CHUNK_SIZE - 1, 5, i, BlockId::DIRT);
+            chunk.set(i, 0, 7, BlockId::GRASS);
+            chunk.set(2, CHUNK_SIZE - 1, i, BlockId::STONE);
+            chunk.set(i, 9, CHUNK_SIZE - 1, BlockId::STONE);
+            chunk.set(11, i, 0, BlockId::DIRT);
+        }
+
+        let mut east = Chunk::new();
+        let mut below = Chunk::new();
+        let mut north = Chunk::new();
+        let mut west = Chunk::new();
+        for i in (0..CHUNK_SIZE).step_by(2) {
+            east.set(0, 5, i, BlockId::STONE);
+            below.set(i, CHUNK_SIZE - 1, 7, BlockId::DIRT);
+            north.set(i, 9, 0, BlockId::STONE);
+            // Plants don't hide the faces next to them
+            west.set(CHUNK_SIZE - 1, i, 3, BlockId::FLOWER);
+        }
+        east.set_light(0, 5, 1, 13);
+        below.set_sky_light(3, CHUNK_SIZE - 1, 7, 6);
+        west.set_light(CHUNK_SIZE - 1, 1, 3, 10);
+        west.set_sky_light(CHUNK_SIZE - 1, 2, 3, 0);
+
+        // Up and south aren't loaded
+        let mut neighbours: Neighbours = [None; 6];
+        neighbours[Face::PosX as usize] = Some(&east);
+        neighbours[Face::NegX as usize] = Some(&west);
+        neighbours[Face::NegY as usize] = Some(&below);
+        neighbours[Face::PosZ as usize] = Some(&north);
+
+        let pos = ChunkCoord::new(3, -1, 0);
+        assert_matches_cpu((&mesher, &device, &queue), pos, &chunk, neighbours);
+    }
+
+    #[test]
+    fn skips_empty_chunks() {
+        let (adapter, device, queue) = test_device();
+        let mesher = GpuMesher::new(&adapter, &device).expect("compute meshing unsupported");
+
+        let chunk = Chunk::new();
+        let meshes = mesher.build_meshes(
+            &device,
+            &queue,
+            [(ChunkCoord::default(), &chunk, [None; 6])],
+        );
+        assert!(meshes.is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+//! Mapping of user input to actions.
+
+use winit::event::VirtualKeyCode;
+
+/// Something the player can do with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+    Sprint,
+    Zoom,
+    /// Switch between flying freely and walking with physics.
+    ToggleFly,
+}
+
+/// The key bound to each [`Action`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub forward: VirtualKeyCode,
+    pub back: VirtualKeyCode,
+    pub left: VirtualKeyCode,
+    pub right: VirtualKeyCode,
+    pub up: VirtualKeyCode,
+    pub down: VirtualKeyCode,
+    pub sprint: VirtualKeyCode,
+    pub zoom: VirtualKeyCode,
+    pub toggle_fly: VirtualKeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: VirtualKeyCode::W,
+            back: VirtualKeyCode::S,
+            left: VirtualKeyCode::A,
+            right: VirtualKeyCode::D,
+            up: VirtualKeyCode::Space,
+            down: VirtualKeyCode::LShift,
+            sprint: VirtualKeyCode::LControl,
+            zoom: VirtualKeyCode::C,
+            toggle_fly: VirtualKeyCode::F,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Get the key bound to an action.
+    pub fn key(&self, action: Action) -> VirtualKeyCode {
+        match action {
+            Action::Forward => self.forward,
+            Action::Back => self.back,
+            Action::Left => self.left,
+            Action::Right => self.right,
+            Action::Up => self.up,
+            Action::Down => self.down,
+            Action::Sprint => self.sprint,
+            Action::Zoom => self.zoom,
+            Action::ToggleFly => self.toggle_fly,
+        }
+    }
+
+    /// Get the action a key is bound to, if any.
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        [
+            Action::Forward,
+            Action::Back,
+            Action::Left,
+            Action::Right,
+            Action::Up,
+            Action::Down,
+            Action::Sprint,
+            Action::Zoom,
+            Action::ToggleFly,
+        ]
+        .into_iter()
+        .find(|action| self.key(*action) == key)
+    }
+}
+
+/// What keyboard and mouse input is currently routed to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum InputState {
+    /// Input moves the camera and interacts with the world.
+    #[default]
+    InGame,
+    /// The game is paused. Escape resumes it; other keys are left to the caller.
+    Paused,
+    /// A menu has focus. Movement keys are ignored, and Escape closes the menu.
+    Menu,
+}
+
+impl InputState {
+    /// Whether input controls the camera.
+    #[inline]
+    pub fn is_in_game(self) -> bool {
+        self == Self::InGame
+    }
+
+    /// The state Escape leads to: in game it pauses, and otherwise it goes
+    /// back to the game.
+    #[inline]
+    pub fn escape(self) -> Self {
+        match self {
+            Self::InGame => Self::Paused,
+            Self::Paused | Self::Menu => Self::InGame,
+        }
+    }
+}
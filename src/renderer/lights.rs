@@ -0,0 +1,83 @@
+//! Point lights, like torches.
+
+use nalgebra_glm as glm;
+
+use super::types::{PointLight, PointLights, MAX_POINT_LIGHTS};
+
+/// Identifies a light added with [`LightSet::add`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LightId(u32);
+
+/// A fixed-capacity set of point lights, uploaded as a single uniform.
+#[derive(Debug, Default)]
+pub struct LightSet {
+    lights: Vec<(LightId, PointLight)>,
+    next_id: u32,
+    /// Whether the lights changed since the last call to `take_uniform`.
+    dirty: bool,
+}
+
+impl LightSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a light, lighting blocks within `range` of `position`.
+    ///
+    /// Returns `None` if there are already [`MAX_POINT_LIGHTS`] lights.
+    pub fn add(&mut self, position: glm::Vec3, color: [f32; 4], range: f32) -> Option<LightId> {
+        if self.lights.len() >= MAX_POINT_LIGHTS {
+            return None;
+        }
+
+        let id = LightId(self.next_id);
+        self.next_id += 1;
+        self.lights.push((
+            id,
+            PointLight {
+                position: position.into(),
+                range,
+                color,
+            },
+        ));
+        self.dirty = true;
+        Some(id)
+    }
+
+    /// Remove a light, returning whether it existed.
+    pub fn remove(&mut self, id: LightId) -> bool {
+        let len = self.lights.len();
+        self.lights.retain(|(i, _)| *i != id);
+        self.dirty |= self.lights.len() != len;
+        self.lights.len() != len
+    }
+
+    /// Number of lights in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Get the uniform data for the lights if they changed since the last call.
+    pub fn take_uniform(&mut self) -> Option<PointLights> {
+        if !std::mem::take(&mut self.dirty) {
+            return None;
+        }
+        Some(self.uniform())
+    }
+
+    /// Get the uniform data for the lights.
+    pub fn uniform(&self) -> PointLights {
+        let mut uniform: PointLights = bytemuck::Zeroable::zeroed();
+        uniform.count = self.lights.len() as u32;
+        for (slot, (_, light)) in uniform.lights.iter_mut().zip(&self.lights) {
+            *slot = *light;
+        }
+        uniform
+    }
+}
@@ -0,0 +1,65 @@
+//! Frame rate limiting.
+
+use std::time::{Duration, Instant};
+
+/// How long before a frame is due to stop sleeping and start spinning.
+///
+/// OS sleeps commonly overshoot by a millisecond or more, so the last stretch
+/// is waited out by spinning for accuracy.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+/// Caps the frame rate by waiting between frames.
+///
+/// Useful with present modes that don't wait for vertical sync, like
+/// [`wgpu::PresentMode::Mailbox`] and [`wgpu::PresentMode::Immediate`], which
+/// otherwise render as many frames as possible.
+#[derive(Debug, Clone)]
+pub struct FrameLimiter {
+    max_fps: Option<u32>,
+    /// When the next frame may start.
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: Option<u32>) -> Self {
+        Self {
+            max_fps: max_fps.filter(|&fps| fps > 0),
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// Change the frame rate cap, or remove it with `None`.
+    #[inline]
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.max_fps = max_fps.filter(|&fps| fps > 0);
+        self.next_frame = Instant::now();
+    }
+
+    #[inline]
+    pub const fn max_fps(&self) -> Option<u32> {
+        self.max_fps
+    }
+
+    /// Block until the next frame is due.
+    ///
+    /// Returns immediately if there is no cap.
+    pub fn wait(&mut self) {
+        let Some(max_fps) = self.max_fps else {
+            return;
+        };
+
+        let now = Instant::now();
+        if let Some(remaining) = self.next_frame.checked_duration_since(now) {
+            if remaining > SPIN_THRESHOLD {
+                std::thread::sleep(remaining - SPIN_THRESHOLD);
+            }
+            while Instant::now() < self.next_frame {
+                std::hint::spin_loop();
+            }
+        }
+
+        // Don't try to catch up after a slow frame, which would cause a burst
+        let period = Duration::from_secs(1) / max_fps;
+        self.next_frame = (self.next_frame + period).max(Instant::now());
+    }
+}
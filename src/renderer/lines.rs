@@ -0,0 +1,261 @@
+//! Debug line rendering.
+
+use nalgebra_glm as glm;
+
+use super::pipeline::depth_compare;
+use super::shader;
+use super::types::texture::Texture;
+
+/// An endpoint of a line.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl LineVertex {
+    const ATTRS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    pub const BUFFER_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &Self::ATTRS,
+    };
+}
+
+/// Batches colored lines and draws them in a single call.
+///
+/// Lines are queued with [`LineRenderer::draw_line`] and friends, uploaded with
+/// [`LineRenderer::prepare`], and discarded after they have been drawn.
+///
+/// Translucent boxes can be queued with [`LineRenderer::draw_box`]. They are
+/// drawn after the lines, without writing depth.
+pub struct LineRenderer {
+    pipeline: wgpu::RenderPipeline,
+    /// Pipeline for translucent triangles.
+    fill_pipeline: wgpu::RenderPipeline,
+    /// Lines queued for the current frame.
+    vertices: Vec<LineVertex>,
+    /// Triangles queued for the current frame.
+    triangles: Vec<LineVertex>,
+    /// Vertex buffer holding lines followed by triangles, grown to fit the
+    /// largest batch seen so far.
+    buffer: wgpu::Buffer,
+    /// Number of vertices the buffer can hold.
+    capacity: usize,
+    /// Number of line vertices uploaded by the last call to `prepare`.
+    uploaded: u32,
+    /// Number of triangle vertices uploaded after the lines.
+    uploaded_triangles: u32,
+}
+
+impl LineRenderer {
+    const INITIAL_CAPACITY: usize = 1024;
+
+    /// Create a line renderer drawing into targets of the given format.
+    ///
+    /// `globals_layout` is the layout of the camera's uniform bind group, and
+    /// `reversed_z` whether its camera uses reversed Z.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        globals_layout: &wgpu::BindGroupLayout,
+        reversed_z: bool,
+    ) -> Self {
+        let shader = shader::create(
+            device,
+            "lines.wgsl",
+            include_str!("../../res/shaders/lines.wgsl"),
+        );
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Line Pipeline Layout"),
+            bind_group_layouts: &[globals_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |label, topology, depth_write_enabled| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[LineVertex::BUFFER_LAYOUT],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled,
+                    depth_compare: depth_compare(wgpu::CompareFunction::LessEqual, reversed_z),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+
+        Self {
+            pipeline: create_pipeline("Line Pipeline", wgpu::PrimitiveTopology::LineList, true),
+            // Translucent surfaces shouldn't hide what's behind them
+            fill_pipeline: create_pipeline(
+                "Line Fill Pipeline",
+                wgpu::PrimitiveTopology::TriangleList,
+                false,
+            ),
+            vertices: Vec::new(),
+            triangles: Vec::new(),
+            buffer: Self::create_buffer(device, Self::INITIAL_CAPACITY),
+            capacity: Self::INITIAL_CAPACITY,
+            uploaded: 0,
+            uploaded_triangles: 0,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Line Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<LineVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    /// Queue a line from `a` to `b`.
+    pub fn draw_line(&mut self, a: glm::Vec3, b: glm::Vec3, color: [f32; 4]) {
+        self.vertices.push(LineVertex {
+            position: a.into(),
+            color,
+        });
+        self.vertices.push(LineVertex {
+            position: b.into(),
+            color,
+        });
+    }
+
+    /// Queue the edges of an axis-aligned box.
+    pub fn draw_aabb(&mut self, min: glm::Vec3, max: glm::Vec3, color: [f32; 4]) {
+        let corner = |x: bool, y: bool, z: bool| {
+            glm::vec3(
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            )
+        };
+
+        for a in [false, true] {
+            for b in [false, true] {
+                // Edges along each axis
+                self.draw_line(corner(false, a, b), corner(true, a, b), color);
+                self.draw_line(corner(a, false, b), corner(a, true, b), color);
+                self.draw_line(corner(a, b, false), corner(a, b, true), color);
+            }
+        }
+    }
+
+    /// Queue the faces of an axis-aligned box, usually with a translucent color.
+    pub fn draw_box(&mut self, min: glm::Vec3, max: glm::Vec3, color: [f32; 4]) {
+        let corner = |x: bool, y: bool, z: bool| LineVertex {
+            position: [
+                if x { max.x } else { min.x },
+                if y { max.y } else { min.y },
+                if z { max.z } else { min.z },
+            ],
+            color,
+        };
+
+        for side in [false, true] {
+            // Faces perpendicular to each axis, as two triangles each
+            let faces = [
+                [
+                    corner(side, false, false),
+                    corner(side, true, false),
+                    corner(side, true, true),
+                    corner(side, false, true),
+                ],
+                [
+                    corner(false, side, false),
+                    corner(true, side, false),
+                    corner(true, side, true),
+                    corner(false, side, true),
+                ],
+                [
+                    corner(false, false, side),
+                    corner(true, false, side),
+                    corner(true, true, side),
+                    corner(false, true, side),
+                ],
+            ];
+            for [a, b, c, d] in faces {
+                self.triangles.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+    }
+
+    /// Upload queued lines and boxes to the GPU.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let len = self.vertices.len() + self.triangles.len();
+        if len > self.capacity {
+            self.capacity = len.next_power_of_two();
+            self.buffer = Self::create_buffer(device, self.capacity);
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.vertices));
+        queue.write_buffer(
+            &self.buffer,
+            std::mem::size_of_val(&self.vertices[..]) as wgpu::BufferAddress,
+            bytemuck::cast_slice(&self.triangles),
+        );
+        self.uploaded = self.vertices.len() as u32;
+        self.uploaded_triangles = self.triangles.len() as u32;
+        self.vertices.clear();
+        self.triangles.clear();
+    }
+
+    /// Draw the lines and boxes uploaded by the last call to [`LineRenderer::prepare`].
+    pub fn render<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        globals: &'a wgpu::BindGroup,
+    ) {
+        if self.uploaded == 0 && self.uploaded_triangles == 0 {
+            return;
+        }
+
+        render_pass.set_bind_group(0, globals, &[]);
+        render_pass.set_vertex_buffer(0, self.buffer.slice(..));
+        if self.uploaded > 0 {
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.draw(0..self.uploaded, 0..1);
+        }
+        if self.uploaded_triangles > 0 {
+            render_pass.set_pipeline(&self.fill_pipeline);
+            render_pass.draw(self.uploaded..self.uploaded + self.uploaded_triangles, 0..1);
+        }
+    }
+}
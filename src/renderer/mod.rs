@@ -1,21 +1,169 @@
 //! State of the GPU.
 
+pub mod animation;
+pub mod atlas;
+pub mod billboard;
+pub mod blit;
+pub mod breaking;
+pub mod camera;
+pub mod config;
+pub mod day_night;
+#[cfg(feature = "debug-ui")]
+pub mod debug_ui;
+#[cfg(feature = "gpu-meshing")]
+pub mod gpu_mesher;
+pub mod input;
+pub mod lights;
+pub mod limiter;
+pub mod lines;
+pub mod occlusion;
+pub mod pipeline;
+pub mod shader;
+pub mod stats;
+pub mod texture_loader;
+pub mod tick;
+pub mod timer;
 pub mod types;
+pub mod ui;
+pub mod upload;
+pub mod viewport;
 
-use winit::event::WindowEvent;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use nalgebra_glm as glm;
+use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
 use winit::window::Window;
 
+use crate::world::{
+    blocks::{BlockId, BlockState},
+    chunk::{ChunkCoord, CHUNK_SIZE},
+    mesh_pool::MeshPool,
+    mesher,
+    player::Player,
+    raycast::RaycastHit,
+    World,
+};
+use billboard::BillboardRenderer;
+use blit::{Blitter, ScaledFrame};
+use breaking::BreakProgress;
+use camera::{Camera, CameraController};
+pub use config::RendererConfig;
+use day_night::DayNightCycle;
+use input::{Action, InputState, KeyBindings};
+use lines::LineRenderer;
+use pipeline::PipelineBuilder;
+use stats::FrameStats;
+use texture_loader::{BlockTextures, TextureLoader};
 use types::{
+    batch::MeshBatch,
     binding,
     buffer::{Buffer, BufferInitDescriptor},
-    texture::{Texture, TextureDescriptor},
-    Vertex,
+    mesh::Mesh,
+    target::{RenderTarget, RenderTargetDescriptor, RenderTargetId},
+    texture::{SamplerOptions, Texture},
+    ChunkUniform, Fog, Globals,
 };
+use upload::MeshUploadQueue;
+use viewport::{Viewport, ViewportId};
+
+/// How far away blocks can be targeted, in blocks.
+const REACH: f32 = 6.0;
+
+/// Color format of the targets a headless renderer draws into.
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Number of blocks the minimap shows on each side of the camera.
+pub const MINIMAP_RADIUS: f32 = 48.0;
+
+/// Lowest height the minimap is seen from, above the generated terrain.
+const MINIMAP_HEIGHT: f32 = 64.0;
+
+/// Which camera a scene is drawn from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scene {
+    /// The camera looking through the window.
+    Main,
+    /// Looking straight down from above the camera.
+    Minimap,
+    /// The camera of a secondary viewport.
+    Viewport(ViewportId),
+}
+
+/// Pipelines drawing chunk geometry.
+struct BlockPipelines {
+    /// Draws opaque and cutout blocks, writing depth.
+    ///
+    /// Parts of cutout textures like plants are smoothed by alpha to coverage
+    /// with MSAA, and discarded below half opacity otherwise.
+    opaque: wgpu::RenderPipeline,
+    /// Blends liquids over everything else, without writing depth.
+    translucent: wgpu::RenderPipeline,
+    /// Depth-only pipeline, if the depth prepass is enabled.
+    depth_prepass: Option<wgpu::RenderPipeline>,
+    /// Blends cracks over the block being broken.
+    crack: wgpu::RenderPipeline,
+    /// Draws the boxes of chunks for occlusion queries, if pipeline statistics
+    /// queries are supported.
+    occlusion: Option<wgpu::RenderPipeline>,
+}
+
+/// Geometry of the loaded chunks.
+enum ChunkGeometry {
+    /// Nothing has been meshed yet.
+    Empty,
+    /// One mesh per chunk, drawn separately.
+    #[cfg(feature = "gpu-meshing")]
+    Meshes(Vec<Mesh>),
+    /// Every chunk packed into shared buffers.
+    Batch(Box<MeshBatch<ChunkCoord>>),
+}
+
+impl ChunkGeometry {
+    /// Get the batch of chunk meshes, replacing any other geometry with an empty
+    /// batch.
+    fn batch_mut(&mut self, device: &wgpu::Device) -> &mut MeshBatch<ChunkCoord> {
+        if !matches!(self, ChunkGeometry::Batch(_)) {
+            *self = ChunkGeometry::Batch(Box::new(MeshBatch::empty(device, Some("chunk_batch"))));
+        }
+        match self {
+            ChunkGeometry::Batch(batch) => batch,
+            _ => unreachable!("geometry was just replaced with a batch"),
+        }
+    }
+
+    /// Get the world-space (min, max) corners enclosing each mesh, in draw order.
+    fn aabbs(&self) -> Vec<(glm::Vec3, glm::Vec3)> {
+        match self {
+            ChunkGeometry::Empty => Vec::new(),
+            #[cfg(feature = "gpu-meshing")]
+            ChunkGeometry::Meshes(meshes) => meshes.iter().map(Mesh::aabb).collect(),
+            ChunkGeometry::Batch(batch) => batch.aabbs().to_vec(),
+        }
+    }
+
+    /// Memory taken by the geometry on the GPU, in bytes.
+    fn byte_len(&self) -> wgpu::BufferAddress {
+        match self {
+            ChunkGeometry::Empty => 0,
+            #[cfg(feature = "gpu-meshing")]
+            ChunkGeometry::Meshes(meshes) => meshes.iter().map(Mesh::byte_len).sum(),
+            ChunkGeometry::Batch(batch) => batch.byte_len(),
+        }
+    }
+}
 
 /// Managed the state of the physical device.
 pub struct Renderer {
+    /// Connection to the graphics API, kept to create surfaces for more viewports.
+    instance: wgpu::Instance,
+    /// The physical device, kept to check which formats new surfaces support.
+    adapter: wgpu::Adapter,
     /// The surface onto which images can be rendered - part of a window.
-    surface: wgpu::Surface,
+    ///
+    /// `None` for a renderer created with [`Renderer::new_headless`].
+    surface: Option<wgpu::Surface>,
     /// The device is an open connection to the physical device.
     device: wgpu::Device,
     /// The queue is a handle to the device's command queue.
@@ -24,286 +172,2996 @@ pub struct Renderer {
     config: wgpu::SurfaceConfiguration,
     /// The size of our surface.
     pub size: winit::dpi::PhysicalSize<u32>,
+    /// Physical pixels per logical pixel of the display the window is on.
+    scale_factor: f32,
+    /// Renderer settings.
+    settings: RendererConfig,
     /// Represents a render pipeline and its stages.
     ///
     /// A render/graphics pipeline is a model that describes all steps the GPU will perform
     /// on input data to produce output. Think of a GPU as an assembly line. It has a lot of
     /// different parts doing different things, and the output is pixels rendered
     /// on a framebuffer. This "assembly line" is what we call the graphics pipeline.
-    render_pipeline: wgpu::RenderPipeline,
-    /// A vertex buffer object.
-    vbo: Buffer,
-    /// An index buffer object.
-    ibo: Buffer,
-    /// The bind group for diffuse textures.
+    pipelines: BlockPipelines,
+    /// Main shader, kept to rebuild the pipelines when settings change.
+    shader: wgpu::ShaderModule,
+    /// The world being rendered.
+    world: World,
+    /// Geometry of the loaded chunks.
+    chunk_geometry: ChunkGeometry,
+    /// Translucent geometry of the loaded chunks, like water.
+    translucent_geometry: MeshBatch<ChunkCoord>,
+    /// Time since the renderer started, for animation.
+    elapsed: Duration,
+    /// Whether batches can be drawn with a single indirect call.
+    multi_draw: bool,
+    /// The bind group for the diffuse atlas and its normal map.
     diffuse_bind_group: binding::Group,
+    /// Bytes taken by the textures and buffers in `diffuse_bind_group`, which
+    /// are only kept alive by the bind group itself.
+    block_texture_memory: u64,
+    /// Decodes the block textures, until they are uploaded in place of the
+    /// placeholder.
+    texture_loader: TextureLoader,
+    /// Uniform buffer holding [`Globals`].
+    globals: Buffer,
+    /// Point lights, like torches.
+    point_lights: lights::LightSet,
+    /// Uniform buffer holding the point lights.
+    point_lights_buffer: Buffer,
+    /// The bind group for the globals and point light uniforms.
+    globals_bind_group: binding::Group,
+    /// Uniform buffer holding [`Globals`] seen from the minimap camera.
+    minimap_globals: Buffer,
+    /// Like `globals_bind_group`, but with the minimap globals.
+    minimap_globals_bind_group: binding::Group,
+    /// Secondary surfaces showing the world from their own cameras.
+    viewports: HashMap<ViewportId, Viewport>,
+    /// Id given to the next viewport.
+    next_viewport_id: u32,
+    /// Offscreen targets kept at the size of the surface.
+    render_targets: HashMap<RenderTargetId, RenderTarget>,
+    /// Id given to the next render target.
+    next_render_target_id: u32,
+    /// Distance fog settings.
+    ///
+    /// The color is the daytime color, and is tinted by `day_night`.
+    fog: Fog,
+    /// Time of day, moving the sun and tinting the sky and fog.
+    day_night: DayNightCycle,
+    /// Color multiplied into tinted faces.
+    grass_color: wgpu::Color,
+    /// The viewpoint the scene is rendered from.
+    camera: Camera,
+    /// Moves the camera in response to input.
+    camera_controller: CameraController,
+    /// Whether the camera was placed with [`Renderer::set_camera`], and is
+    /// left alone by the controller and player until released.
+    camera_scripted: bool,
+    /// The player walking through the world, or `None` while flying freely.
+    player: Option<Player>,
+    /// Whether the mouse is held down to look around.
+    mouse_pressed: bool,
+    /// What input is routed to: the game, a menu, or nothing while paused.
+    input_state: InputState,
+    /// Whether the window has keyboard focus.
+    focused: bool,
+    /// Whether the game was paused because the window lost focus, and should
+    /// resume once it regains it.
+    paused_on_focus_loss: bool,
+    /// Watches the shader source for changes, in debug builds.
+    #[cfg(debug_assertions)]
+    shader_watcher: Option<shader::ShaderWatcher>,
+    /// Depth buffer, matching the size the world is rendered at.
+    depth_texture: Texture,
+    /// Color buffer rendered to when MSAA is enabled.
+    msaa_texture: Option<Texture>,
+    /// Stretches the scaled frame over the surface.
+    blitter: Blitter,
+    /// Color buffer the world is rendered to when the render scale isn't 1.
+    scaled_frame: Option<ScaledFrame>,
+    /// Draws debug lines on top of the world.
+    line_renderer: LineRenderer,
+    /// Draws camera-facing quads, like dropped items.
+    billboard_renderer: BillboardRenderer,
+    /// Draws the crosshair.
+    ui_renderer: ui::UiRenderer,
+    /// Builds chunk meshes in the background.
+    mesh_pool: MeshPool,
+    /// Meshes finished by `mesh_pool`, uploaded a few per frame.
+    mesh_uploads: MeshUploadQueue,
+    /// Block the camera is looking at, within reach.
+    target: Option<RaycastHit>,
+    /// Where a block would be placed, if the camera is aiming at a face.
+    place_preview: Option<[i32; 3]>,
+    /// Whether the right mouse button is held down to mine the target.
+    mining: bool,
+    /// How far the targeted block has been mined.
+    break_progress: BreakProgress,
+    /// Texture array layer of the first crack stage.
+    crack_layer: u32,
+    /// Cracks drawn over the block being broken, and the block and stage they
+    /// were built for.
+    crack_mesh: Option<(([i32; 3], u32), Mesh)>,
+    /// Whether to outline the chunks around the camera.
+    show_chunk_borders: bool,
+    /// Whether to tint each chunk with its own color.
+    show_chunk_colors: bool,
+    /// Model matrix and debug color of each chunk mesh, spaced for dynamic offsets.
+    chunk_uniforms: binding::Group,
+    /// Distance in bytes between the entries in `chunk_uniforms`.
+    chunk_uniform_stride: wgpu::BufferAddress,
+    /// Splits frames into the fixed ticks the player is simulated in.
+    ticks: tick::FixedTimestep,
+    /// Frame timing statistics.
+    stats: FrameStats,
+    /// Caps the frame rate, independently of the present mode.
+    frame_limiter: limiter::FrameLimiter,
+    /// Times scene drawing on the GPU, if timestamp queries are supported.
+    gpu_timer: Option<timer::GpuTimer>,
+    /// Skips chunks hidden behind terrain, if pipeline statistics queries are
+    /// supported.
+    occlusion: Option<occlusion::OcclusionCuller>,
+    /// Meshes chunks with compute shaders, if supported.
+    #[cfg(feature = "gpu-meshing")]
+    gpu_mesher: Option<gpu_mesher::GpuMesher>,
+    /// Overlay showing statistics and tunable settings.
+    #[cfg(feature = "debug-ui")]
+    debug_ui: debug_ui::DebugUi,
+    /// Holds screenshots copied to the clipboard, opened when first needed.
+    #[cfg(feature = "clipboard")]
+    clipboard: Option<arboard::Clipboard>,
 }
 
 impl Renderer {
-    /// Retrieve and store the GPU's state.
+    /// Lowest allowed [`RendererConfig::render_scale`].
+    pub const MIN_RENDER_SCALE: f32 = 0.25;
+    /// Highest allowed [`RendererConfig::render_scale`].
+    pub const MAX_RENDER_SCALE: f32 = 2.0;
+
+    /// Retrieve and store the GPU's state, using the default settings.
     pub async fn new(window: &Window) -> Self {
+        Self::with_config(window, RendererConfig::default()).await
+    }
+
+    /// Retrieve and store the GPU's state.
+    ///
+    /// Settings out of their valid range are reset with
+    /// [`RendererConfig::validate`].
+    pub async fn with_config(window: &Window, mut settings: RendererConfig) -> Self {
+        settings.validate();
+        let world = World::generate(
+            settings.seed,
+            settings.render_distance as i32,
+            settings.world_height,
+        );
+        Self::with_world(window, settings, world).await
+    }
+
+    /// Retrieve and store the GPU's state, rendering an existing world.
+    async fn with_world(window: &Window, settings: RendererConfig, world: World) -> Self {
         let size = window.inner_size();
 
         // WGPU context
-        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let instance = wgpu::Instance::new(Self::backends(&settings));
 
         // SAFETY: window is always valid
         let surface = unsafe { instance.create_surface(&window) };
 
         // A handle to the physical device
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
+        let adapter = Self::request_adapter(&instance, &surface, settings.power_preference)
             .await
-            .unwrap(); // unwrap is okay here since we can't get a handle to the GPU
+            .expect("no suitable graphics adapter found"); // we can't do anything without a GPU
+        let (device, queue) = Self::request_device(&adapter, &settings).await;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: Self::choose_surface_format(&surface, &adapter),
+            width: size.width,
+            height: size.height,
+            present_mode: Self::choose_present_mode(&surface, &adapter, settings.present_mode),
+        };
+        surface.configure(&device, &config);
+
+        Self::from_device(
+            instance,
+            adapter,
+            Some(surface),
+            (device, queue),
+            config,
+            window.scale_factor() as f32,
+            settings,
+            world,
+        )
+    }
+
+    /// Create a renderer without a window, drawing only into offscreen targets
+    /// with [`Renderer::render_to`].
+    ///
+    /// The adapter is picked with `options` instead of the power preference of
+    /// `settings`. Setting [`force_fallback_adapter`] picks a software adapter
+    /// like llvmpipe, so tests can render on machines without a GPU. Targets must
+    /// use [`HEADLESS_FORMAT`].
+    ///
+    /// Settings out of their valid range are reset with
+    /// [`RendererConfig::validate`]. Returns `None` if no adapter matches
+    /// `options`.
+    ///
+    /// [`force_fallback_adapter`]: wgpu::RequestAdapterOptions::force_fallback_adapter
+    pub async fn new_headless(
+        mut settings: RendererConfig,
+        width: u32,
+        height: u32,
+        options: &wgpu::RequestAdapterOptions<'_>,
+    ) -> Option<Self> {
+        settings.validate();
+        let instance = wgpu::Instance::new(Self::backends(&settings));
+        let adapter = instance.request_adapter(options).await?;
+        let info = adapter.get_info();
+        tracing::info!("using headless adapter: {} ({:?})", info.name, info.backend);
+        let (device, queue) = Self::request_device(&adapter, &settings).await;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: HEADLESS_FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let world = World::generate(
+            settings.seed,
+            settings.render_distance as i32,
+            settings.world_height,
+        );
 
-        let (device, queue) = adapter
+        let mut renderer = Self::from_device(
+            instance,
+            adapter,
+            None,
+            (device, queue),
+            config,
+            1.0,
+            settings,
+            world,
+        );
+        // Nothing is shown before the first render anyway, so it may as well be textured
+        renderer.wait_for_textures();
+        Some(renderer)
+    }
+
+    /// Open a device on `adapter`, with the optional features of `settings` it
+    /// supports.
+    async fn request_device(
+        adapter: &wgpu::Adapter,
+        settings: &RendererConfig,
+    ) -> (wgpu::Device, wgpu::Queue) {
+        // Only request what the adapter can provide, anything else is optional
+        let features = adapter.features() & settings.features;
+        let missing = settings.features - features;
+        if !missing.is_empty() {
+            tracing::warn!("requested features unsupported by the adapter: {missing:?}");
+        }
+
+        adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     // Debug label
-                    label: Some("Some Device"),
-                    features: wgpu::Features::empty(),
+                    label: Some("Renderer Device"),
+                    features,
                     limits: wgpu::Limits::default(),
                 },
                 None, // API call tracing
             )
             .await
-            .unwrap();
+            .unwrap()
+    }
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+    /// Create the renderer's resources on an open device.
+    ///
+    /// `surface` must already be configured with `config`. Without one, `config`
+    /// only sets the format and size that offscreen targets are expected to have.
+    #[allow(clippy::too_many_arguments)]
+    fn from_device(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        surface: Option<wgpu::Surface>,
+        (device, queue): (wgpu::Device, wgpu::Queue),
+        config: wgpu::SurfaceConfiguration,
+        scale_factor: f32,
+        mut settings: RendererConfig,
+        world: World,
+    ) -> Self {
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
+        settings.render_scale = settings
+            .render_scale
+            .clamp(Self::MIN_RENDER_SCALE, Self::MAX_RENDER_SCALE);
+        let blitter = Blitter::new(&device, config.format, settings.sample_count);
+        let (depth_texture, msaa_texture, scaled_frame) =
+            Self::create_scaled_framebuffers(&device, &blitter, &config, &settings);
+
+        // Textures are decoded in the background, and shown as a placeholder until then
+        let texture_loader = TextureLoader::spawn(settings.normal_map.clone());
+        let placeholder = BlockTextures::placeholder();
+        let crack_layer = placeholder.crack_layer;
+        let (diffuse_bind_group, block_texture_memory) = Self::create_block_texture_group(
+            &device,
+            &queue,
+            &placeholder,
+            settings.mip_bias,
+            None,
+        );
+
+        // Camera
+        let mut camera = Camera::new(
+            glm::vec3(0.0, 20.0, 24.0),
+            -std::f32::consts::FRAC_PI_2,
+            -0.5,
+            settings.fov,
+            size.width as f32 / size.height as f32,
+        );
+        camera.znear = settings.znear;
+        camera.zfar = settings.zfar;
+        camera.reversed_z = settings.reversed_z;
+        let mut camera_controller = CameraController::new(4.0, settings.mouse_sensitivity);
+        camera_controller.invert_y = settings.invert_y;
+        camera_controller.bindings = settings.key_bindings.clone();
+
+        let grass_color = wgpu::Color {
+            r: 0.2,
+            g: 0.6,
+            b: 0.062,
+            a: 1.0,
         };
-        surface.configure(&device, &config);
 
-        // Texture stuff
-        let dirt = image::load_from_memory(include_bytes!("../../res/textures/dirt.png")).unwrap();
+        // Fade into the sky towards the edge of the loaded world
+        let day_night = DayNightCycle::new();
+        let fog_end = (settings.render_distance as usize * CHUNK_SIZE) as f32;
+        let fog = Fog::new(settings.clear_color, fog_end * 0.6, fog_end);
 
-        let diffuse_texture = Texture::new(
+        let globals = Buffer::new(
             &device,
-            &queue,
-            &TextureDescriptor {
-                label: Some("dirt_texture"),
-                mip_level_count: 1,
-                sample_count: 1,
-                image: &dirt,
+            &BufferInitDescriptor {
+                label: Some("Globals Buffer"),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                contents: &[Self::globals(
+                    &camera,
+                    &fog,
+                    &day_night,
+                    grass_color,
+                    false,
+                    0.0,
+                    !config.format.describe().srgb,
+                    &settings,
+                )],
+            },
+        );
+
+        let point_lights = lights::LightSet::new();
+        let point_lights_buffer = Buffer::new(
+            &device,
+            &BufferInitDescriptor {
+                label: Some("Point Lights Buffer"),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                contents: &[point_lights.uniform()],
             },
+        );
+
+        let globals_bind_group =
+            Self::create_globals_group(&device, &globals, &point_lights_buffer, None);
+
+        // The minimap is seen from its own camera, but shares the point lights
+        let minimap_globals = Buffer::zeroed::<Globals>(
+            &device,
+            Some("Minimap Globals Buffer"),
+            1,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        let minimap_globals_bind_group = Self::create_globals_group(
+            &device,
+            &minimap_globals,
+            &point_lights_buffer,
+            Some(globals_bind_group.clone_layout()),
+        );
+
+        let shader = shader::load(&device);
+        // Colors are only uploaded once chunks are meshed
+        let chunk_uniform_stride = Self::chunk_uniform_stride(&device);
+        let chunk_uniforms = Self::create_chunk_uniforms(
+            &device,
+            &[ChunkUniform {
+                model: glm::Mat4::identity().into(),
+                tint: [1.0; 4],
+            }],
+            chunk_uniform_stride,
             None,
         );
 
-        let diffuse_bind_group = binding::Group::new(
+        let bind_group_layouts = [
+            diffuse_bind_group.layout(),
+            globals_bind_group.layout(),
+            chunk_uniforms.layout(),
+        ];
+        let pipelines = Self::create_block_pipelines(
             &device,
-            Some("diffuse_texture_group"),
-            [
-                binding::group::Entry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    resource: wgpu::BindingResource::TextureView(diffuse_texture.view()),
-                },
-                binding::group::Entry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    resource: wgpu::BindingResource::Sampler(diffuse_texture.sampler()),
-                },
-            ]
-            .into_iter(),
+            config.format,
+            &settings,
+            &bind_group_layouts,
+            &shader,
         );
 
-        let render_pipeline =
-            Self::create_pipeline(&device, &config, &[diffuse_bind_group.layout()]);
+        let line_renderer = LineRenderer::new(
+            &device,
+            config.format,
+            settings.sample_count,
+            globals_bind_group.layout(),
+            settings.reversed_z,
+        );
+        let billboard_renderer = BillboardRenderer::new(
+            &device,
+            config.format,
+            settings.sample_count,
+            diffuse_bind_group.layout(),
+            globals_bind_group.layout(),
+            settings.reversed_z,
+        );
+        let ui_renderer = ui::UiRenderer::new(
+            &device,
+            config.format,
+            settings.sample_count,
+            (config.width, config.height),
+            scale_factor,
+            settings.ui_scale,
+        );
 
-        // Get vertex data
-        let (vbo, ibo) = Self::get_data(&device);
+        #[cfg(feature = "gpu-meshing")]
+        let gpu_mesher = gpu_mesher::GpuMesher::new(&adapter, &device);
+        #[cfg(feature = "debug-ui")]
+        let debug_ui = debug_ui::DebugUi::new(&device, config.format, scale_factor);
+        let gpu_timer = timer::GpuTimer::new(&device, &queue);
+        let occlusion = occlusion::OcclusionCuller::new(&device);
+        let frame_limiter = limiter::FrameLimiter::new(settings.max_fps);
+        let mesh_uploads = MeshUploadQueue::new(settings.mesh_uploads_per_frame);
+        let translucent_geometry = MeshBatch::empty(&device, Some("translucent_chunk_batch"));
+        let ticks = tick::FixedTimestep::new(settings.tick_rate);
+        let multi_draw = device
+            .features()
+            .contains(wgpu::Features::MULTI_DRAW_INDIRECT);
 
-        Self {
+        let mut renderer = Self {
+            instance,
+            adapter,
             surface,
             device,
             queue,
             config,
             size,
-            render_pipeline,
-            vbo,
-            ibo,
+            scale_factor,
+            settings,
+            pipelines,
+            shader,
+            world,
+            chunk_geometry: ChunkGeometry::Empty,
+            translucent_geometry,
+            elapsed: Duration::ZERO,
+            multi_draw,
             diffuse_bind_group,
-        }
+            block_texture_memory,
+            texture_loader,
+            globals,
+            point_lights,
+            point_lights_buffer,
+            globals_bind_group,
+            minimap_globals,
+            minimap_globals_bind_group,
+            viewports: HashMap::new(),
+            next_viewport_id: 0,
+            render_targets: HashMap::new(),
+            next_render_target_id: 0,
+            fog,
+            day_night,
+            grass_color,
+            camera,
+            camera_controller,
+            camera_scripted: false,
+            player: None,
+            mouse_pressed: false,
+            input_state: InputState::InGame,
+            focused: true,
+            paused_on_focus_loss: false,
+            #[cfg(debug_assertions)]
+            shader_watcher: shader::ShaderWatcher::new()
+                .map_err(|e| tracing::warn!("shader hot-reloading disabled: {e}"))
+                .ok(),
+            depth_texture,
+            msaa_texture,
+            blitter,
+            scaled_frame,
+            line_renderer,
+            billboard_renderer,
+            ui_renderer,
+            mesh_pool: MeshPool::with_available_threads(),
+            mesh_uploads,
+            target: None,
+            place_preview: None,
+            mining: false,
+            break_progress: BreakProgress::default(),
+            crack_layer,
+            crack_mesh: None,
+            show_chunk_borders: false,
+            show_chunk_colors: false,
+            chunk_uniforms,
+            chunk_uniform_stride,
+            ticks,
+            stats: FrameStats::new(),
+            frame_limiter,
+            gpu_timer,
+            occlusion,
+            #[cfg(feature = "gpu-meshing")]
+            gpu_mesher,
+            #[cfg(feature = "debug-ui")]
+            debug_ui,
+            #[cfg(feature = "clipboard")]
+            clipboard: None,
+        };
+        renderer.rebuild_chunk_geometry();
+        renderer
     }
 
-    /// Compile shaders and create the render pipeline.
-    fn create_pipeline(
+    /// Upload block textures, and bind them with their sampler and animations.
+    ///
+    /// `layout` is reused if given, so that existing pipelines remain compatible.
+    /// Also returns the bytes taken by the textures and buffers, which are only
+    /// kept alive by the bind group itself.
+    fn create_block_texture_group(
         device: &wgpu::Device,
-        config: &wgpu::SurfaceConfiguration,
-        bind_group_layouts: &[&wgpu::BindGroupLayout],
-    ) -> wgpu::RenderPipeline {
-        // Compile the shader as a shader module
-        let shader =
-            device.create_shader_module(wgpu::include_wgsl!("../../res/shaders/shader.wgsl"));
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts,
-                push_constant_ranges: &[],
-            });
+        queue: &wgpu::Queue,
+        textures: &BlockTextures,
+        mip_bias: f32,
+        layout: Option<Arc<wgpu::BindGroupLayout>>,
+    ) -> (binding::Group, u64) {
+        // Pixel art stays sharp up close, and may be blurred in the distance
+        let block_sampler = SamplerOptions {
+            mip_bias,
+            ..SamplerOptions::nearest()
+        };
+        let diffuse_texture = Texture::array(
+            device,
+            queue,
+            &textures.tiles,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            block_sampler,
+            Some("block_texture_array"),
+        );
 
-        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[Vertex::BUFFER_LAYOUT],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+        // Normals are data, not color, so they must not be gamma corrected
+        let normal_texture = Texture::array(
+            device,
+            queue,
+            &textures.normals,
+            wgpu::TextureFormat::Rgba8Unorm,
+            block_sampler,
+            Some("normal_map_array"),
+        );
+
+        let animations_buffer = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some("Texture Animations Buffer"),
+                usage: wgpu::BufferUsages::UNIFORM,
+                contents: &[textures.animations],
             },
-            multiview: None,
-        })
-    }
+        );
 
-    /// Get vertex data.
-    /// Returns a (vertex buffer, index buffer) pair.
-    pub fn get_data(device: &wgpu::Device) -> (Buffer, Buffer) {
-        const VERTICES: &[Vertex] = &[
-            Vertex {
-                position: [0.5, 0.5, 0.0],
-                texture: [1.0, 1.0],
+        let block_texture_memory =
+            diffuse_texture.byte_size() + normal_texture.byte_size() + animations_buffer.byte_len();
+        let entries = [
+            binding::group::Entry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
+                },
+                resource: wgpu::BindingResource::TextureView(diffuse_texture.view()),
             },
-            Vertex {
-                position: [-0.5, 0.5, 0.0],
-                texture: [-1.0, 1.0],
+            binding::group::Entry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                resource: wgpu::BindingResource::Sampler(diffuse_texture.sampler()),
             },
-            Vertex {
-                position: [-0.5, -0.5, 0.0],
-                texture: [-1.0, -1.0],
+            binding::group::Entry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
+                },
+                resource: wgpu::BindingResource::TextureView(normal_texture.view()),
             },
-            Vertex {
-                position: [0.5, -0.5, 0.0],
-                texture: [1.0, -1.0],
+            binding::group::Entry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                resource: animations_buffer.inner().as_entire_binding(),
             },
         ];
 
-        const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+        let group = match layout {
+            Some(layout) => {
+                let entries = entries.map(|entry| wgpu::BindGroupEntry {
+                    binding: entry.binding,
+                    resource: entry.resource,
+                });
+                let inner = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("diffuse_texture_group"),
+                    layout: &layout,
+                    entries: &entries,
+                });
+                // SAFETY: the layout was created from identical entries
+                unsafe { binding::Group::from_raw(inner, layout) }
+            }
+            None => binding::Group::new(device, Some("diffuse_texture_group"), entries.into_iter()),
+        };
+        (group, block_texture_memory)
+    }
 
-        let vbo = Buffer::new(
-            device,
-            &BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                usage: wgpu::BufferUsages::VERTEX,
-                contents: VERTICES,
-            },
+    /// Upload the block textures in place of the placeholder, if they have
+    /// finished decoding.
+    fn poll_block_textures(&mut self) {
+        if let Some(textures) = self.texture_loader.poll() {
+            self.upload_block_textures(&textures);
+        }
+    }
+
+    /// Block until the block textures have finished decoding, and upload them.
+    ///
+    /// Until then, blocks are drawn with a magenta placeholder. Does nothing
+    /// once they are loaded.
+    pub fn wait_for_textures(&mut self) {
+        if let Some(textures) = self.texture_loader.wait() {
+            self.upload_block_textures(&textures);
+        }
+    }
+
+    #[profiling::function]
+    fn upload_block_textures(&mut self, textures: &BlockTextures) {
+        (self.diffuse_bind_group, self.block_texture_memory) = Self::create_block_texture_group(
+            &self.device,
+            &self.queue,
+            textures,
+            self.settings.mip_bias,
+            Some(self.diffuse_bind_group.clone_layout()),
         );
+        self.crack_layer = textures.crack_layer;
+        // Rebuilt with the new layers by the next update
+        self.crack_mesh = None;
+        tracing::debug!("block textures uploaded, {} layers", textures.tiles.len());
+    }
 
-        let ibo = Buffer::new(
-            device,
-            &BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                usage: wgpu::BufferUsages::INDEX,
-                contents: INDICES,
-            },
+    /// Switch to an adapter with another power preference, like
+    /// [`wgpu::PowerPreference::LowPower`] to save battery.
+    ///
+    /// This is a heavy operation: the surface, device and every GPU resource
+    /// (buffers, textures and pipelines) are created again, and all chunks are
+    /// remeshed. The world, camera, lights and other settings are kept, but
+    /// viewports added with [`Renderer::add_viewport`] are removed.
+    ///
+    /// `window` must be the window the renderer was created with.
+    pub async fn set_power_preference(
+        &mut self,
+        window: &Window,
+        preference: wgpu::PowerPreference,
+    ) {
+        if preference == self.settings.power_preference {
+            return;
+        }
+
+        let settings = RendererConfig {
+            power_preference: preference,
+            ..self.config()
+        };
+        let world = std::mem::take(&mut self.world);
+        let mut renderer = Self::with_world(window, settings, world).await;
+
+        // Carry over everything that doesn't live on the GPU
+        std::mem::swap(&mut renderer.camera, &mut self.camera);
+        std::mem::swap(&mut renderer.camera_controller, &mut self.camera_controller);
+        std::mem::swap(&mut renderer.player, &mut self.player);
+        renderer.camera_scripted = self.camera_scripted;
+        renderer.input_state = self.input_state;
+        renderer.focused = self.focused;
+        renderer.paused_on_focus_loss = self.paused_on_focus_loss;
+        std::mem::swap(&mut renderer.point_lights, &mut self.point_lights);
+        std::mem::swap(&mut renderer.stats, &mut self.stats);
+        std::mem::swap(&mut renderer.frame_limiter, &mut self.frame_limiter);
+        #[cfg(feature = "clipboard")]
+        std::mem::swap(&mut renderer.clipboard, &mut self.clipboard);
+        renderer.fog = self.fog;
+        renderer.day_night = self.day_night;
+        renderer.grass_color = self.grass_color;
+        renderer.elapsed = self.elapsed;
+        renderer.show_chunk_borders = self.show_chunk_borders;
+        renderer.show_chunk_colors = self.show_chunk_colors;
+        renderer
+            .point_lights_buffer
+            .write(&renderer.queue, 0, &[renderer.point_lights.uniform()]);
+
+        // Dropping the old renderer releases its device
+        *self = renderer;
+    }
+
+    /// Graphics APIs to pick adapters from, preferring the `WGPU_BACKEND`
+    /// environment variable over the config.
+    fn backends(settings: &RendererConfig) -> wgpu::Backends {
+        let (backends, source) = match wgpu::util::backend_bits_from_env() {
+            Some(backends) => (backends, "WGPU_BACKEND"),
+            None => (settings.backends, "config"),
+        };
+
+        if backends.is_empty() {
+            tracing::warn!("no known backends selected by {source}, using any backend");
+            return wgpu::Backends::all();
+        }
+        tracing::info!("backends selected by {source}: {backends:?}");
+        backends
+    }
+
+    /// Find an adapter that can present to the surface.
+    ///
+    /// Tries an adapter with the preferred power usage first, then the other
+    /// kind, and finally the fallback (software) adapter.
+    async fn request_adapter(
+        instance: &wgpu::Instance,
+        surface: &wgpu::Surface,
+        preference: wgpu::PowerPreference,
+    ) -> Option<wgpu::Adapter> {
+        let high = (
+            "high-performance",
+            wgpu::PowerPreference::HighPerformance,
+            false,
         );
+        let low = ("low-power", wgpu::PowerPreference::LowPower, false);
+        let fallback = ("fallback", wgpu::PowerPreference::LowPower, true);
+
+        let tiers = match preference {
+            wgpu::PowerPreference::HighPerformance => [high, low, fallback],
+            wgpu::PowerPreference::LowPower => [low, high, fallback],
+        };
+
+        for (name, power_preference, force_fallback_adapter) in tiers {
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference,
+                    force_fallback_adapter,
+                    compatible_surface: Some(surface),
+                })
+                .await;
 
-        (vbo, ibo)
+            match adapter {
+                Some(adapter) => {
+                    let info = adapter.get_info();
+                    tracing::info!("using {name} adapter: {} ({:?})", info.name, info.backend);
+                    return Some(adapter);
+                }
+                None => tracing::warn!("no {name} adapter available"),
+            }
+        }
+
+        None
     }
 
-    /// Resize the render surface.
-    pub fn resize(&mut self, new: winit::dpi::PhysicalSize<u32>) {
-        if new.width > 0 && new.height > 0 {
-            self.size = new;
-            self.config.width = new.width;
-            self.config.height = new.height;
-            self.surface.configure(&self.device, &self.config);
+    /// Pick a surface format, preferring one with sRGB encoding.
+    ///
+    /// Textures are authored in sRGB and decoded to linear when sampled, and
+    /// shading happens in linear space. An sRGB surface encodes the result for
+    /// display; on a linear surface the shaders encode it themselves instead.
+    fn choose_surface_format(
+        surface: &wgpu::Surface,
+        adapter: &wgpu::Adapter,
+    ) -> wgpu::TextureFormat {
+        let formats = surface.get_supported_formats(adapter);
+
+        match formats.iter().find(|f| f.describe().srgb) {
+            Some(format) => *format,
+            None => {
+                tracing::info!(
+                    "no sRGB surface format available, using {:?} and encoding in shaders",
+                    formats[0]
+                );
+                formats[0]
+            }
         }
     }
 
-    pub fn input(&mut self, _: &WindowEvent) -> bool {
-        false
+    /// Use the requested present mode if the surface supports it, or FIFO otherwise.
+    fn choose_present_mode(
+        surface: &wgpu::Surface,
+        adapter: &wgpu::Adapter,
+        requested: wgpu::PresentMode,
+    ) -> wgpu::PresentMode {
+        if surface.get_supported_modes(adapter).contains(&requested) {
+            requested
+        } else {
+            // FIFO is guaranteed to be supported
+            tracing::warn!("present mode {requested:?} unsupported, falling back to FIFO");
+            wgpu::PresentMode::Fifo
+        }
     }
 
-    pub fn update(&mut self) {}
+    /// Create the depth buffer, and the multisampled color buffer if MSAA is enabled.
+    fn create_framebuffers(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> (Texture, Option<Texture>) {
+        let depth = Texture::depth(
+            device,
+            config.width,
+            config.height,
+            sample_count,
+            Some("depth_texture"),
+        );
+        let msaa = (sample_count > 1).then(|| {
+            Texture::multisampled(
+                device,
+                config.width,
+                config.height,
+                config.format,
+                sample_count,
+                Some("msaa_texture"),
+            )
+        });
 
-    #[profiling::function]
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+        (depth, msaa)
+    }
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// Create the framebuffers of the main view, at the surface's size times
+    /// the render scale.
+    ///
+    /// The scaled frame is only created when the scale isn't 1, otherwise the
+    /// world is rendered to the surface directly.
+    fn create_scaled_framebuffers(
+        device: &wgpu::Device,
+        blitter: &Blitter,
+        config: &wgpu::SurfaceConfiguration,
+        settings: &RendererConfig,
+    ) -> (Texture, Option<Texture>, Option<ScaledFrame>) {
+        let (width, height) = blit::scaled_size(config.width, config.height, settings.render_scale);
+        let scaled_config = wgpu::SurfaceConfiguration {
+            width,
+            height,
+            ..config.clone()
+        };
+        let (depth, msaa) =
+            Self::create_framebuffers(device, &scaled_config, settings.sample_count);
+        let frame = ((width, height) != (config.width, config.height))
+            .then(|| ScaledFrame::new(device, blitter, width, height, config.format));
 
-        // An encoder records GPU operations to obtain a command buffer
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+        (depth, msaa, frame)
+    }
 
-        {
-            // `render_pass` is an in-progress recording of a render pass.
-            // A render pass is a GPU operation that renders an output image onto a framebuffer.
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.09,
-                            g: 0.03,
-                            b: 0.01,
-                            a: 1.00,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
+    /// Create the pipelines for block geometry from the current settings.
+    fn create_block_pipelines(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        settings: &RendererConfig,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        shader: &wgpu::ShaderModule,
+    ) -> BlockPipelines {
+        // Alpha to coverage only works with multiple samples, so without it
+        // cutouts are alpha tested instead
+        let alpha_to_coverage = settings.alpha_to_coverage && settings.sample_count > 1;
+        let mut render_pipeline =
+            PipelineBuilder::new("Render Pipeline", shader, bind_group_layouts)
+                .fragment_entry(if alpha_to_coverage {
+                    "fs_main"
+                } else {
+                    "fs_cutout"
+                })
+                .color_target(format, wgpu::BlendState::REPLACE)
+                .sample_count(settings.sample_count)
+                .reversed_z(settings.reversed_z)
+                .alpha_to_coverage(alpha_to_coverage);
+        if settings.depth_prepass {
+            // Depth is already known, only shade the nearest fragments
+            render_pipeline = render_pipeline.depth(false, wgpu::CompareFunction::Equal);
+        }
+
+        // Only writes the depth of opaque geometry, leaving the gaps in cutouts
+        let depth_prepass_pipeline = settings.depth_prepass.then(|| {
+            PipelineBuilder::new("Depth Prepass Pipeline", shader, bind_group_layouts)
+                .fragment_entry("fs_depth_cutout")
+                .sample_count(settings.sample_count)
+                .reversed_z(settings.reversed_z)
+                .build(device)
+        });
+
+        // Liquids are sorted behind opaque geometry by the depth test, but
+        // don't hide each other
+        let translucent_pipeline =
+            PipelineBuilder::new("Translucent Pipeline", shader, bind_group_layouts)
+                .vertex_entry("vs_liquid")
+                .color_target(format, wgpu::BlendState::ALPHA_BLENDING)
+                .sample_count(settings.sample_count)
+                .reversed_z(settings.reversed_z)
+                .depth(false, wgpu::CompareFunction::Less)
+                .build(device);
+
+        // Drawn over the faces of a block, so that it doesn't hide it
+        let crack_pipeline = PipelineBuilder::new("Crack Pipeline", shader, bind_group_layouts)
+            .fragment_entry("fs_crack")
+            .color_target(format, wgpu::BlendState::ALPHA_BLENDING)
+            .sample_count(settings.sample_count)
+            .reversed_z(settings.reversed_z)
+            .depth(false, wgpu::CompareFunction::Less)
+            .build(device);
+
+        // Tests the boxes around chunks against the finished depth buffer. Both
+        // sides are drawn, so boxes are still tested with the eye inside them.
+        let occlusion_pipeline = device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+            .then(|| {
+                PipelineBuilder::new("Occlusion Pipeline", shader, bind_group_layouts)
+                    .fragment_entry("fs_occlusion")
+                    .cull_mode(None)
+                    .sample_count(settings.sample_count)
+                    .reversed_z(settings.reversed_z)
+                    .depth(false, wgpu::CompareFunction::LessEqual)
+                    .build(device)
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, self.diffuse_bind_group.inner(), &[]);
-            render_pass.set_vertex_buffer(0, self.vbo.inner().slice(..));
-            render_pass.set_index_buffer(self.ibo.inner().slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..self.ibo.len(), 0, 0..1);
+        BlockPipelines {
+            opaque: render_pipeline.build(device),
+            translucent: translucent_pipeline,
+            depth_prepass: depth_prepass_pipeline,
+            crack: crack_pipeline,
+            occlusion: occlusion_pipeline,
         }
+    }
 
-        // Submit the command buffer to the command queue
-        self.queue.submit([encoder.finish()]);
+    /// Rebuild the block pipelines from the current settings.
+    ///
+    /// Needed after changing a setting the pipelines are built with.
+    pub fn reload_pipeline(&mut self) {
+        self.pipelines = Self::create_block_pipelines(
+            &self.device,
+            self.config.format,
+            &self.settings,
+            &self.block_bind_group_layouts(),
+            &self.shader,
+        );
+    }
 
-        // Present this texture on the surface
-        output.present();
+    /// Layouts of the bind groups used by the block pipelines.
+    fn block_bind_group_layouts(&self) -> [&wgpu::BindGroupLayout; 3] {
+        [
+            self.diffuse_bind_group.layout(),
+            self.globals_bind_group.layout(),
+            self.chunk_uniforms.layout(),
+        ]
+    }
 
-        profiling::finish_frame!();
-        Ok(())
+    /// Gather the uniform data shared by all shaders.
+    #[allow(clippy::too_many_arguments)]
+    fn globals(
+        camera: &Camera,
+        fog: &Fog,
+        day_night: &DayNightCycle,
+        grass_color: wgpu::Color,
+        chunk_tint: bool,
+        time: f32,
+        encode_srgb: bool,
+        settings: &RendererConfig,
+    ) -> Globals {
+        let sun = day_night.sun_direction();
+        Globals {
+            view_proj: camera.view_projection().into(),
+            view: camera.view_matrix().into(),
+            fog_color: color_to_array(day_night.sky_color(fog.color)),
+            fog_start: fog.start,
+            fog_end: fog.end,
+            chunk_tint: if chunk_tint { 1.0 } else { 0.0 },
+            time,
+            grass_color: color_to_array(grass_color),
+            sun: [sun.x, sun.y, sun.z, day_night.daylight()],
+            encode_srgb: if encode_srgb { 1.0 } else { 0.0 },
+            mip_bias: settings.mip_bias,
+            ambient: settings.ambient,
+            _padding: 0.0,
+        }
+    }
+
+    /// Show the world in another window, from its own camera.
+    ///
+    /// The viewport shares the device, pipelines and world with the main
+    /// window, and is drawn by [`Renderer::render_viewport`]. Outlines and the
+    /// crosshair are only drawn in the main window.
+    ///
+    /// The camera's depth range is switched to the one the pipelines are built
+    /// for, see [`RendererConfig::reversed_z`].
+    ///
+    /// Returns `None` if the window's surface doesn't support the main
+    /// surface's format, which the pipelines are built for.
+    ///
+    /// ## Safety
+    ///
+    /// The viewport draws to a surface of `window`, so it must be removed with
+    /// [`Renderer::remove_viewport`], or the renderer dropped, before `window` is.
+    pub unsafe fn add_viewport(
+        &mut self,
+        window: &Window,
+        mut camera: Camera,
+    ) -> Option<ViewportId> {
+        // SAFETY: the caller removes the viewport before the window is dropped
+        let surface = unsafe { self.instance.create_surface(window) };
+        if !surface
+            .get_supported_formats(&self.adapter)
+            .contains(&self.config.format)
+        {
+            tracing::warn!(
+                "viewport surface doesn't support {:?}, not adding it",
+                self.config.format
+            );
+            return None;
+        }
+
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            width: size.width.max(1),
+            height: size.height.max(1),
+            ..self.config.clone()
+        };
+        surface.configure(&self.device, &config);
+
+        let (depth_texture, msaa_texture) =
+            Self::create_framebuffers(&self.device, &config, self.settings.sample_count);
+        let globals = Buffer::zeroed::<Globals>(
+            &self.device,
+            Some("Viewport Globals Buffer"),
+            1,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        let globals_bind_group = Self::create_globals_group(
+            &self.device,
+            &globals,
+            &self.point_lights_buffer,
+            Some(self.globals_bind_group.clone_layout()),
+        );
+
+        camera.reversed_z = self.settings.reversed_z;
+        let id = ViewportId(self.next_viewport_id);
+        self.next_viewport_id += 1;
+        self.viewports.insert(
+            id,
+            Viewport {
+                surface,
+                config,
+                camera,
+                depth_texture,
+                msaa_texture,
+                globals,
+                globals_bind_group,
+            },
+        );
+        Some(id)
+    }
+
+    /// Remove a viewport, returning whether it existed.
+    ///
+    /// Must be called before the viewport's window is dropped, see
+    /// [`Renderer::add_viewport`].
+    pub fn remove_viewport(&mut self, id: ViewportId) -> bool {
+        self.viewports.remove(&id).is_some()
+    }
+
+    /// Resize the surface of a viewport, after its window was resized.
+    pub fn resize_viewport(&mut self, id: ViewportId, new: winit::dpi::PhysicalSize<u32>) {
+        let viewport = match self.viewports.get_mut(&id) {
+            Some(viewport) if new.width > 0 && new.height > 0 => viewport,
+            _ => return,
+        };
+
+        viewport.config.width = new.width;
+        viewport.config.height = new.height;
+        viewport.surface.configure(&self.device, &viewport.config);
+        viewport.camera.resize(new.width, new.height);
+        (viewport.depth_texture, viewport.msaa_texture) =
+            Self::create_framebuffers(&self.device, &viewport.config, self.settings.sample_count);
+    }
+
+    /// Get a mutable handle to the camera of a viewport, if it exists.
+    #[inline]
+    pub fn viewport_camera_mut(&mut self, id: ViewportId) -> Option<&mut Camera> {
+        self.viewports
+            .get_mut(&id)
+            .map(|viewport| &mut viewport.camera)
+    }
+
+    /// Create the bind group for the globals and point light uniforms.
+    ///
+    /// `layout` is reused if given, so that existing pipelines remain compatible.
+    fn create_globals_group(
+        device: &wgpu::Device,
+        globals: &Buffer,
+        point_lights: &Buffer,
+        layout: Option<Arc<wgpu::BindGroupLayout>>,
+    ) -> binding::Group {
+        let entries = [
+            binding::group::Entry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                resource: globals.inner().as_entire_binding(),
+            },
+            binding::group::Entry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                resource: point_lights.inner().as_entire_binding(),
+            },
+        ];
+
+        match layout {
+            Some(layout) => {
+                let entries = entries.map(|entry| wgpu::BindGroupEntry {
+                    binding: entry.binding,
+                    resource: entry.resource,
+                });
+                let inner = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("globals_group"),
+                    layout: &layout,
+                    entries: &entries,
+                });
+                // SAFETY: the layout was created from identical entries
+                unsafe { binding::Group::from_raw(inner, layout) }
+            }
+            None => binding::Group::new(device, Some("globals_group"), entries.into_iter()),
+        }
+    }
+
+    /// Remesh every loaded chunk, after the world has been replaced.
+    ///
+    /// Without compute meshing, chunks are meshed in the background and appear
+    /// as they finish.
+    #[profiling::function]
+    fn rebuild_chunk_geometry(&mut self) {
+        #[cfg(feature = "gpu-meshing")]
+        if let Some(mesher) = &self.gpu_mesher {
+            self.chunk_geometry = ChunkGeometry::Meshes(
+                mesher.build_meshes(
+                    &self.device,
+                    &self.queue,
+                    self.world
+                        .chunks()
+                        .map(|(pos, chunk)| (*pos, chunk, self.world.neighbours(*pos))),
+                ),
+            );
+            // Liquids are still meshed on the CPU
+            profiling::scope!("build translucent meshes");
+            let translucent: Vec<_> = self
+                .world
+                .chunks()
+                .map(|(pos, chunk)| {
+                    let origin = pos.to_world_origin().into();
+                    let neighbours = self.world.neighbours(*pos);
+                    let (vertices, indices) =
+                        mesher::build_translucent_mesh(chunk, &neighbours, origin);
+                    (*pos, vertices, indices)
+                })
+                .collect();
+            self.translucent_geometry = MeshBatch::new(
+                &self.device,
+                Some("translucent_chunk_batch"),
+                translucent
+                    .iter()
+                    .map(|(pos, v, i)| (*pos, &v[..], &i[..], pos.bounds())),
+            );
+            self.update_chunk_uniforms();
+            return;
+        }
+
+        self.mesh_pool.cancel_all();
+        self.mesh_uploads.clear();
+        // Chunks still in the world keep their old geometry until they are remeshed
+        let world = &self.world;
+        let removed = match &mut self.chunk_geometry {
+            ChunkGeometry::Batch(batch) => {
+                batch.retain(&self.queue, |pos| world.chunk(*pos).is_some())
+            }
+            _ => false,
+        };
+        self.translucent_geometry
+            .retain(&self.queue, |pos| world.chunk(*pos).is_some());
+        if removed {
+            self.update_chunk_uniforms();
+        }
+        for (pos, chunk) in self.world.chunks() {
+            self.mesh_pool
+                .queue(*pos, chunk, self.world.neighbours(*pos));
+        }
+    }
+
+    /// Remesh the chunks touched by an edit.
+    fn remesh_chunks(&mut self, positions: &[ChunkCoord]) {
+        #[cfg(feature = "gpu-meshing")]
+        if self.gpu_mesher.is_some() {
+            return self.rebuild_chunk_geometry();
+        }
+
+        for &pos in positions {
+            if let Some(chunk) = self.world.chunk(pos) {
+                self.mesh_pool.queue(pos, chunk, self.world.neighbours(pos));
+            }
+        }
+    }
+
+    /// Upload meshes finished by the background workers, up to the per-frame
+    /// budget.
+    ///
+    /// Each mesh only replaces its own chunk's geometry and draw commands in the
+    /// batches, leaving every other chunk as it is. The chunk uniforms are only
+    /// rebuilt when chunks are added or removed.
+    #[profiling::function]
+    fn upload_chunk_meshes(&mut self) {
+        self.mesh_uploads.extend(self.mesh_pool.poll());
+        if self.mesh_uploads.is_empty() {
+            return;
+        }
+
+        let batch = self.chunk_geometry.batch_mut(&self.device);
+        let mut order_changed = false;
+        for mesh in self.mesh_uploads.take_frame() {
+            // Drop meshes of chunks that were unloaded while they were being built
+            if self.world.chunk(mesh.pos).is_none() {
+                continue;
+            }
+            let aabb = mesh.pos.bounds();
+            order_changed |= batch.set(
+                &self.device,
+                &self.queue,
+                mesh.pos,
+                &mesh.vertices,
+                &mesh.indices,
+                aabb,
+            );
+            self.translucent_geometry.set(
+                &self.device,
+                &self.queue,
+                mesh.pos,
+                &mesh.translucent_vertices,
+                &mesh.translucent_indices,
+                aabb,
+            );
+        }
+        let chunks = batch.len();
+
+        if order_changed {
+            self.update_chunk_uniforms();
+        }
+        if self.mesh_uploads.is_empty() {
+            tracing::debug!(
+                "{chunks} chunks uploaded, about {:.1} MiB of GPU memory in use",
+                self.gpu_memory_estimate() as f64 / (1024.0 * 1024.0)
+            );
+        }
+    }
+
+    /// Give every chunk mesh its model matrix and debug color, in draw order,
+    /// and hand the chunks to the occlusion culler.
+    ///
+    /// Chunk meshes are built in world space, so their model matrix is the
+    /// identity.
+    #[profiling::function]
+    fn update_chunk_uniforms(&mut self) {
+        let aabbs = self.chunk_geometry.aabbs();
+        if let Some(culler) = &mut self.occlusion {
+            culler.set_chunks(&self.device, &aabbs);
+        }
+
+        let uniforms: Vec<_> = aabbs
+            .into_iter()
+            .map(|(min, _)| ChunkUniform {
+                model: glm::Mat4::identity().into(),
+                tint: chunk_debug_color(ChunkCoord::from_world_pos(min)),
+            })
+            .collect();
+        self.chunk_uniforms = Self::create_chunk_uniforms(
+            &self.device,
+            &uniforms,
+            self.chunk_uniform_stride,
+            Some(self.chunk_uniforms.clone_layout()),
+        );
+    }
+
+    /// Distance in bytes between per-chunk uniforms, respecting dynamic offset alignment.
+    fn chunk_uniform_stride(device: &wgpu::Device) -> wgpu::BufferAddress {
+        let align = device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let size = std::mem::size_of::<ChunkUniform>() as wgpu::BufferAddress;
+        size.next_multiple_of(align)
+    }
+
+    /// Upload the uniforms of every chunk mesh into one buffer, bound with a
+    /// dynamic offset per draw.
+    ///
+    /// `layout` is reused if given, so that existing pipelines remain compatible.
+    fn create_chunk_uniforms(
+        device: &wgpu::Device,
+        uniforms: &[ChunkUniform],
+        stride: wgpu::BufferAddress,
+        layout: Option<Arc<wgpu::BindGroupLayout>>,
+    ) -> binding::Group {
+        // Always hold at least one entry, since the group is bound even without chunks
+        let size = std::mem::size_of::<ChunkUniform>();
+        let count = uniforms.len().max(1);
+        let mut contents = vec![0u8; count * stride as usize];
+        for (i, uniform) in uniforms.iter().enumerate() {
+            let offset = i * stride as usize;
+            contents[offset..offset + size].copy_from_slice(bytemuck::bytes_of(uniform));
+        }
+        let buffer = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some("Chunk Uniforms Buffer"),
+                usage: wgpu::BufferUsages::UNIFORM,
+                contents: &contents,
+            },
+        );
+
+        let entry = binding::group::Entry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(size as u64),
+            },
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: buffer.inner(),
+                offset: 0,
+                size: wgpu::BufferSize::new(size as u64),
+            }),
+        };
+
+        match layout {
+            Some(layout) => {
+                let inner = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("chunk_uniforms_group"),
+                    layout: &layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: entry.binding,
+                        resource: entry.resource,
+                    }],
+                });
+                // SAFETY: the layout was created from an identical entry
+                unsafe { binding::Group::from_raw(inner, layout) }
+            }
+            None => binding::Group::new(device, Some("chunk_uniforms_group"), [entry].into_iter()),
+        }
+    }
+
+    /// Whether a chunk enclosed by `aabb` is skipped when drawing `scene`,
+    /// because the last occlusion queries found it hidden.
+    ///
+    /// Only the main camera is queried, so other scenes draw every chunk.
+    fn chunk_occluded(&self, scene: Scene, aabb: (glm::Vec3, glm::Vec3)) -> bool {
+        scene == Scene::Main
+            && self
+                .occlusion
+                .as_ref()
+                .is_some_and(|culler| culler.is_hidden(aabb, self.camera.position))
+    }
+
+    /// Record draws of every chunk not occluded in `scene`.
+    ///
+    /// Each chunk's uniforms are bound before it is drawn. Only with multi-draw,
+    /// without chunk colors and without occlusion culling is the whole batch
+    /// drawn at once, sharing the first chunk's uniforms, which is correct while
+    /// every chunk has the same model matrix.
+    #[profiling::function]
+    fn draw_chunks<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, scene: Scene) {
+        let uniforms = self.chunk_uniforms.inner();
+        let offset = |i: usize| (i as wgpu::BufferAddress * self.chunk_uniform_stride) as u32;
+        let culled = scene == Scene::Main && self.occlusion.is_some();
+
+        match &self.chunk_geometry {
+            ChunkGeometry::Empty => {}
+            #[cfg(feature = "gpu-meshing")]
+            ChunkGeometry::Meshes(meshes) => {
+                for (i, mesh) in meshes.iter().enumerate() {
+                    if self.chunk_occluded(scene, mesh.aabb()) {
+                        continue;
+                    }
+                    render_pass.set_bind_group(2, uniforms, &[offset(i)]);
+                    mesh.draw(render_pass);
+                }
+            }
+            ChunkGeometry::Batch(batch) if self.show_chunk_colors || !self.multi_draw || culled => {
+                batch.bind(render_pass);
+                for (i, &aabb) in batch.aabbs().iter().enumerate() {
+                    if self.chunk_occluded(scene, aabb) {
+                        continue;
+                    }
+                    render_pass.set_bind_group(2, uniforms, &[offset(i)]);
+                    batch.draw_mesh(render_pass, i);
+                }
+            }
+            ChunkGeometry::Batch(batch) => {
+                render_pass.set_bind_group(2, uniforms, &[0]);
+                batch.draw(render_pass, self.multi_draw);
+            }
+        }
+    }
+
+    /// Resize the render surface.
+    pub fn resize(&mut self, new: winit::dpi::PhysicalSize<u32>) {
+        if new.width > 0 && new.height > 0 {
+            self.size = new;
+            self.config.width = new.width;
+            self.config.height = new.height;
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            self.camera.resize(new.width, new.height);
+            self.ui_renderer.resize(&self.queue, new.width, new.height);
+            self.recreate_framebuffers();
+            for target in self.render_targets.values_mut() {
+                target.resize(&self.device, new.width, new.height);
+            }
+        }
+    }
+
+    fn recreate_framebuffers(&mut self) {
+        (self.depth_texture, self.msaa_texture, self.scaled_frame) =
+            Self::create_scaled_framebuffers(
+                &self.device,
+                &self.blitter,
+                &self.config,
+                &self.settings,
+            );
+    }
+
+    /// Configure the surface again with the current settings.
+    ///
+    /// Needed when the surface is outdated, for example after the display it
+    /// is shown on changes. A surface that was lost entirely needs
+    /// [`Renderer::recreate_surface`] instead.
+    pub fn reconfigure(&mut self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    /// Create the window's surface again, after the old one was destroyed, like
+    /// when the app is resumed on mobile or a display is reconnected.
+    ///
+    /// The device, pipelines and every other GPU resource are kept, so the new
+    /// surface must support the format the pipelines are built for. If it
+    /// doesn't, the renderer is left without a surface and draws nothing until
+    /// a later call succeeds.
+    ///
+    /// Returns whether there is a surface to render to.
+    ///
+    /// ## Safety
+    ///
+    /// `window` must outlive the renderer, or the surface be released with
+    /// [`Renderer::release_surface`] before `window` is dropped.
+    pub unsafe fn recreate_surface(&mut self, window: &Window) -> bool {
+        // Some platforms refuse a second surface for a window that still has one
+        self.surface = None;
+
+        // SAFETY: the caller keeps the window alive for as long as the surface
+        let surface = unsafe { self.instance.create_surface(window) };
+        if !surface
+            .get_supported_formats(&self.adapter)
+            .contains(&self.config.format)
+        {
+            tracing::error!(
+                "recreated surface doesn't support {:?}, not rendering",
+                self.config.format
+            );
+            return false;
+        }
+        self.config.present_mode =
+            Self::choose_present_mode(&surface, &self.adapter, self.settings.present_mode);
+        self.surface = Some(surface);
+
+        // The window may have changed size while it had no surface
+        let size = window.inner_size();
+        if size != self.size && size.width > 0 && size.height > 0 {
+            self.resize(size);
+        } else {
+            self.reconfigure();
+        }
+        true
+    }
+
+    /// Destroy the window's surface, before the window is, like when the app is
+    /// suspended on mobile.
+    ///
+    /// Nothing is rendered until [`Renderer::recreate_surface`] is called.
+    pub fn release_surface(&mut self) {
+        self.surface = None;
+    }
+
+    /// Whether there is a surface to render to.
+    #[inline]
+    pub fn has_surface(&self) -> bool {
+        self.surface.is_some()
+    }
+
+    /// Handle a window event.
+    ///
+    /// Events are routed by the [`InputState`]: Escape pauses the game or
+    /// leaves a menu, and game controls only respond while in game.
+    ///
+    /// Returns `true` if the event was consumed.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        #[cfg(feature = "debug-ui")]
+        if self.debug_ui.input(event) {
+            return true;
+        }
+
+        match event {
+            // Not consumed, so that the new size still reaches `resize`
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.set_scale_factor(*scale_factor as f32);
+                false
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                self.set_input_state(self.input_state.escape());
+                true
+            }
+            // Not consumed, so that the caller can stop redrawing while unfocused
+            WindowEvent::Focused(focused) => {
+                self.set_focused(*focused);
+                false
+            }
+            _ if self.input_state.is_in_game() => self.game_input(event),
+            _ => false,
+        }
+    }
+
+    /// Handle a window event controlling the game.
+    fn game_input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                self.show_chunk_borders = !self.show_chunk_borders;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(VirtualKeyCode::F4),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                self.show_chunk_colors = !self.show_chunk_colors;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if self.camera_controller.bindings.action(*key) == Some(Action::ToggleFly) => {
+                self.toggle_fly();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => self.camera_controller.process_keyboard(*key, *state),
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => {
+                self.mouse_pressed = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state,
+                ..
+            } => {
+                self.mining = *state == ElementState::Pressed;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// What input is currently routed to.
+    #[inline]
+    pub fn input_state(&self) -> InputState {
+        self.input_state
+    }
+
+    /// Whether the window has keyboard focus.
+    ///
+    /// Nothing changes while unfocused, so there is no need to keep rendering.
+    #[inline]
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Pause the game when the window loses focus, releasing the mouse, and
+    /// resume it when focus returns unless it was already paused.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused && self.input_state.is_in_game() {
+            self.set_input_state(InputState::Paused);
+            self.paused_on_focus_loss = true;
+        } else if focused && self.paused_on_focus_loss {
+            if self.input_state == InputState::Paused {
+                self.set_input_state(InputState::InGame);
+            }
+            self.paused_on_focus_loss = false;
+        }
+    }
+
+    /// Route input to the game, a menu, or pause the game.
+    ///
+    /// Held keys and mouse buttons are released when leaving the game, so the
+    /// camera doesn't keep moving.
+    pub fn set_input_state(&mut self, state: InputState) {
+        if self.input_state.is_in_game() && !state.is_in_game() {
+            self.camera_controller.release_all();
+            self.mouse_pressed = false;
+            self.mining = false;
+        }
+        self.input_state = state;
+    }
+
+    /// Handle relative mouse movement.
+    pub fn mouse_motion(&mut self, dx: f64, dy: f64) {
+        #[cfg(feature = "debug-ui")]
+        if self.debug_ui.wants_pointer() {
+            return;
+        }
+
+        if self.mouse_pressed && self.input_state.is_in_game() {
+            self.camera_controller.process_mouse(dx, dy);
+        }
+    }
+
+    /// Switch between flying freely and walking with physics.
+    fn toggle_fly(&mut self) {
+        self.player = match self.player {
+            Some(_) => None,
+            None => {
+                let mut player = Player::new(self.camera.position);
+                player.teleport(player.position - glm::vec3(0.0, player.eye_height, 0.0));
+                Some(player)
+            }
+        };
+    }
+
+    /// Rebuild the render pipeline from new shader source.
+    ///
+    /// Errors are logged, and the current pipeline is kept.
+    #[cfg(debug_assertions)]
+    fn reload_shader(&mut self, source: &str) {
+        let module = match shader::compile(&self.device, source) {
+            Some(module) => module,
+            None => return,
+        };
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipelines = Self::create_block_pipelines(
+            &self.device,
+            self.config.format,
+            &self.settings,
+            &self.block_bind_group_layouts(),
+            &module,
+        );
+
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(e) => tracing::error!("failed to rebuild pipeline: {e}"),
+            None => {
+                tracing::info!("reloaded shader");
+                self.pipelines = pipelines;
+                self.shader = module;
+            }
+        }
+    }
+
+    /// Advance the scene by `dt`.
+    #[profiling::function]
+    pub fn update(&mut self, dt: Duration) {
+        self.stats.record(dt);
+
+        #[cfg(debug_assertions)]
+        if let Some(source) = self.shader_watcher.as_ref().and_then(|w| w.poll()) {
+            self.reload_shader(&source);
+        }
+
+        // Time stands still while paused
+        let dt = match self.input_state {
+            InputState::Paused => 0.0,
+            InputState::InGame | InputState::Menu => dt.as_secs_f32(),
+        };
+
+        match &mut self.player {
+            _ if self.camera_scripted => {}
+            Some(player) => {
+                // Walking is simulated in fixed ticks, while looking around
+                // follows every frame
+                let step = self.ticks.step();
+                for _ in 0..self.ticks.advance(dt) {
+                    self.camera_controller
+                        .steer_player(player, self.camera.yaw, step);
+                    player.physics_step(step, &self.world);
+                }
+                self.camera_controller.update_view(&mut self.camera, dt);
+                // Between the last two ticks, so motion is smooth at any frame rate
+                self.camera.position = player.interpolated_eye_position(self.ticks.alpha());
+            }
+            None => self.camera_controller.update_camera(&mut self.camera, dt),
+        }
+
+        self.elapsed += Duration::from_secs_f32(dt);
+        self.day_night.advance(dt);
+        self.write_globals();
+
+        if let Some(lights) = self.point_lights.take_uniform() {
+            self.point_lights_buffer.write(&self.queue, 0, &[lights]);
+        }
+
+        self.upload_chunk_meshes();
+        self.poll_block_textures();
+
+        self.target = self
+            .world
+            .raycast(self.camera.position, self.camera.forward(), REACH);
+        if let Some(target) = self.target {
+            self.draw_block_outline(target.block);
+        }
+        self.place_preview =
+            self.world
+                .raycast_place_preview(self.camera.position, self.camera.forward(), REACH);
+        if let Some(pos) = self.place_preview {
+            self.draw_place_preview(pos);
+        }
+        self.update_breaking(dt);
+
+        if self.show_chunk_borders {
+            self.draw_chunk_borders();
+        }
+        self.line_renderer.prepare(&self.device, &self.queue);
+        self.billboard_renderer.prepare(&self.device, &self.queue);
+    }
+
+    /// Mine the targeted block while the right mouse button is held, breaking it
+    /// once it has been mined for long enough, and keep its cracks up to date.
+    ///
+    /// Letting go or looking at another block starts over. Unbreakable blocks
+    /// don't crack at all.
+    fn update_breaking(&mut self, dt: f32) {
+        match self.target.filter(|_| self.mining) {
+            Some(hit) => {
+                let [x, y, z] = hit.block;
+                if self
+                    .break_progress
+                    .mine(hit.block, self.world.block(x, y, z), dt)
+                {
+                    self.set_block(x, y, z, BlockId::AIR);
+                }
+            }
+            None => self.break_progress.reset(),
+        }
+
+        let stage = self.break_progress.stage();
+        if stage == self.crack_mesh.as_ref().map(|(stage, _)| *stage) {
+            return;
+        }
+        self.crack_mesh = stage.map(|(block, i)| {
+            // Enlarged like the block outline, to stay in front of the block's faces
+            let (vertices, indices) =
+                mesher::build_overlay_cube(block, self.crack_layer + i, 0.002);
+            let min = glm::vec3(block[0] as f32, block[1] as f32, block[2] as f32);
+            let mesh = Mesh::new(
+                &self.device,
+                Some("crack"),
+                &vertices,
+                &indices,
+                (min, min.add_scalar(1.0)),
+            );
+            ((block, i), mesh)
+        });
+    }
+
+    /// Outline a block, slightly enlarged so the lines aren't hidden by its faces.
+    fn draw_block_outline(&mut self, [x, y, z]: [i32; 3]) {
+        const INFLATE: f32 = 0.002;
+        let min = glm::vec3(x as f32, y as f32, z as f32);
+        self.line_renderer.draw_aabb(
+            min.add_scalar(-INFLATE),
+            min.add_scalar(1.0 + INFLATE),
+            [0.0, 0.0, 0.0, 1.0],
+        );
+    }
+
+    /// Draw a translucent ghost of a block about to be placed.
+    fn draw_place_preview(&mut self, [x, y, z]: [i32; 3]) {
+        // Shrink slightly so the ghost doesn't fight with the face it's placed against
+        const INSET: f32 = 0.002;
+        let min = glm::vec3(x as f32, y as f32, z as f32);
+        self.line_renderer.draw_box(
+            min.add_scalar(INSET),
+            min.add_scalar(1.0 - INSET),
+            [1.0, 1.0, 1.0, 0.25],
+        );
+    }
+
+    /// Outline the chunk containing the camera and its horizontal neighbours.
+    fn draw_chunk_borders(&mut self) {
+        let center = ChunkCoord::from_world_pos(self.camera.position);
+
+        for pos in center.horizontal_square(1) {
+            let (color, offset) = if pos == center {
+                ([1.0, 1.0, 0.0, 1.0], 0.0)
+            } else {
+                ([0.0, 0.6, 1.0, 1.0], 0.01)
+            };
+            // Shrink neighbours slightly so shared edges stay the center's color
+            let (min, max) = pos.bounds();
+            self.line_renderer
+                .draw_aabb(min.add_scalar(offset), max.add_scalar(-offset), color);
+        }
+    }
+
+    /// Draw the debug overlay on top of `view`, and apply changes made through it.
+    #[cfg(feature = "debug-ui")]
+    fn render_debug_ui(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let draws = match &self.chunk_geometry {
+            ChunkGeometry::Empty => 0,
+            ChunkGeometry::Batch(_) if self.multi_draw && self.occlusion.is_none() => 1,
+            geometry => geometry
+                .aabbs()
+                .into_iter()
+                .filter(|&aabb| !self.chunk_occluded(Scene::Main, aabb))
+                .count(),
+        };
+        let stats = debug_ui::DebugStats {
+            fps: self.stats.fps(),
+            frame_time: self.stats.frame_time(),
+            gpu_time: self.gpu_time(),
+            gpu_prepass_time: self.gpu_prepass_time(),
+            position: self.camera.position,
+            chunks: self.world.chunks().count(),
+            draws,
+            gpu_memory: self.gpu_memory_estimate(),
+        };
+        let before = debug_ui::DebugSettings {
+            render_distance: self.settings.render_distance,
+            fov: self.camera.fov(),
+            fog_start: self.fog.start,
+            fog_end: self.fog.end,
+            ambient: self.settings.ambient,
+        };
+
+        let mut after = before.clone();
+        self.debug_ui.render(
+            &self.device,
+            &self.queue,
+            encoder,
+            view,
+            [self.config.width, self.config.height],
+            &stats,
+            &mut after,
+        );
+
+        self.camera.set_fov(after.fov);
+        self.set_fog(self.fog.color, after.fog_start, after.fog_end);
+        self.set_ambient(after.ambient);
+        if after.render_distance != before.render_distance {
+            self.set_render_distance(after.render_distance);
+        }
+    }
+
+    /// Create an offscreen render target matching the size and format of the
+    /// surface.
+    ///
+    /// The renderer keeps the target, and resizes it along with the surface,
+    /// until it is removed with [`Renderer::remove_render_target`]. Get it to
+    /// render to with [`Renderer::render_target`].
+    pub fn create_render_target(&mut self, label: wgpu::Label<'_>, depth: bool) -> RenderTargetId {
+        let target = self.surface_sized_target(label, depth);
+        let id = RenderTargetId(self.next_render_target_id);
+        self.next_render_target_id += 1;
+        self.render_targets.insert(id, target);
+        id
+    }
+
+    /// Get a render target created with [`Renderer::create_render_target`].
+    #[inline]
+    pub fn render_target(&self, id: RenderTargetId) -> Option<&RenderTarget> {
+        self.render_targets.get(&id)
+    }
+
+    /// Remove a render target, returning it if it existed.
+    ///
+    /// The target is no longer resized with the surface.
+    pub fn remove_render_target(&mut self, id: RenderTargetId) -> Option<RenderTarget> {
+        self.render_targets.remove(&id)
+    }
+
+    /// Create a render target of the surface's size and format, which isn't kept
+    /// in sync with it.
+    fn surface_sized_target(&self, label: wgpu::Label<'_>, depth: bool) -> RenderTarget {
+        RenderTarget::new(
+            &self.device,
+            &RenderTargetDescriptor {
+                label,
+                width: self.config.width,
+                height: self.config.height,
+                format: self.config.format,
+                depth,
+            },
+        )
+    }
+
+    /// Get the format of the surface being rendered to.
+    #[inline]
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Change the number of chunks loaded on each side of the origin.
+    ///
+    /// Regenerates the world, and moves the fog to fade out at its new edge.
+    pub fn set_render_distance(&mut self, distance: u32) {
+        self.settings.render_distance = distance;
+        self.world = World::generate(
+            self.world.seed(),
+            distance as i32,
+            self.settings.world_height,
+        );
+        self.rebuild_chunk_geometry();
+
+        let fog_end = (distance as usize * CHUNK_SIZE) as f32;
+        self.set_fog(self.fog.color, fog_end * 0.6, fog_end);
+    }
+
+    /// Set the color and view-space depth range of the distance fog.
+    ///
+    /// The fog color should usually match the clear color, so that distant
+    /// geometry blends seamlessly into the sky. Like the clear color, it is the
+    /// color during the day, and is tinted by the time of day.
+    ///
+    /// `end` is moved just past `start` if it isn't already beyond it.
+    pub fn set_fog(&mut self, color: wgpu::Color, start: f32, end: f32) {
+        self.fog = Fog::new(color, start, end);
+    }
+
+    /// Get the time of day, from 0 to 1.
+    ///
+    /// See [`DayNightCycle`] for how it maps to the position of the sun.
+    #[inline]
+    pub fn time_of_day(&self) -> f32 {
+        self.day_night.time_of_day()
+    }
+
+    /// Jump to a time of day, from 0 to 1.
+    #[inline]
+    pub fn set_time_of_day(&mut self, time: f32) {
+        self.day_night.set_time_of_day(time);
+    }
+
+    /// Set the fraction of a day passing per second. Zero stops time.
+    #[inline]
+    pub fn set_day_speed(&mut self, speed: f32) {
+        self.day_night.set_speed(speed);
+    }
+
+    /// Get the current settings, including changes made while running.
+    pub fn config(&self) -> RendererConfig {
+        RendererConfig {
+            fov: self.camera.fov(),
+            mouse_sensitivity: self.camera_controller.sensitivity,
+            invert_y: self.camera_controller.invert_y,
+            key_bindings: self.camera_controller.bindings.clone(),
+            ..self.settings.clone()
+        }
+    }
+
+    /// Replace the keys controlling the camera.
+    #[inline]
+    pub fn set_key_bindings(&mut self, bindings: KeyBindings) {
+        self.camera_controller.bindings = bindings;
+    }
+
+    /// Set the mouse look speed in radians per unit of raw mouse movement.
+    #[inline]
+    pub fn set_mouse_sensitivity(&mut self, sensitivity: f32) {
+        self.camera_controller.set_sensitivity(sensitivity);
+    }
+
+    /// Set the size of overlay elements like the crosshair.
+    ///
+    /// This is multiplied into the window's scale factor.
+    pub fn set_ui_scale(&mut self, ui_scale: f32) {
+        self.settings.ui_scale = ui_scale;
+        self.ui_renderer.set_ui_scale(&self.queue, ui_scale);
+    }
+
+    /// Get the resolution the world is rendered at, relative to the window.
+    #[inline]
+    pub fn render_scale(&self) -> f32 {
+        self.settings.render_scale
+    }
+
+    /// Set the resolution the world is rendered at, relative to the window.
+    ///
+    /// Recreates the framebuffers at the new size. See
+    /// [`RendererConfig::render_scale`].
+    pub fn set_render_scale(&mut self, scale: f32) {
+        let scale = scale.clamp(Self::MIN_RENDER_SCALE, Self::MAX_RENDER_SCALE);
+        if scale != self.settings.render_scale {
+            self.settings.render_scale = scale;
+            self.recreate_framebuffers();
+        }
+    }
+
+    /// Get the fraction of daylight reaching faces turned away from the sun.
+    #[inline]
+    pub fn ambient(&self) -> f32 {
+        self.settings.ambient
+    }
+
+    /// Set the fraction of daylight reaching faces turned away from the sun,
+    /// clamped between 0 and 1. See [`RendererConfig::ambient`].
+    #[inline]
+    pub fn set_ambient(&mut self, ambient: f32) {
+        self.settings.ambient = ambient.clamp(0.0, 1.0);
+    }
+
+    /// Get the number of physical pixels per logical pixel of the display the
+    /// window is on, like 2 on most Retina displays.
+    #[inline]
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor
+    }
+
+    /// Set the number of physical pixels per logical pixel, after the window
+    /// moves to a display with a different scale factor.
+    ///
+    /// Overlay elements keep the same logical size. This doesn't resize the
+    /// surface, which gets its own resize event.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+        self.ui_renderer.set_scale_factor(&self.queue, scale_factor);
+    }
+
+    /// Get the size of the surface in logical pixels.
+    #[inline]
+    pub fn logical_size(&self) -> winit::dpi::LogicalSize<f32> {
+        self.size.to_logical(self.scale_factor as f64)
+    }
+
+    /// Set whether moving the mouse up looks down.
+    #[inline]
+    pub fn set_invert_y(&mut self, invert_y: bool) {
+        self.camera_controller.set_invert_y(invert_y);
+    }
+
+    /// Set the color multiplied into tinted faces, like the top of grass.
+    ///
+    /// The color is in linear space.
+    pub fn set_grass_color(&mut self, color: wgpu::Color) {
+        self.grass_color = color;
+    }
+
+    /// Get the world being rendered.
+    #[inline]
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Get frame timing statistics.
+    #[inline]
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// Set the block at a world-space block position, and remesh its chunk.
+    ///
+    /// The chunk keeps its old geometry until the new mesh is ready. Does
+    /// nothing if the position isn't in a loaded chunk.
+    #[inline]
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockId) {
+        self.set_block_state(x, y, z, block, BlockState::default());
+    }
+
+    /// Set the block and its state at a world-space block position, and remesh
+    /// its chunk.
+    ///
+    /// Behaves like [`set_block`](Self::set_block) otherwise. Use
+    /// [`BlockState::facing`] to place a block like a log sideways.
+    pub fn set_block_state(&mut self, x: i32, y: i32, z: i32, block: BlockId, state: BlockState) {
+        if self.world.set_block_state(x, y, z, block, state).is_none() {
+            return;
+        }
+
+        // Blocks on the edge of a chunk can hide or reveal faces of its
+        // neighbours, and light can spread further still
+        let mut changed = ChunkCoord::touching_block(x, y, z);
+        for pos in self.world.take_relit_chunks() {
+            if !changed.contains(&pos) {
+                changed.push(pos);
+            }
+        }
+        self.remesh_chunks(&changed);
+    }
+
+    /// Add a point light, lighting blocks within `range` of `position`.
+    ///
+    /// The color is in linear space. Returns `None` if there are already
+    /// [`MAX_POINT_LIGHTS`](types::MAX_POINT_LIGHTS) lights.
+    pub fn add_point_light(
+        &mut self,
+        position: glm::Vec3,
+        color: wgpu::Color,
+        range: f32,
+    ) -> Option<lights::LightId> {
+        self.point_lights
+            .add(position, color_to_array(color), range)
+    }
+
+    /// Remove a point light, returning whether it existed.
+    pub fn remove_point_light(&mut self, id: lights::LightId) -> bool {
+        self.point_lights.remove(id)
+    }
+
+    /// Queue a square of `size` blocks centered on `position`, turned to face
+    /// the camera, like a dropped item.
+    ///
+    /// `tile` is a layer of the block texture array, like the faces of a
+    /// [`BlockType`](crate::world::blocks::BlockType). Billboards are only drawn
+    /// for one frame, so they must be queued again before every
+    /// [`Renderer::update`].
+    pub fn draw_billboard(&mut self, position: glm::Vec3, tile: u32, size: f32) {
+        self.billboard_renderer.draw_billboard(position, tile, size);
+    }
+
+    /// Get the block the camera is looking at, if one is within reach.
+    #[inline]
+    pub fn target(&self) -> Option<RaycastHit> {
+        self.target
+    }
+
+    /// Get where a block would be placed, if the camera is aiming at a face within reach.
+    #[inline]
+    pub fn place_preview(&self) -> Option<[i32; 3]> {
+        self.place_preview
+    }
+
+    /// Cap the frame rate, or remove the cap with `None`.
+    ///
+    /// Mostly useful to save power with present modes that don't wait for
+    /// vertical sync.
+    #[inline]
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.settings.max_fps = max_fps;
+        self.frame_limiter.set_max_fps(max_fps);
+    }
+
+    /// Block until the next frame is due under the frame rate cap.
+    #[inline]
+    pub fn wait_for_next_frame(&mut self) {
+        self.frame_limiter.wait();
+    }
+
+    /// Get the optional features enabled on the device.
+    ///
+    /// These are the [`RendererConfig::features`] the adapter supports.
+    #[inline]
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    /// Time the GPU spent drawing the scene in a recent frame.
+    ///
+    /// `None` if timestamp queries aren't supported, or no frame has been measured yet.
+    #[inline]
+    pub fn gpu_time(&self) -> Option<Duration> {
+        self.gpu_timer.as_ref()?.last().map(|times| times.total)
+    }
+
+    /// Time the GPU spent on the depth prepass in a recent frame, which is
+    /// part of [`Renderer::gpu_time`].
+    ///
+    /// `None` if there is no prepass, or no frame has been measured yet.
+    #[inline]
+    pub fn gpu_prepass_time(&self) -> Option<Duration> {
+        self.pipelines.depth_prepass.as_ref()?;
+        self.gpu_timer.as_ref()?.last().map(|times| times.prepass)
+    }
+
+    /// Estimate the GPU memory taken by the renderer's buffers and textures, in
+    /// bytes.
+    ///
+    /// Covers chunk geometry, block textures, framebuffers, viewports, render
+    /// targets and uniform buffers. The surface's own textures, small buffers of the line, billboard
+    /// and UI renderers, and any padding added by the driver aren't counted.
+    pub fn gpu_memory_estimate(&self) -> usize {
+        let framebuffers = |depth: &Texture, msaa: &Option<Texture>| {
+            depth.byte_size() + msaa.as_ref().map_or(0, Texture::byte_size)
+        };
+        let viewports: u64 = self
+            .viewports
+            .values()
+            .map(|v| framebuffers(&v.depth_texture, &v.msaa_texture) + v.globals.byte_len())
+            .sum();
+        let render_targets: u64 = self
+            .render_targets
+            .values()
+            .map(|t| t.color().byte_size() + t.depth().map_or(0, Texture::byte_size))
+            .sum();
+        let chunk_uniforms =
+            self.chunk_geometry.aabbs().len().max(1) as u64 * self.chunk_uniform_stride;
+
+        let bytes = self.chunk_geometry.byte_len()
+            + self.translucent_geometry.byte_len()
+            + self
+                .crack_mesh
+                .as_ref()
+                .map_or(0, |(_, mesh)| mesh.byte_len())
+            + self.block_texture_memory
+            + framebuffers(&self.depth_texture, &self.msaa_texture)
+            + self
+                .scaled_frame
+                .as_ref()
+                .map_or(0, |frame| frame.target().color().byte_size())
+            + viewports
+            + render_targets
+            + self.globals.byte_len()
+            + self.minimap_globals.byte_len()
+            + self.point_lights_buffer.byte_len()
+            + self
+                .occlusion
+                .as_ref()
+                .map_or(0, occlusion::OcclusionCuller::byte_len)
+            + chunk_uniforms;
+        bytes as usize
+    }
+
+    /// Get the position of the camera in world space.
+    #[inline]
+    pub fn camera_position(&self) -> glm::Vec3 {
+        self.camera.position
+    }
+
+    /// Get the camera.
+    #[inline]
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Get a mutable handle to the camera.
+    #[inline]
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Place the camera directly, like for screenshots or cinematic views.
+    ///
+    /// The controller and player stop moving it until
+    /// [`Renderer::release_camera`] is called, so the view stays exactly where
+    /// it was put. The aspect ratio and depth range are kept, since they follow
+    /// the surface and pipelines.
+    pub fn set_camera(&mut self, mut camera: Camera) {
+        camera.aspect = self.camera.aspect;
+        camera.reversed_z = self.camera.reversed_z;
+        self.camera = camera;
+        self.camera_controller.reset();
+        self.camera_scripted = true;
+        self.write_globals();
+    }
+
+    /// Give control of the camera back to the controller, or the player while
+    /// walking.
+    pub fn release_camera(&mut self) {
+        self.camera_scripted = false;
+    }
+
+    /// Upload the globals seen from the main camera.
+    fn write_globals(&self) {
+        self.globals.write(
+            &self.queue,
+            0,
+            &[Self::globals(
+                &self.camera,
+                &self.fog,
+                &self.day_night,
+                self.grass_color,
+                self.show_chunk_colors,
+                self.elapsed.as_secs_f32(),
+                !self.config.format.describe().srgb,
+                &self.settings,
+            )],
+        );
+    }
+
+    /// Record the world into `view`, as seen from the camera of `scene`.
+    ///
+    /// With MSAA, `view` must be multisampled and is resolved into `resolve_target`.
+    /// `depth_view` must have the same sample count as `view`.
+    ///
+    /// `color_load` decides whether `view` is cleared first or drawn over.
+    /// `timer`, if given, gets a timestamp once the depth prepass is done.
+    #[allow(clippy::too_many_arguments)]
+    #[profiling::function]
+    fn draw_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        resolve_target: Option<&wgpu::TextureView>,
+        depth_view: &wgpu::TextureView,
+        scene: Scene,
+        color_load: wgpu::LoadOp<wgpu::Color>,
+        timer: Option<&timer::GpuTimer>,
+    ) {
+        let globals = match scene {
+            Scene::Main => &self.globals_bind_group,
+            Scene::Minimap => &self.minimap_globals_bind_group,
+            Scene::Viewport(id) => &self.viewports[&id].globals_bind_group,
+        };
+
+        if let Some(pipeline) = &self.pipelines.depth_prepass {
+            profiling::scope!("depth prepass");
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Depth Prepass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(pipeline::far_depth(self.settings.reversed_z)),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, self.diffuse_bind_group.inner(), &[]);
+            render_pass.set_bind_group(1, globals.inner(), &[]);
+            self.draw_chunks(&mut render_pass, scene);
+        }
+        if let Some(timer) = timer {
+            timer.end_prepass(encoder);
+        }
+
+        // Keep the depth from the prepass, if there was one
+        let depth_load = match self.pipelines.depth_prepass {
+            Some(_) => wgpu::LoadOp::Load,
+            None => wgpu::LoadOp::Clear(pipeline::far_depth(self.settings.reversed_z)),
+        };
+
+        // `render_pass` is an in-progress recording of a render pass.
+        // A render pass is a GPU operation that renders an output image onto a framebuffer.
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: color_load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        render_pass.set_pipeline(&self.pipelines.opaque);
+        render_pass.set_bind_group(0, self.diffuse_bind_group.inner(), &[]);
+        render_pass.set_bind_group(1, globals.inner(), &[]);
+        self.draw_chunks(&mut render_pass, scene);
+
+        if let (Scene::Main, Some((_, mesh))) = (scene, &self.crack_mesh) {
+            render_pass.set_pipeline(&self.pipelines.crack);
+            render_pass.set_bind_group(2, self.chunk_uniforms.inner(), &[0]);
+            mesh.draw(&mut render_pass);
+        }
+
+        self.billboard_renderer.render(
+            &mut render_pass,
+            self.diffuse_bind_group.inner(),
+            globals.inner(),
+        );
+
+        if !self.translucent_geometry.is_empty() {
+            render_pass.set_pipeline(&self.pipelines.translucent);
+            render_pass.set_bind_group(2, self.chunk_uniforms.inner(), &[0]);
+            self.translucent_geometry
+                .draw(&mut render_pass, self.multi_draw);
+        }
+
+        // Other scenes are just the world, without outlines or the crosshair
+        if scene == Scene::Main {
+            self.line_renderer
+                .render(&mut render_pass, self.globals_bind_group.inner());
+            self.ui_renderer.render(&mut render_pass);
+        }
+    }
+
+    /// Query which chunks are hidden behind the main view's depth buffer, after
+    /// the scene has been drawn into it, so that later frames can skip them.
+    fn record_occlusion_queries(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(culler), Some(pipeline)) = (&mut self.occlusion, &self.pipelines.occlusion)
+        else {
+            return;
+        };
+        if !culler.prepare(&self.camera.view_projection(), self.camera.position) {
+            return;
+        }
+
+        {
+            profiling::scope!("occlusion queries");
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Occlusion Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: self.depth_texture.view(),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, self.diffuse_bind_group.inner(), &[]);
+            render_pass.set_bind_group(1, self.globals_bind_group.inner(), &[]);
+            render_pass.set_bind_group(2, self.chunk_uniforms.inner(), &[0]);
+            culler.draw(&mut render_pass);
+        }
+        culler.resolve(encoder);
+    }
+
+    /// Color the frame is cleared to, encoded for the surface format.
+    ///
+    /// Clear colors are written as-is, so unlike colors output by shaders, they
+    /// aren't encoded to sRGB by a linear surface.
+    fn clear_color(&self) -> wgpu::Color {
+        let color = self.day_night.sky_color(self.settings.clear_color);
+        if self.config.format.describe().srgb {
+            return color;
+        }
+        wgpu::Color {
+            r: linear_to_srgb(color.r),
+            g: linear_to_srgb(color.g),
+            b: linear_to_srgb(color.b),
+            a: color.a,
+        }
+    }
+
+    /// Render the world into an offscreen target instead of the window.
+    ///
+    /// The debug overlay is not drawn. Framebuffers are created for the target's
+    /// size if it has no depth buffer of its own, or MSAA is enabled.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the target's format differs from the surface format, which the
+    /// pipelines are built for.
+    #[profiling::function]
+    pub fn render_to(&self, target: &RenderTarget) {
+        self.render_to_with_load(target, wgpu::LoadOp::Clear(self.clear_color()));
+    }
+
+    /// Render the world into an offscreen target, choosing whether its color is
+    /// cleared first or kept to draw over.
+    ///
+    /// With MSAA the world is drawn into a fresh multisampled buffer, so with
+    /// [`LoadOp::Load`](wgpu::LoadOp::Load) the target's contents are first
+    /// drawn into it, at the cost of an extra pass.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the target's format differs from the surface format, which the
+    /// pipelines are built for.
+    #[profiling::function]
+    pub fn render_to_with_load(
+        &self,
+        target: &RenderTarget,
+        color_load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        self.render_scene_to(target, Scene::Main, color_load);
+    }
+
+    /// Capture the view from the camera, as shown in the window.
+    ///
+    /// The debug overlay isn't included. Blocks until the frame has been rendered
+    /// and copied back from the GPU.
+    pub fn screenshot(&self) -> image::RgbaImage {
+        let target = self.surface_sized_target(Some("screenshot"), false);
+        self.render_to(&target);
+        target.read_pixels(&self.device, &self.queue)
+    }
+
+    /// Copy a [screenshot](Self::screenshot) to the system clipboard.
+    ///
+    /// Fails if the platform's clipboard can't hold images.
+    #[cfg(feature = "clipboard")]
+    pub fn screenshot_to_clipboard(&mut self) -> Result<(), arboard::Error> {
+        let image = self.screenshot();
+        let (width, height) = image.dimensions();
+        let data = arboard::ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: image.into_raw().into(),
+        };
+
+        // Some platforms lose the contents once every clipboard handle is dropped
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => self.clipboard.insert(arboard::Clipboard::new()?),
+        };
+        clipboard.set_image(data)
+    }
+
+    /// Render a top-down map of the world around the camera into an offscreen
+    /// target, for example to composite into a corner of the screen.
+    ///
+    /// The map follows the camera with north at the top, showing
+    /// [`MINIMAP_RADIUS`] blocks on each side.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the target's format differs from the surface format, which the
+    /// pipelines are built for.
+    #[profiling::function]
+    pub fn render_minimap(&self, target: &RenderTarget) {
+        let (width, height) = target.size();
+        let eye = glm::vec3(
+            self.camera.position.x,
+            self.camera.position.y.max(MINIMAP_HEIGHT),
+            self.camera.position.z,
+        );
+        let mut camera = Camera::top_down(eye, MINIMAP_RADIUS, width as f32 / height as f32);
+        camera.reversed_z = self.settings.reversed_z;
+
+        // Seen from above, the ground is far away, so push the fog past the far plane
+        let fog = Fog {
+            start: camera.zfar,
+            end: camera.zfar * 2.0,
+            ..self.fog
+        };
+        self.minimap_globals.write(
+            &self.queue,
+            0,
+            &[Self::globals(
+                &camera,
+                &fog,
+                &self.day_night,
+                self.grass_color,
+                self.show_chunk_colors,
+                self.elapsed.as_secs_f32(),
+                !self.config.format.describe().srgb,
+                &self.settings,
+            )],
+        );
+
+        self.render_scene_to(
+            target,
+            Scene::Minimap,
+            wgpu::LoadOp::Clear(self.clear_color()),
+        );
+    }
+
+    /// Render a frame to a viewport and present it.
+    ///
+    /// Does nothing if the viewport was removed. A lost or outdated surface is
+    /// reconfigured, skipping the frame.
+    #[profiling::function]
+    pub fn render_viewport(&self, id: ViewportId) -> Result<(), wgpu::SurfaceError> {
+        let viewport = match self.viewports.get(&id) {
+            Some(viewport) => viewport,
+            None => return Ok(()),
+        };
+
+        viewport.globals.write(
+            &self.queue,
+            0,
+            &[Self::globals(
+                &viewport.camera,
+                &self.fog,
+                &self.day_night,
+                self.grass_color,
+                self.show_chunk_colors,
+                self.elapsed.as_secs_f32(),
+                !self.config.format.describe().srgb,
+                &self.settings,
+            )],
+        );
+
+        let output = match viewport.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                viewport.surface.configure(&self.device, &viewport.config);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Viewport Render Encoder"),
+            });
+
+        let scene = Scene::Viewport(id);
+        let clear = wgpu::LoadOp::Clear(self.clear_color());
+        let depth = viewport.depth_texture.view();
+        match &viewport.msaa_texture {
+            Some(msaa) => self.draw_scene(
+                &mut encoder,
+                msaa.view(),
+                Some(&view),
+                depth,
+                scene,
+                clear,
+                None,
+            ),
+            None => self.draw_scene(&mut encoder, &view, None, depth, scene, clear, None),
+        }
+
+        self.queue.submit([encoder.finish()]);
+        output.present();
+        Ok(())
+    }
+
+    /// Render a scene into an offscreen target.
+    fn render_scene_to(
+        &self,
+        target: &RenderTarget,
+        scene: Scene,
+        color_load: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        assert_eq!(
+            target.format(),
+            self.config.format,
+            "render target format must match the surface format"
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Render Encoder"),
+            });
+
+        let sample_count = self.settings.sample_count;
+        match target.depth() {
+            Some(depth) if sample_count == 1 => {
+                self.draw_scene(
+                    &mut encoder,
+                    target.color().view(),
+                    None,
+                    depth.view(),
+                    scene,
+                    color_load,
+                    None,
+                );
+            }
+            _ => {
+                let (width, height) = target.size();
+                let config = wgpu::SurfaceConfiguration {
+                    width,
+                    height,
+                    ..self.config.clone()
+                };
+                let (depth, msaa) = Self::create_framebuffers(&self.device, &config, sample_count);
+                match &msaa {
+                    Some(msaa) => {
+                        // The multisampled buffer starts out empty, unlike the target
+                        if color_load == wgpu::LoadOp::Load {
+                            self.blitter.blit_multisampled(
+                                &self.device,
+                                &mut encoder,
+                                target.color(),
+                                msaa.view(),
+                            );
+                        }
+                        self.draw_scene(
+                            &mut encoder,
+                            msaa.view(),
+                            Some(target.color().view()),
+                            depth.view(),
+                            scene,
+                            color_load,
+                            None,
+                        )
+                    }
+                    None => self.draw_scene(
+                        &mut encoder,
+                        target.color().view(),
+                        None,
+                        depth.view(),
+                        scene,
+                        color_load,
+                        None,
+                    ),
+                }
+            }
+        }
+
+        self.queue.submit([encoder.finish()]);
+    }
+
+    /// Render a frame to the window and present it.
+    ///
+    /// Does nothing for a headless renderer, which has no window to present to.
+    #[profiling::function]
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let Some(surface) = &self.surface else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
+
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        // An encoder records GPU operations to obtain a command buffer
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.update(&self.device);
+            timer.begin(&mut encoder);
+        }
+        if let Some(culler) = &mut self.occlusion {
+            culler.update(&self.device);
+        }
+
+        // Render into the multisampled buffer and resolve into the surface with
+        // MSAA. When scaled, the scaled frame stands in for the surface.
+        let clear = wgpu::LoadOp::Clear(self.clear_color());
+        let target = self
+            .scaled_frame
+            .as_ref()
+            .map_or(&view, |frame| frame.target().color().view());
+        match &self.msaa_texture {
+            Some(msaa) => self.draw_scene(
+                &mut encoder,
+                msaa.view(),
+                Some(target),
+                self.depth_texture.view(),
+                Scene::Main,
+                clear,
+                self.gpu_timer.as_ref(),
+            ),
+            None => self.draw_scene(
+                &mut encoder,
+                target,
+                None,
+                self.depth_texture.view(),
+                Scene::Main,
+                clear,
+                self.gpu_timer.as_ref(),
+            ),
+        }
+        self.record_occlusion_queries(&mut encoder);
+        if let Some(frame) = &self.scaled_frame {
+            self.blitter.blit(&mut encoder, frame, &view);
+        }
+
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.end(&mut encoder);
+        }
+
+        #[cfg(feature = "debug-ui")]
+        self.render_debug_ui(&mut encoder, &view);
+
+        // Submit the command buffer to the command queue
+        self.queue.submit([encoder.finish()]);
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.submitted();
+        }
+        if let Some(culler) = &mut self.occlusion {
+            culler.submitted();
+        }
+
+        // Present this texture on the surface
+        output.present();
+
+        profiling::finish_frame!();
+        Ok(())
+    }
+}
+
+/// Convert a color into the layout used by uniforms.
+fn color_to_array(color: wgpu::Color) -> [f32; 4] {
+    [
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+    ]
+}
+
+/// Encode a linear color channel with the sRGB transfer function.
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Pick a distinct color for a chunk, so that neighbouring chunks can be told apart.
+fn chunk_debug_color(pos: ChunkCoord) -> [f32; 4] {
+    let mut h = (pos.x as u32).wrapping_mul(0x9e37_79b1)
+        ^ (pos.y as u32).wrapping_mul(0x85eb_ca6b)
+        ^ (pos.z as u32).wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+
+    // Fully saturated hue, lightened so that textures stay readable
+    let hue = (h & 0xffff) as f32 / 65536.0 * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    [0.3 + 0.7 * r, 0.3 + 0.7 * g, 0.3 + 0.7 * b, 1.0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a 64x48 headless renderer on the fallback adapter.
+    ///
+    /// Panics without one, so that the tests using it fail instead of passing
+    /// without having drawn anything.
+    fn headless(settings: RendererConfig) -> Renderer {
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::LowPower,
+            force_fallback_adapter: true,
+            compatible_surface: None,
+        };
+        pollster::block_on(Renderer::new_headless(settings, 64, 48, &options))
+            .expect("no fallback adapter available")
+    }
+
+    #[test]
+    fn linear_to_srgb_matches_the_transfer_function() {
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        // Both pieces meet at the knee
+        let knee = 0.0031308;
+        assert!((linear_to_srgb(knee) - knee * 12.92).abs() < 1e-9);
+        assert!((1.055 * f64::powf(knee, 1.0 / 2.4) - 0.055 - knee * 12.92).abs() < 1e-6);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn headless_renders_terrain_on_fallback_adapter() {
+        let settings = RendererConfig {
+            render_distance: 1,
+            ..RendererConfig::default()
+        };
+        let mut renderer = headless(settings);
+
+        while renderer.mesh_pool.pending() > 0 || !renderer.mesh_uploads.is_empty() {
+            std::thread::sleep(Duration::from_millis(1));
+            renderer.upload_chunk_meshes();
+        }
+
+        let image = renderer.screenshot();
+        assert_eq!(image.dimensions(), (64, 48));
+        // The camera looks down at the ground, below the sky in the top corner.
+        // The crosshair is in the middle, so only terrain covers the bottom rows.
+        let sky = *image.get_pixel(0, 0);
+        for y in 44..48 {
+            assert!((0..64).all(|x| *image.get_pixel(x, y) != sky));
+        }
+    }
+
+    #[test]
+    fn render_targets_follow_the_surface_size() {
+        let settings = RendererConfig {
+            render_distance: 1,
+            ..RendererConfig::default()
+        };
+        let mut renderer = headless(settings);
+
+        let id = renderer.create_render_target(Some("tracked"), true);
+        assert_eq!(renderer.render_target(id).unwrap().size(), (64, 48));
+        renderer.resize(winit::dpi::PhysicalSize::new(32, 40));
+        let target = renderer.render_target(id).unwrap();
+        assert_eq!(target.size(), (32, 40));
+        let image = target.read_pixels(&renderer.device, &renderer.queue);
+        assert_eq!(image.dimensions(), (32, 40));
+
+        assert!(renderer.remove_render_target(id).is_some());
+        assert!(renderer.render_target(id).is_none());
+    }
+
+    #[test]
+    #[ignore = "software adapters may not resolve multisampled targets, run with --ignored"]
+    fn loading_keeps_target_contents_with_msaa() {
+        let settings = RendererConfig {
+            render_distance: 1,
+            sample_count: 4,
+            ..RendererConfig::default()
+        };
+        let renderer = headless(settings);
+
+        // Nothing is drawn over the sky, so it keeps the color it was cleared to
+        let target = renderer.surface_sized_target(Some("loaded"), false);
+        renderer.render_to_with_load(&target, wgpu::LoadOp::Clear(wgpu::Color::RED));
+        let cleared = *target
+            .read_pixels(&renderer.device, &renderer.queue)
+            .get_pixel(0, 0);
+        renderer.render_to_with_load(&target, wgpu::LoadOp::Load);
+        let loaded = *target
+            .read_pixels(&renderer.device, &renderer.queue)
+            .get_pixel(0, 0);
+
+        assert_eq!(cleared, image::Rgba([255, 0, 0, 255]));
+        assert_eq!(loaded, cleared);
     }
 }
@@ -0,0 +1,349 @@
+//! Skipping chunks hidden behind terrain, with pipeline statistics queries.
+//!
+//! The box around every chunk on screen is drawn over the depth of a finished
+//! frame, counting the fragments that pass the depth test. Chunks whose box had
+//! none are skipped until a later query finds part of them in view again.
+
+use std::collections::HashSet;
+
+use nalgebra_glm as glm;
+
+use crate::world::chunk::ChunkCoord;
+
+use super::types::{
+    buffer::{Buffer, BufferInitDescriptor},
+    readback::AsyncReadback,
+    Vertex,
+};
+
+/// Indices of the twelve triangles of a box, into corners laid out by [`box_corners`].
+const BOX_INDICES: [u32; 36] = [
+    0, 2, 6, 0, 6, 4, // -X
+    1, 3, 7, 1, 7, 5, // +X
+    0, 1, 5, 0, 5, 4, // -Y
+    2, 3, 7, 2, 7, 6, // +Y
+    0, 1, 3, 0, 3, 2, // -Z
+    4, 5, 7, 4, 7, 6, // +Z
+];
+
+/// How far boxes reach past the chunks they enclose, in blocks, so that faces
+/// on the edges of a chunk don't hide its own box.
+const BOX_MARGIN: f32 = 0.01;
+
+/// Distance from a box within which the eye counts as inside it, in blocks.
+///
+/// The near plane may cut away the front of boxes this close, leaving only
+/// faces behind the terrain around the eye to be tested, so their chunks are
+/// always drawn instead.
+const EYE_MARGIN: f32 = 1.0;
+
+/// Number of queries allocated at first, grown to fit the loaded chunks.
+const MIN_CAPACITY: u32 = 64;
+
+/// Size of the result of a query in bytes, with only fragment shader
+/// invocations counted.
+const RESULT_SIZE: wgpu::BufferAddress = std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+/// Finds the chunks hidden behind other geometry, with queries counting the
+/// fragment shader invocations of their boxes.
+///
+/// Results are read back without stalling, so they lag a frame or two behind,
+/// and frames are skipped while the previous results are still in flight.
+/// Chunks that haven't been queried yet are drawn.
+pub struct OcclusionCuller {
+    /// Corners of the box around each chunk, eight per chunk.
+    vertices: Option<Buffer>,
+    /// Indices of a single box, drawn with each box's first corner as the base vertex.
+    indices: Buffer,
+    /// World-space (min, max) corners of each chunk, in the order of `vertices`.
+    aabbs: Vec<(glm::Vec3, glm::Vec3)>,
+    queries: wgpu::QuerySet,
+    /// Number of queries in `queries`, and of results `readback` holds.
+    capacity: u32,
+    /// Results resolved from the query set.
+    readback: AsyncReadback,
+    /// Boxes queried in the frame being recorded or read back, in query order,
+    /// as their index in `aabbs` and the chunk they enclose.
+    queried: Vec<(u32, ChunkCoord)>,
+    /// Chunks on screen whose box had no fragments pass the depth test when
+    /// last queried.
+    hidden: HashSet<ChunkCoord>,
+}
+
+impl OcclusionCuller {
+    /// Create a culler, or `None` if the device doesn't support
+    /// [`wgpu::Features::PIPELINE_STATISTICS_QUERY`].
+    pub fn new(device: &wgpu::Device) -> Option<Self> {
+        if !device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return None;
+        }
+
+        let (queries, readback) = Self::create_queries(device, MIN_CAPACITY);
+        let indices = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: Some("occlusion_box_indices"),
+                usage: wgpu::BufferUsages::INDEX,
+                contents: &BOX_INDICES,
+            },
+        );
+
+        Some(Self {
+            vertices: None,
+            indices,
+            aabbs: Vec::new(),
+            queries,
+            capacity: MIN_CAPACITY,
+            readback,
+            queried: Vec::new(),
+            hidden: HashSet::new(),
+        })
+    }
+
+    /// Create a query set and the buffers its results are read back through.
+    fn create_queries(device: &wgpu::Device, capacity: u32) -> (wgpu::QuerySet, AsyncReadback) {
+        let queries = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("occlusion_queries"),
+            ty: wgpu::QueryType::PipelineStatistics(
+                wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS,
+            ),
+            count: capacity,
+        });
+        let size = capacity as wgpu::BufferAddress * RESULT_SIZE;
+        (queries, AsyncReadback::new(device, "occlusion", size))
+    }
+
+    /// Replace the chunks to query with the world-space (min, max) corners of
+    /// every chunk, after the chunk geometry changed.
+    ///
+    /// Chunks that weren't there before are drawn until they have been queried.
+    pub fn set_chunks(&mut self, device: &wgpu::Device, aabbs: &[(glm::Vec3, glm::Vec3)]) {
+        let vertices: Vec<_> = aabbs
+            .iter()
+            .flat_map(|&(min, max)| box_corners(min, max))
+            .collect();
+        self.vertices = (!vertices.is_empty()).then(|| {
+            Buffer::new(
+                device,
+                &BufferInitDescriptor {
+                    label: Some("occlusion_box_vertices"),
+                    usage: wgpu::BufferUsages::VERTEX,
+                    contents: &vertices,
+                },
+            )
+        });
+
+        let chunks: HashSet<_> = aabbs
+            .iter()
+            .map(|(min, _)| ChunkCoord::from_world_pos(*min))
+            .collect();
+        self.hidden.retain(|pos| chunks.contains(pos));
+        self.aabbs = aabbs.to_vec();
+
+        let needed = (aabbs.len() as u32).min(wgpu::QUERY_SET_MAX_QUERIES);
+        if needed > self.capacity {
+            let capacity = needed.next_power_of_two().min(wgpu::QUERY_SET_MAX_QUERIES);
+            // Results in flight are lost with the old buffers
+            (self.queries, self.readback) = Self::create_queries(device, capacity);
+            self.capacity = capacity;
+        }
+    }
+
+    /// Choose the boxes to query in this frame, as seen through `view_proj`
+    /// from `eye`.
+    ///
+    /// Boxes off screen or around the eye aren't queried, so their chunks are
+    /// drawn. Returns `false` if there is nothing to query, or the previous
+    /// results are still in flight, in which case nothing should be recorded.
+    pub fn prepare(&mut self, view_proj: &glm::Mat4, eye: glm::Vec3) -> bool {
+        if !self.readback.is_idle() || self.vertices.is_none() {
+            return false;
+        }
+
+        self.queried = self
+            .aabbs
+            .iter()
+            .enumerate()
+            .filter(|(_, &aabb)| !contains_eye(aabb, eye) && on_screen(view_proj, aabb))
+            .map(|(i, (min, _))| (i as u32, ChunkCoord::from_world_pos(*min)))
+            .take(self.capacity as usize)
+            .collect();
+        if self.queried.is_empty() {
+            self.hidden.clear();
+            return false;
+        }
+        true
+    }
+
+    /// Record a query around the draw of each box chosen by
+    /// [`OcclusionCuller::prepare`].
+    ///
+    /// The pipeline must test depth without writing it, and run a fragment
+    /// shader for the fragments that pass.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let Some(vertices) = &self.vertices else {
+            return;
+        };
+        render_pass.set_vertex_buffer(0, vertices.inner().slice(..));
+        render_pass.set_index_buffer(self.indices.inner().slice(..), wgpu::IndexFormat::Uint32);
+        for (query, &(i, _)) in self.queried.iter().enumerate() {
+            render_pass.begin_pipeline_statistics_query(&self.queries, query as u32);
+            render_pass.draw_indexed(0..self.indices.len(), i as i32 * 8, 0..1);
+            render_pass.end_pipeline_statistics_query();
+        }
+    }
+
+    /// Copy the results of the queries recorded by [`OcclusionCuller::draw`]
+    /// into the readback buffer.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.readback.is_idle() || self.queried.is_empty() {
+            return;
+        }
+
+        let count = self.queried.len() as u32;
+        encoder.resolve_query_set(&self.queries, 0..count, self.readback.source(), 0);
+        self.readback
+            .copy(encoder, count as wgpu::BufferAddress * RESULT_SIZE);
+    }
+
+    /// Start reading back the results of a frame.
+    ///
+    /// Must be called after the encoder passed to [`OcclusionCuller::resolve`]
+    /// is submitted.
+    pub fn submitted(&mut self) {
+        self.readback.submitted();
+    }
+
+    /// Collect the results of a previous frame, if they are ready.
+    ///
+    /// Chunks found hidden before stay hidden until results arrive.
+    pub fn update(&mut self, device: &wgpu::Device) {
+        let queried = &self.queried;
+        let hidden = self.readback.poll(device, |data| {
+            let invocations: &[u64] = bytemuck::cast_slice(data);
+            queried
+                .iter()
+                .zip(invocations)
+                .filter(|(_, &count)| count == 0)
+                .map(|(&(_, pos), _)| pos)
+                .collect::<HashSet<_>>()
+        });
+        if let Some(hidden) = hidden {
+            tracing::trace!(
+                "{} of {} queried chunks hidden",
+                hidden.len(),
+                self.queried.len()
+            );
+            self.hidden = hidden;
+        }
+    }
+
+    /// Whether the chunk enclosed by `aabb` was hidden when last queried, and
+    /// can be skipped. Chunks around `eye` are never hidden.
+    pub fn is_hidden(&self, aabb: (glm::Vec3, glm::Vec3), eye: glm::Vec3) -> bool {
+        self.hidden.contains(&ChunkCoord::from_world_pos(aabb.0)) && !contains_eye(aabb, eye)
+    }
+
+    /// Memory taken by the boxes and query results on the GPU, in bytes.
+    pub fn byte_len(&self) -> wgpu::BufferAddress {
+        self.vertices.as_ref().map_or(0, Buffer::byte_len)
+            + self.indices.byte_len()
+            + self.readback.byte_len()
+    }
+}
+
+/// Corners of the box around a chunk enclosing `min` to `max`, widened by
+/// [`BOX_MARGIN`].
+///
+/// The first, second and third bits of each corner's index select whether its
+/// X, Y and Z are at the maximum.
+fn box_corners(min: glm::Vec3, max: glm::Vec3) -> [Vertex; 8] {
+    let (min, max) = (min.add_scalar(-BOX_MARGIN), max.add_scalar(BOX_MARGIN));
+    std::array::from_fn(|i| {
+        let pick = |bit: usize, axis: usize| if i & bit != 0 { max[axis] } else { min[axis] };
+        Vertex {
+            position: [pick(1, 0), pick(2, 1), pick(4, 2)],
+            ..bytemuck::Zeroable::zeroed()
+        }
+    })
+}
+
+/// Whether `eye` is inside a box, or within [`EYE_MARGIN`] of it.
+fn contains_eye((min, max): (glm::Vec3, glm::Vec3), eye: glm::Vec3) -> bool {
+    (0..3).all(|i| eye[i] > min[i] - EYE_MARGIN && eye[i] < max[i] + EYE_MARGIN)
+}
+
+/// Whether any part of a box may be in front of the camera and inside the
+/// edges of the screen, as seen through `view_proj`.
+///
+/// Boxes are only found off screen when all their corners are past the same
+/// edge, so some boxes beyond a corner of the screen pass too.
+fn on_screen(view_proj: &glm::Mat4, (min, max): (glm::Vec3, glm::Vec3)) -> bool {
+    let clip: [glm::Vec4; 8] = std::array::from_fn(|i| {
+        let pick = |bit: usize, axis: usize| if i & bit != 0 { max[axis] } else { min[axis] };
+        view_proj * glm::vec4(pick(1, 0), pick(2, 1), pick(4, 2), 1.0)
+    });
+    let outside = |past: fn(&glm::Vec4) -> bool| clip.iter().all(past);
+    !(outside(|c| c.w <= 0.0)
+        || outside(|c| c.x < -c.w)
+        || outside(|c| c.x > c.w)
+        || outside(|c| c.y < -c.w)
+        || outside(|c| c.y > c.w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::camera::Camera;
+
+    fn chunk_aabb(x: i32, y: i32, z: i32) -> (glm::Vec3, glm::Vec3) {
+        ChunkCoord::new(x, y, z).bounds()
+    }
+
+    #[test]
+    fn boxes_enclose_their_chunk() {
+        let (min, max) = chunk_aabb(1, -1, 0);
+        let corners = box_corners(min, max);
+        for corner in &corners {
+            let p = glm::Vec3::from(corner.position);
+            for axis in 0..3 {
+                assert!(p[axis] < min[axis] || p[axis] > max[axis]);
+            }
+        }
+        // Every triangle uses corners on the same face of the box
+        for triangle in BOX_INDICES.chunks(3) {
+            let shared = triangle
+                .iter()
+                .fold(7, |bits, &i| bits & !(i ^ triangle[0]));
+            assert_ne!(shared, 0);
+        }
+    }
+
+    #[test]
+    fn only_boxes_on_screen_are_queried() {
+        // Looking along -Z from the middle of a chunk
+        let eye = glm::vec3(8.0, 8.0, 8.0);
+        let camera = Camera::look_at(eye, eye - glm::Vec3::z(), glm::Vec3::y(), 70.0, 1.0);
+        let view_proj = camera.view_projection();
+
+        assert!(on_screen(&view_proj, chunk_aabb(0, 0, -3)));
+        // Partly past the left edge
+        assert!(on_screen(&view_proj, chunk_aabb(-1, 0, -2)));
+        assert!(!on_screen(&view_proj, chunk_aabb(0, 0, 2)));
+        assert!(!on_screen(&view_proj, chunk_aabb(6, 0, -1)));
+        assert!(!on_screen(&view_proj, chunk_aabb(0, 5, -1)));
+    }
+
+    #[test]
+    fn chunks_around_the_eye_are_never_hidden() {
+        let aabb = chunk_aabb(0, 0, 0);
+        assert!(contains_eye(aabb, glm::vec3(8.0, 8.0, 8.0)));
+        // Close enough for the near plane to cut into the box
+        assert!(contains_eye(aabb, glm::vec3(-0.5, 8.0, 8.0)));
+        assert!(!contains_eye(aabb, glm::vec3(-2.0, 8.0, 8.0)));
+        assert!(!contains_eye(aabb, glm::vec3(8.0, 20.0, 8.0)));
+    }
+}
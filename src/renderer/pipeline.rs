@@ -0,0 +1,204 @@
+//! Construction of the pipelines drawing block geometry.
+
+use super::types::{texture::Texture, Vertex};
+
+/// Flip a depth test written for the standard depth range, where nearer
+/// fragments have lower depth, to work with reversed Z.
+pub fn depth_compare(compare: wgpu::CompareFunction, reversed_z: bool) -> wgpu::CompareFunction {
+    use wgpu::CompareFunction::*;
+    match (compare, reversed_z) {
+        (Less, true) => Greater,
+        (LessEqual, true) => GreaterEqual,
+        (Greater, true) => Less,
+        (GreaterEqual, true) => LessEqual,
+        (compare, _) => compare,
+    }
+}
+
+/// Depth of the far plane, which depth buffers are cleared to.
+pub fn far_depth(reversed_z: bool) -> f32 {
+    if reversed_z {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// Describes a pipeline drawing [`Vertex`] geometry into the depth buffer, and
+/// optionally a color target.
+///
+/// Starts out as a depth-only pipeline with back-face culling, filled polygons,
+/// no multisampling and a `Less` depth test, in the standard depth range.
+#[derive(Clone, Copy)]
+pub struct PipelineBuilder<'a> {
+    label: &'a str,
+    shader: &'a wgpu::ShaderModule,
+    vertex_entry: &'a str,
+    /// Fragment entry point, if not the default.
+    fragment_entry: Option<&'a str>,
+    bind_group_layouts: &'a [&'a wgpu::BindGroupLayout],
+    /// Format and blending of the color target, if there is one.
+    color: Option<(wgpu::TextureFormat, wgpu::BlendState)>,
+    polygon_mode: wgpu::PolygonMode,
+    cull_mode: Option<wgpu::Face>,
+    sample_count: u32,
+    alpha_to_coverage: bool,
+    depth_write: bool,
+    depth_compare: wgpu::CompareFunction,
+    reversed_z: bool,
+}
+
+impl<'a> PipelineBuilder<'a> {
+    /// Start a pipeline using the `vs_main` and `fs_main` entry points of `shader`.
+    ///
+    /// Pipelines without a color target don't use a fragment shader, unless one
+    /// is given with [`PipelineBuilder::fragment_entry`], for example to discard
+    /// fragments.
+    pub fn new(
+        label: &'a str,
+        shader: &'a wgpu::ShaderModule,
+        bind_group_layouts: &'a [&'a wgpu::BindGroupLayout],
+    ) -> Self {
+        Self {
+            label,
+            shader,
+            vertex_entry: "vs_main",
+            fragment_entry: None,
+            bind_group_layouts,
+            color: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            cull_mode: Some(wgpu::Face::Back),
+            sample_count: 1,
+            alpha_to_coverage: false,
+            depth_write: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            reversed_z: false,
+        }
+    }
+
+    /// Use another vertex shader entry point of the shader.
+    #[inline]
+    pub fn vertex_entry(mut self, entry_point: &'a str) -> Self {
+        self.vertex_entry = entry_point;
+        self
+    }
+
+    /// Use another fragment shader entry point of the shader.
+    #[inline]
+    pub fn fragment_entry(mut self, entry_point: &'a str) -> Self {
+        self.fragment_entry = Some(entry_point);
+        self
+    }
+
+    /// Shade fragments into a color target of the given format.
+    #[inline]
+    pub fn color_target(mut self, format: wgpu::TextureFormat, blend: wgpu::BlendState) -> Self {
+        self.color = Some((format, blend));
+        self
+    }
+
+    /// Set how polygons are rasterized.
+    ///
+    /// Modes other than [`wgpu::PolygonMode::Fill`] need their device feature.
+    #[inline]
+    pub fn polygon_mode(mut self, mode: wgpu::PolygonMode) -> Self {
+        self.polygon_mode = mode;
+        self
+    }
+
+    /// Set which faces are discarded, if any.
+    #[inline]
+    pub fn cull_mode(mut self, cull_mode: Option<wgpu::Face>) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    /// Set the number of samples per pixel of the targets.
+    #[inline]
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Derive coverage from the fragment alpha. Only takes effect with multisampling.
+    #[inline]
+    pub fn alpha_to_coverage(mut self, enabled: bool) -> Self {
+        self.alpha_to_coverage = enabled;
+        self
+    }
+
+    /// Set whether depth is written, and how it is tested.
+    #[inline]
+    pub fn depth(mut self, write: bool, compare: wgpu::CompareFunction) -> Self {
+        self.depth_write = write;
+        self.depth_compare = compare;
+        self
+    }
+
+    /// Flip the depth test for cameras using reversed Z.
+    ///
+    /// Tests are still given for the standard range, like `Less` for nearer.
+    #[inline]
+    pub fn reversed_z(mut self, reversed_z: bool) -> Self {
+        self.reversed_z = reversed_z;
+        self
+    }
+
+    pub fn build(&self, device: &wgpu::Device) -> wgpu::RenderPipeline {
+        let layout_label = format!("{} Layout", self.label);
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&layout_label),
+            bind_group_layouts: self.bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let targets = self.color.map(|(format, blend)| {
+            [Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })]
+        });
+
+        let fragment_entry = self
+            .fragment_entry
+            .or_else(|| self.color.map(|_| "fs_main"));
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(self.label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: self.shader,
+                entry_point: self.vertex_entry,
+                buffers: &[Vertex::BUFFER_LAYOUT],
+            },
+            fragment: fragment_entry.map(|entry_point| wgpu::FragmentState {
+                module: self.shader,
+                entry_point,
+                targets: targets.as_ref().map_or(&[], |targets| &targets[..]),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: self.cull_mode,
+                polygon_mode: self.polygon_mode,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: self.depth_write,
+                depth_compare: depth_compare(self.depth_compare, self.reversed_z),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: self.sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: self.alpha_to_coverage && self.sample_count > 1,
+            },
+            multiview: None,
+        })
+    }
+}
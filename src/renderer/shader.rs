@@ -0,0 +1,117 @@
+//! Loading of shaders, and hot reloading of the main one.
+//!
+//! Release builds embed the shader in the binary. Debug builds read it from disk
+//! instead, and can watch it for changes so that it can be edited without
+//! recompiling.
+//!
+//! Functions shared between shaders, like the sRGB encoding, live in their own
+//! files and are prepended to the source of every shader created here.
+
+/// WGSL encoding linear colors as sRGB, for targets that don't do it themselves.
+const SRGB: &str = include_str!("../../res/shaders/srgb.wgsl");
+
+/// Location of the main shader's source on disk.
+#[cfg(debug_assertions)]
+const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/res/shaders/shader.wgsl");
+
+/// Load the main shader.
+pub fn load(device: &wgpu::Device) -> wgpu::ShaderModule {
+    #[cfg(debug_assertions)]
+    match std::fs::read_to_string(SHADER_PATH) {
+        Ok(source) => match compile(device, &source) {
+            Some(module) => return module,
+            None => tracing::warn!("falling back to the embedded shader"),
+        },
+        Err(e) => tracing::warn!("couldn't read {SHADER_PATH}, using the embedded shader: {e}"),
+    }
+
+    create(
+        device,
+        "shader.wgsl",
+        include_str!("../../res/shaders/shader.wgsl"),
+    )
+}
+
+/// Create a shader module from WGSL source, with the shared functions prepended.
+pub fn create(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(with_shared(source).into()),
+    })
+}
+
+/// Prepend the shared functions to a shader's source.
+fn with_shared(source: &str) -> String {
+    format!("{SRGB}\n{source}")
+}
+
+/// Compile WGSL source, logging any errors instead of panicking.
+#[cfg(debug_assertions)]
+pub fn compile(device: &wgpu::Device, source: &str) -> Option<wgpu::ShaderModule> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("shader.wgsl"),
+        source: wgpu::ShaderSource::Wgsl(with_shared(source).into()),
+    });
+
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(e) => {
+            tracing::error!("failed to compile shader: {e}");
+            None
+        }
+        None => Some(module),
+    }
+}
+
+/// Watches the main shader's source for changes.
+#[cfg(debug_assertions)]
+pub struct ShaderWatcher {
+    _watcher: notify::RecommendedWatcher,
+    events: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+#[cfg(debug_assertions)]
+impl ShaderWatcher {
+    pub fn new() -> notify::Result<Self> {
+        use notify::Watcher;
+
+        let (sender, events) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+
+        // Watch the directory, since editors often replace files instead of writing to them
+        let dir = std::path::Path::new(SHADER_PATH).parent().unwrap();
+        watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Get the new source of the shader if it changed since the last call.
+    pub fn poll(&self) -> Option<String> {
+        let path = std::path::Path::new(SHADER_PATH);
+        let mut changed = false;
+
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    changed |= !event.kind.is_access()
+                        && event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name() == path.file_name())
+                }
+                Err(e) => tracing::warn!("error watching shader: {e}"),
+            }
+        }
+
+        if !changed {
+            return None;
+        }
+
+        std::fs::read_to_string(path)
+            .map_err(|e| tracing::error!("couldn't read {SHADER_PATH}: {e}"))
+            .ok()
+    }
+}
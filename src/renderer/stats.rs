@@ -0,0 +1,62 @@
+//! Frame timing statistics.
+
+use std::time::Duration;
+
+/// Tracks how quickly frames are being rendered.
+///
+/// Averages are taken over windows of [`FrameStats::WINDOW`], so that the
+/// reported values are stable enough to read.
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// Frames recorded in the current window.
+    frames: u32,
+    /// Time elapsed in the current window.
+    elapsed: Duration,
+    /// Average frames per second over the last complete window.
+    fps: f32,
+    /// Average frame time over the last complete window.
+    frame_time: Duration,
+    /// Total number of frames recorded.
+    total_frames: u64,
+}
+
+impl FrameStats {
+    /// Length of the window averages are taken over.
+    pub const WINDOW: Duration = Duration::from_millis(500);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a frame that took `dt` to produce.
+    pub fn record(&mut self, dt: Duration) {
+        self.frames += 1;
+        self.total_frames += 1;
+        self.elapsed += dt;
+
+        if self.elapsed >= Self::WINDOW {
+            self.fps = self.frames as f32 / self.elapsed.as_secs_f32();
+            self.frame_time = self.elapsed / self.frames;
+            self.frames = 0;
+            self.elapsed = Duration::ZERO;
+        }
+    }
+
+    /// Average frames per second.
+    #[inline]
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Average time taken per frame.
+    #[inline]
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    /// Total number of frames recorded.
+    #[inline]
+    pub fn total_frames(&self) -> u64 {
+        self.total_frames
+    }
+}
@@ -0,0 +1,243 @@
+//! Decoding block textures in the background, so startup doesn't wait on them.
+
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+
+use super::animation::{self, AnimatedTexture};
+use super::atlas::{AtlasBuilder, AtlasError};
+use super::breaking;
+use super::types::texture::checkerboard_image;
+use super::types::{TextureAnimations, MAX_TEXTURE_LAYERS};
+use crate::world::mesher;
+
+/// Color of the placeholder shown until block textures are loaded, chosen to
+/// stand out from any real texture.
+pub const PLACEHOLDER_COLOR: image::Rgba<u8> = image::Rgba([255, 0, 255, 255]);
+
+/// Size of the tiles of the built-in textures, which checkerboards standing in
+/// for textures that fail to decode must match.
+const TILE_SIZE: u32 = 16;
+
+/// Block textures decoded on the CPU, ready to be uploaded.
+pub struct BlockTextures {
+    /// Color of every layer: the atlas tiles, then the frames of animated
+    /// textures, then the crack stages.
+    pub tiles: Vec<image::RgbaImage>,
+    /// Normals of every layer.
+    pub normals: Vec<image::RgbaImage>,
+    pub animations: TextureAnimations,
+    /// Layer of the first crack stage.
+    pub crack_layer: u32,
+}
+
+impl BlockTextures {
+    /// Decode the built-in block textures, and the normal map at `normal_map`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the built-in textures aren't all the same size.
+    pub fn load(normal_map: Option<&Path>) -> Self {
+        // Built-in textures are all the same size, so a mismatch is a bug
+        let built_in = |e: AtlasError| panic!("invalid built-in texture: {e}");
+        let mut tiles = AtlasBuilder::new();
+        let atlas = decode_built_in(
+            "atlas.png",
+            include_bytes!("../../res/textures/atlas.png"),
+            TILE_SIZE * mesher::ATLAS_TILES,
+            TILE_SIZE * mesher::ATLAS_TILES,
+        );
+        tiles
+            .push_grid("atlas.png", &atlas, mesher::ATLAS_TILES)
+            .unwrap_or_else(built_in);
+
+        // Frames of animated textures follow the atlas tiles
+        let animated = load_animated_textures();
+        let animations = animation::texture_animations(tiles.len(), &animated);
+        tiles
+            .push_all("lava.png", animated.iter().flat_map(|a| a.frames()))
+            .unwrap_or_else(built_in);
+
+        // Followed by the stages of cracks on blocks being broken
+        let crack_layer = tiles
+            .push_all("crack.png", &load_crack_stages())
+            .unwrap_or_else(built_in);
+
+        let normals = load_normal_map(normal_map, tiles.len());
+        Self {
+            tiles: tiles.into_tiles(),
+            normals,
+            animations,
+            crack_layer,
+        }
+    }
+
+    /// Tiny [`PLACEHOLDER_COLOR`] layers, shown until the real textures are
+    /// loaded.
+    ///
+    /// There are as many layers as blocks could refer to, since how many the
+    /// real textures have isn't known until they are decoded.
+    pub fn placeholder() -> Self {
+        let layers = MAX_TEXTURE_LAYERS;
+        Self {
+            tiles: vec![image::RgbaImage::from_pixel(1, 1, PLACEHOLDER_COLOR); layers],
+            normals: vec![flat_normal_tile(1, 1); layers],
+            animations: bytemuck::Zeroable::zeroed(),
+            crack_layer: 0,
+        }
+    }
+}
+
+/// Decodes [`BlockTextures`] on a worker thread.
+pub struct TextureLoader {
+    /// `None` once the textures have been taken.
+    worker: Option<JoinHandle<BlockTextures>>,
+}
+
+impl TextureLoader {
+    /// Start decoding the block textures, and the normal map at `normal_map`.
+    pub fn spawn(normal_map: Option<PathBuf>) -> Self {
+        let worker = std::thread::Builder::new()
+            .name("texture loader".to_owned())
+            .spawn(move || BlockTextures::load(normal_map.as_deref()))
+            .expect("failed to spawn texture loader thread");
+        Self {
+            worker: Some(worker),
+        }
+    }
+
+    /// Take the textures if they have finished decoding.
+    ///
+    /// Returns `None` while they are still being decoded, and after they have
+    /// been taken.
+    pub fn poll(&mut self) -> Option<BlockTextures> {
+        if !self.worker.as_ref()?.is_finished() {
+            return None;
+        }
+        self.wait()
+    }
+
+    /// Block until the textures have finished decoding, and take them.
+    ///
+    /// Returns `None` if they have already been taken.
+    ///
+    /// ## Panics
+    ///
+    /// Resumes the panic of the worker thread, if decoding panicked.
+    pub fn wait(&mut self) -> Option<BlockTextures> {
+        let worker = self.worker.take()?;
+        Some(
+            worker
+                .join()
+                .unwrap_or_else(|e| std::panic::resume_unwind(e)),
+        )
+    }
+}
+
+/// Load the block normal map at `path`, split into one image per tile and
+/// padded to `layers` tiles.
+///
+/// Falls back to flat normals, which leave lighting unchanged, if there is
+/// no normal map or it can't be loaded.
+fn load_normal_map(path: Option<&Path>, layers: u32) -> Vec<image::RgbaImage> {
+    let mut normals = AtlasBuilder::new();
+    if let Some(path) = path {
+        let loaded = image::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|image| {
+                normals
+                    .push_grid(&path.display().to_string(), &image, mesher::ATLAS_TILES)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = loaded {
+            tracing::warn!("failed to load normal map: {e}");
+            normals = AtlasBuilder::new();
+        }
+    }
+
+    // Layers past the normal map, like animated textures, are lit with flat faces
+    let (width, height) = normals.tile_size().unwrap_or((1, 1));
+    let mut tiles = normals.into_tiles();
+    tiles.resize(
+        (layers as usize).max(tiles.len()),
+        flat_normal_tile(width, height),
+    );
+    tiles
+}
+
+/// Load the animated block textures, in the order their frames are stored
+/// after the atlas tiles.
+///
+/// Must stay in sync with the layers blocks refer to, starting at
+/// [`mesher::ANIMATED_LAYERS`].
+fn load_animated_textures() -> Vec<AnimatedTexture> {
+    let lava = decode_built_in(
+        "lava.png",
+        include_bytes!("../../res/textures/lava.png"),
+        TILE_SIZE,
+        TILE_SIZE,
+    );
+    vec![AnimatedTexture::from_strip(&lava, 6.0)]
+}
+
+/// Load the textures of cracks on a block being broken, from the least to the
+/// most broken.
+fn load_crack_stages() -> Vec<image::DynamicImage> {
+    let strip = decode_built_in(
+        "crack.png",
+        include_bytes!("../../res/textures/crack.png"),
+        TILE_SIZE,
+        TILE_SIZE * breaking::BREAK_STAGES,
+    );
+    let size = strip.width();
+    (0..breaking::BREAK_STAGES)
+        .map(|i| strip.crop_imm(0, i * size, size, size))
+        .collect()
+}
+
+/// Decode a built-in texture, or stand in a `width` by `height` checkerboard
+/// if it is corrupt.
+fn decode_built_in(name: &str, bytes: &[u8], width: u32, height: u32) -> image::DynamicImage {
+    image::load_from_memory(bytes).unwrap_or_else(|e| {
+        tracing::error!("failed to decode {name}, showing a checkerboard instead: {e}");
+        image::DynamicImage::ImageRgba8(checkerboard_image(width, height))
+    })
+}
+
+/// A normal map tile facing straight out, leaving lighting unchanged.
+fn flat_normal_tile(width: u32, height: u32) -> image::RgbaImage {
+    image::RgbaImage::from_pixel(width, height, image::Rgba([128, 128, 255, 255]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_every_layer_in_the_background() {
+        let mut loader = TextureLoader::spawn(None);
+        let textures = loader.wait().unwrap();
+        assert!(loader.poll().is_none());
+
+        let layers = textures.tiles.len();
+        assert!(layers <= MAX_TEXTURE_LAYERS);
+        assert_eq!(textures.normals.len(), layers);
+        assert_eq!(
+            textures.crack_layer as usize + breaking::BREAK_STAGES as usize,
+            layers
+        );
+        assert_ne!(*textures.tiles[0].get_pixel(0, 0), PLACEHOLDER_COLOR);
+        // Lava's frames start where blocks expect them, right after the atlas
+        let lava = &textures.animations.layers[mesher::ANIMATED_LAYERS as usize];
+        assert!(lava.frames > 1);
+    }
+
+    #[test]
+    fn corrupt_textures_become_checkerboards() {
+        let image = decode_built_in("broken.png", b"not a png", 16, 32).to_rgba8();
+        assert_eq!(image.dimensions(), (16, 32));
+        let [light, dark] = crate::renderer::types::texture::CHECKERBOARD_COLORS;
+        assert_eq!(*image.get_pixel(0, 0), light);
+        assert_eq!(*image.get_pixel(8, 0), dark);
+        assert_eq!(*image.get_pixel(8, 8), light);
+    }
+}
@@ -0,0 +1,72 @@
+//! Running the simulation at a fixed rate, independent of the frame rate.
+
+/// Most ticks run in one frame, so that catching up after a stall doesn't
+/// stall the next frame even longer.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/// Splits variable frame times into ticks of a fixed length.
+///
+/// Time left over after the last whole tick carries over to the next frame. How
+/// far it reaches into the next tick is the [`FixedTimestep::alpha`] that
+/// rendering interpolates between the last two ticks by.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    /// Seconds per tick.
+    step: f32,
+    /// Seconds passed but not yet simulated.
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    /// Run `rate` ticks per second, at least one.
+    pub fn new(rate: u32) -> Self {
+        Self {
+            step: 1.0 / rate.max(1) as f32,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Length of a tick in seconds.
+    #[inline]
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Let `dt` seconds pass, returning the number of ticks to simulate.
+    ///
+    /// Ticks beyond [`MAX_TICKS_PER_FRAME`] are dropped, slowing the simulation
+    /// down rather than falling further behind.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+        let ticks = (self.accumulator / self.step) as u32;
+        self.accumulator -= ticks as f32 * self.step;
+        ticks.min(MAX_TICKS_PER_FRAME)
+    }
+
+    /// Fraction of the way from the last tick to the next, from 0 to 1.
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leftover_time_carries_over() {
+        let mut ticks = FixedTimestep::new(4);
+        assert_eq!(ticks.advance(0.1), 0);
+        assert!((ticks.alpha() - 0.4).abs() < 1e-5);
+        assert_eq!(ticks.advance(0.5), 2);
+        assert!((ticks.alpha() - 0.4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stalls_drop_ticks() {
+        let mut ticks = FixedTimestep::new(60);
+        assert_eq!(ticks.advance(10.0), MAX_TICKS_PER_FRAME);
+        assert!(ticks.alpha() < 1.0);
+    }
+}
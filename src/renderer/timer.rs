@@ -0,0 +1,125 @@
+//! GPU pass timing.
+
+use std::time::Duration;
+
+use super::types::readback::AsyncReadback;
+
+/// Number of timestamps written per frame: at the start of the scene, after its
+/// depth prepass, and at its end.
+const QUERY_COUNT: u32 = 3;
+
+/// Size of the resolved timestamps in bytes.
+const RESOLVE_SIZE: wgpu::BufferAddress =
+    QUERY_COUNT as wgpu::BufferAddress * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+/// Measures how long the GPU spends drawing the scene, with timestamp queries.
+///
+/// The depth prepass is timed separately from the rest of the scene, so that
+/// its cost can be weighed against what it saves the main pass.
+///
+/// Results are read back without stalling, so they lag a frame or two behind,
+/// and frames are skipped while the previous result is still in flight.
+pub struct GpuTimer {
+    queries: wgpu::QuerySet,
+    /// Timestamps resolved from the query set.
+    readback: AsyncReadback,
+    /// Nanoseconds per timestamp tick.
+    period: f32,
+    last: Option<GpuTimes>,
+}
+
+/// GPU time spent on a frame, measured by a [`GpuTimer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpuTimes {
+    /// Time spent on the depth prepass, zero without one.
+    pub prepass: Duration,
+    /// Time spent on the whole scene, including the prepass.
+    pub total: Duration,
+}
+
+impl GpuTimer {
+    /// Create a timer, or `None` if the device doesn't support
+    /// [`wgpu::Features::TIMESTAMP_QUERY`].
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let queries = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu_timer_queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+
+        Some(Self {
+            queries,
+            readback: AsyncReadback::new(device, "gpu_timer", RESOLVE_SIZE),
+            period: queue.get_timestamp_period(),
+            last: None,
+        })
+    }
+
+    /// Write the starting timestamp, unless a previous result is still in flight.
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.readback.is_idle() {
+            encoder.write_timestamp(&self.queries, 0);
+        }
+    }
+
+    /// Write the timestamp ending the depth prepass, which must be between
+    /// [`GpuTimer::begin`] and [`GpuTimer::end`].
+    ///
+    /// Must be written even without a prepass, right after `begin`.
+    pub fn end_prepass(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.readback.is_idle() {
+            encoder.write_timestamp(&self.queries, 1);
+        }
+    }
+
+    /// Write the ending timestamp and copy all of them into the readback buffer.
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.readback.is_idle() {
+            return;
+        }
+
+        encoder.write_timestamp(&self.queries, 2);
+        encoder.resolve_query_set(&self.queries, 0..QUERY_COUNT, self.readback.source(), 0);
+        self.readback.copy(encoder, RESOLVE_SIZE);
+    }
+
+    /// Start reading back the timestamps of a frame.
+    ///
+    /// Must be called after the encoder passed to [`GpuTimer::end`] is submitted.
+    pub fn submitted(&mut self) {
+        self.readback.submitted();
+    }
+
+    /// Collect the result of a previous frame, if it is ready.
+    pub fn update(&mut self, device: &wgpu::Device) {
+        let period = self.period as f64;
+        let times = self.readback.poll(device, |data| {
+            let timestamps: &[u64] = bytemuck::cast_slice(data);
+            let elapsed = |from: u64, to: u64| {
+                Duration::from_nanos((to.saturating_sub(from) as f64 * period) as u64)
+            };
+            GpuTimes {
+                prepass: elapsed(timestamps[0], timestamps[1]),
+                total: elapsed(timestamps[0], timestamps[2]),
+            }
+        });
+        if let Some(times) = times {
+            tracing::trace!(
+                "gpu scene time: {:?}, of which depth prepass: {:?}",
+                times.total,
+                times.prepass
+            );
+            self.last = Some(times);
+        }
+    }
+
+    /// Time the GPU spent drawing the most recently measured frame.
+    #[inline]
+    pub const fn last(&self) -> Option<GpuTimes> {
+        self.last
+    }
+}
@@ -0,0 +1,473 @@
+//! Many meshes sharing the same buffers.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+
+use nalgebra_glm as glm;
+use wgpu::util::DrawIndexedIndirect;
+
+use super::buffer::{Buffer, BufferInitDescriptor};
+use super::Vertex;
+
+/// Size of a [`DrawIndexedIndirect`] command in the indirect buffer.
+const COMMAND_SIZE: wgpu::BufferAddress = std::mem::size_of::<DrawIndexedIndirect>() as _;
+
+/// Geometry of several meshes packed into one vertex and one index buffer.
+///
+/// Every mesh gets a [`DrawIndexedIndirect`] command, so the whole batch can be
+/// drawn in a single call where [`wgpu::Features::MULTI_DRAW_INDIRECT`] is
+/// supported.
+///
+/// Meshes are identified by a key, like the position of a chunk, and can be
+/// replaced or removed one at a time with [`MeshBatch::set`]. Only the mesh's
+/// own range of the buffers and its draw command are written, and ranges freed
+/// by old meshes are reused by new ones.
+pub struct MeshBatch<K> {
+    vbo: Buffer,
+    ibo: Buffer,
+    /// Draw commands, one per mesh.
+    indirect: Buffer,
+    /// CPU copy of the draw commands, for drawing without indirect support.
+    commands: Vec<DrawIndexedIndirect>,
+    /// World-space (min, max) corners enclosing each mesh.
+    aabbs: Vec<(glm::Vec3, glm::Vec3)>,
+    /// Key and number of vertices of each mesh, in draw order.
+    slots: Vec<(K, u32)>,
+    /// Position of each key's mesh in draw order.
+    positions: HashMap<K, usize>,
+    /// Ranges of `vbo` in use, in vertices.
+    vertex_ranges: RangeAllocator,
+    /// Ranges of `ibo` in use, in indices.
+    index_ranges: RangeAllocator,
+}
+
+impl<K: Copy + Eq + Hash> MeshBatch<K> {
+    /// Pack and upload geometry to the GPU.
+    ///
+    /// Each item is the (key, vertices, indices, aabb) of a mesh. Indices are
+    /// local to the mesh's own vertices. Meshes without indices are left out, so
+    /// that no empty draws are issued for them.
+    pub fn new<'a>(
+        device: &wgpu::Device,
+        label: Option<&str>,
+        meshes: impl IntoIterator<Item = (K, &'a [Vertex], &'a [u32], (glm::Vec3, glm::Vec3))>,
+    ) -> Self {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut commands = Vec::new();
+        let mut aabbs = Vec::new();
+        let mut slots = Vec::new();
+
+        for (key, v, i, aabb) in meshes.into_iter().filter(|(_, _, i, _)| !i.is_empty()) {
+            commands.push(DrawIndexedIndirect {
+                vertex_count: i.len() as u32,
+                instance_count: 1,
+                base_index: indices.len() as u32,
+                vertex_offset: vertices.len() as i32,
+                base_instance: 0,
+            });
+            aabbs.push(aabb);
+            slots.push((key, v.len() as u32));
+            vertices.extend_from_slice(v);
+            indices.extend_from_slice(i);
+        }
+
+        // Meshes are written into the buffers in place later, and moved over
+        // when they grow
+        let usage = wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+
+        let vbo_label = label.map(|x| format!("{x}_vertices"));
+        let vbo = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: vbo_label.as_deref(),
+                usage: wgpu::BufferUsages::VERTEX | usage,
+                contents: &vertices,
+            },
+        );
+
+        let ibo_label = label.map(|x| format!("{x}_indices"));
+        let ibo = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: ibo_label.as_deref(),
+                usage: wgpu::BufferUsages::INDEX | usage,
+                contents: &indices,
+            },
+        );
+
+        let indirect_label = label.map(|x| format!("{x}_indirect"));
+        let bytes: Vec<u8> = commands
+            .iter()
+            .flat_map(|c| c.as_bytes())
+            .copied()
+            .collect();
+        let indirect = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: indirect_label.as_deref(),
+                usage: wgpu::BufferUsages::INDIRECT | usage,
+                contents: &bytes,
+            },
+        );
+
+        let positions = slots
+            .iter()
+            .enumerate()
+            .map(|(i, &(key, _))| (key, i))
+            .collect();
+
+        Self {
+            vbo,
+            ibo,
+            indirect,
+            commands,
+            aabbs,
+            slots,
+            positions,
+            vertex_ranges: RangeAllocator::new(vertices.len() as u32),
+            index_ranges: RangeAllocator::new(indices.len() as u32),
+        }
+    }
+
+    /// Create a batch without any meshes, to add them with [`MeshBatch::set`].
+    pub fn empty(device: &wgpu::Device, label: Option<&str>) -> Self {
+        Self::new(device, label, std::iter::empty())
+    }
+
+    /// Add the mesh of `key`, or replace it if there is one already.
+    ///
+    /// Only the mesh's own geometry and draw command are written. A replaced
+    /// mesh keeps its place in draw order, a new one is drawn last, and a mesh
+    /// without indices is removed like with [`MeshBatch::remove`]. The buffers
+    /// are grown on the GPU if the mesh doesn't fit.
+    ///
+    /// Returns whether meshes were added or removed, changing the draw order.
+    pub fn set(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        key: K,
+        vertices: &[Vertex],
+        indices: &[u32],
+        aabb: (glm::Vec3, glm::Vec3),
+    ) -> bool {
+        if indices.is_empty() {
+            return self.remove(queue, &key);
+        }
+
+        let position = self.positions.get(&key).copied();
+        if let Some(i) = position {
+            self.free(i);
+        }
+
+        let base_vertex = self.vertex_ranges.alloc(vertices.len() as u32);
+        let base_index = self.index_ranges.alloc(indices.len() as u32);
+        self.vbo
+            .grow::<Vertex>(device, queue, self.vertex_ranges.end);
+        self.ibo.grow::<u32>(device, queue, self.index_ranges.end);
+        self.vbo.write(
+            queue,
+            base_vertex as wgpu::BufferAddress
+                * std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            vertices,
+        );
+        self.ibo.write(
+            queue,
+            base_index as wgpu::BufferAddress * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            indices,
+        );
+
+        let command = DrawIndexedIndirect {
+            vertex_count: indices.len() as u32,
+            instance_count: 1,
+            base_index,
+            vertex_offset: base_vertex as i32,
+            base_instance: 0,
+        };
+        let slot = (key, vertices.len() as u32);
+        let i = match position {
+            Some(i) => {
+                self.commands[i] = command;
+                self.aabbs[i] = aabb;
+                self.slots[i] = slot;
+                i
+            }
+            None => {
+                self.commands.push(command);
+                self.aabbs.push(aabb);
+                self.slots.push(slot);
+                self.positions.insert(key, self.commands.len() - 1);
+                let size = self.commands.len() as wgpu::BufferAddress * COMMAND_SIZE;
+                self.indirect.grow::<u8>(device, queue, size as u32);
+                self.commands.len() - 1
+            }
+        };
+        self.write_command(queue, i);
+        position.is_none()
+    }
+
+    /// Remove the mesh of `key`, if there is one.
+    ///
+    /// The last mesh in draw order takes its place, so only that mesh's draw
+    /// command is written.
+    ///
+    /// Returns whether a mesh was removed, changing the draw order.
+    pub fn remove(&mut self, queue: &wgpu::Queue, key: &K) -> bool {
+        let Some(i) = self.positions.remove(key) else {
+            return false;
+        };
+
+        self.free(i);
+        self.commands.swap_remove(i);
+        self.aabbs.swap_remove(i);
+        self.slots.swap_remove(i);
+        if let Some(&(moved, _)) = self.slots.get(i) {
+            self.positions.insert(moved, i);
+            self.write_command(queue, i);
+        }
+        true
+    }
+
+    /// Remove the meshes of every key `keep` returns `false` for.
+    ///
+    /// Returns whether any mesh was removed, changing the draw order.
+    pub fn retain(&mut self, queue: &wgpu::Queue, mut keep: impl FnMut(&K) -> bool) -> bool {
+        let removed: Vec<_> = self
+            .slots
+            .iter()
+            .map(|&(key, _)| key)
+            .filter(|key| !keep(key))
+            .collect();
+        for key in &removed {
+            self.remove(queue, key);
+        }
+        !removed.is_empty()
+    }
+
+    /// Give the buffer ranges of the mesh at `index` back to the allocators.
+    fn free(&mut self, index: usize) {
+        let c = &self.commands[index];
+        let base_vertex = c.vertex_offset as u32;
+        self.vertex_ranges
+            .free(base_vertex..base_vertex + self.slots[index].1);
+        self.index_ranges
+            .free(c.base_index..c.base_index + c.vertex_count);
+    }
+
+    /// Copy the draw command at `index` into the indirect buffer.
+    fn write_command(&self, queue: &wgpu::Queue, index: usize) {
+        self.indirect.write(
+            queue,
+            index as wgpu::BufferAddress * COMMAND_SIZE,
+            self.commands[index].as_bytes(),
+        );
+    }
+
+    /// Record a draw of every mesh in the batch.
+    ///
+    /// Issues a single indirect draw if `multi_draw` is set, which requires
+    /// [`wgpu::Features::MULTI_DRAW_INDIRECT`], or one draw per mesh otherwise.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, multi_draw: bool) {
+        if self.commands.is_empty() {
+            return;
+        }
+
+        self.bind(render_pass);
+
+        if multi_draw {
+            render_pass.multi_draw_indexed_indirect(
+                self.indirect.inner(),
+                0,
+                self.commands.len() as u32,
+            );
+        } else {
+            for i in 0..self.commands.len() {
+                self.draw_mesh(render_pass, i);
+            }
+        }
+    }
+
+    /// Bind the shared vertex and index buffers.
+    ///
+    /// Must be called before [`MeshBatch::draw_mesh`].
+    pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vbo.inner().slice(..));
+        render_pass.set_index_buffer(self.ibo.inner().slice(..), wgpu::IndexFormat::Uint32);
+    }
+
+    /// Record a draw of a single mesh in the batch.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn draw_mesh(&self, render_pass: &mut wgpu::RenderPass<'_>, index: usize) {
+        let c = &self.commands[index];
+        render_pass.draw_indexed(
+            c.base_index..c.base_index + c.vertex_count,
+            c.vertex_offset,
+            0..1,
+        );
+    }
+
+    /// Number of meshes in the batch, not counting empty ones that were left out.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Memory taken by the batch's buffers on the GPU, in bytes.
+    ///
+    /// This includes room left for meshes that haven't been added yet.
+    pub fn byte_len(&self) -> wgpu::BufferAddress {
+        self.vbo.byte_len() + self.ibo.byte_len() + self.indirect.byte_len()
+    }
+
+    /// Get the world-space (min, max) corners enclosing each mesh.
+    #[inline]
+    pub fn aabbs(&self) -> &[(glm::Vec3, glm::Vec3)] {
+        &self.aabbs
+    }
+}
+
+/// First-fit allocator of element ranges within a buffer.
+struct RangeAllocator {
+    /// Unused ranges below `end`, sorted and never adjacent to each other.
+    free: Vec<Range<u32>>,
+    /// End of the last range in use.
+    end: u32,
+}
+
+impl RangeAllocator {
+    /// Create an allocator with the first `used` elements in use.
+    const fn new(used: u32) -> Self {
+        Self {
+            free: Vec::new(),
+            end: used,
+        }
+    }
+
+    /// Allocate `len` elements, returning the first one.
+    ///
+    /// The first free range large enough is used, otherwise the elements are
+    /// taken from the end.
+    fn alloc(&mut self, len: u32) -> u32 {
+        if let Some(i) = self.free.iter().position(|r| r.end - r.start >= len) {
+            let start = self.free[i].start;
+            self.free[i].start += len;
+            if self.free[i].is_empty() {
+                self.free.remove(i);
+            }
+            return start;
+        }
+
+        let start = self.end;
+        self.end += len;
+        start
+    }
+
+    /// Free a range returned by [`RangeAllocator::alloc`].
+    fn free(&mut self, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let mut i = self.free.partition_point(|r| r.start < range.start);
+        self.free.insert(i, range);
+        // Merge with the neighbouring free ranges
+        if i + 1 < self.free.len() && self.free[i].end == self.free[i + 1].start {
+            self.free[i].end = self.free.remove(i + 1).end;
+        }
+        if i > 0 && self.free[i - 1].end == self.free[i].start {
+            self.free[i - 1].end = self.free.remove(i).end;
+            i -= 1;
+        }
+        if self.free[i].end == self.end {
+            self.end = self.free.remove(i).start;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_device;
+    use super::*;
+
+    #[test]
+    fn freed_ranges_are_merged_and_reused() {
+        let mut ranges = RangeAllocator::new(0);
+        assert_eq!(ranges.alloc(4), 0);
+        assert_eq!(ranges.alloc(2), 4);
+        assert_eq!(ranges.alloc(3), 6);
+
+        ranges.free(0..4);
+        ranges.free(4..6);
+        assert_eq!(ranges.free, vec![0..6]);
+        assert_eq!(ranges.alloc(5), 0);
+        assert_eq!(ranges.free, vec![5..6]);
+        assert_eq!(ranges.alloc(2), 9);
+
+        // Freeing the end shrinks the allocation instead
+        ranges.free(9..11);
+        ranges.free(6..9);
+        assert_eq!((ranges.free.as_slice(), ranges.end), (&[][..], 5));
+    }
+
+    #[test]
+    fn meshes_are_updated_without_touching_the_others() {
+        let (_, device, queue) = test_device();
+        let vertices = [bytemuck::Zeroable::zeroed(); 3];
+        let aabb = |x: f32| (glm::vec3(x, 0.0, 0.0), glm::vec3(x + 1.0, 1.0, 1.0));
+        let mut batch = MeshBatch::new(
+            &device,
+            None,
+            [
+                (0, &vertices[..], &[0, 1, 2][..], aabb(0.0)),
+                (1, &vertices[..], &[2, 1, 0][..], aabb(1.0)),
+            ],
+        );
+        let indices = |batch: &MeshBatch<i32>| batch.ibo.read_back::<u32>(&device, &queue);
+
+        // Replacing a mesh of the same size reuses its range
+        let size = batch.byte_len();
+        assert!(!batch.set(&device, &queue, 0, &vertices, &[1, 1, 1], aabb(0.0)));
+        assert_eq!(batch.byte_len(), size);
+        assert_eq!(indices(&batch), [1, 1, 1, 2, 1, 0]);
+
+        // The last mesh is moved into the place of a removed one
+        assert!(batch.remove(&queue, &0));
+        assert_eq!(batch.aabbs(), [aabb(1.0)]);
+        assert_eq!(batch.commands[0].base_index, 3);
+
+        // New meshes go into the freed range if they fit, and at the end otherwise
+        assert!(batch.set(
+            &device,
+            &queue,
+            2,
+            &vertices,
+            &[0, 0, 1, 1, 2, 2],
+            aabb(2.0)
+        ));
+        assert!(batch.set(&device, &queue, 3, &vertices, &[2, 2, 2], aabb(3.0)));
+        assert_eq!(batch.aabbs(), [aabb(1.0), aabb(2.0), aabb(3.0)]);
+        assert_eq!(indices(&batch)[..12], [2, 2, 2, 2, 1, 0, 0, 0, 1, 1, 2, 2]);
+        let commands: Vec<_> = batch
+            .indirect
+            .read_back::<u8>(&device, &queue)
+            .chunks(COMMAND_SIZE as usize)
+            .take(batch.len())
+            .map(|c| bytemuck::pod_read_unaligned::<[u32; 5]>(c)[2])
+            .collect();
+        assert_eq!(commands, [3, 6, 0]);
+
+        // Meshes without indices are removed
+        assert!(batch.set(&device, &queue, 1, &[], &[], aabb(1.0)));
+        assert_eq!(batch.aabbs(), [aabb(3.0), aabb(2.0)]);
+    }
+}
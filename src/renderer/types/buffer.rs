@@ -21,9 +21,19 @@ impl<'a, A: bytemuck::NoUninit> BufferInitDescriptor<'a, A> {
 }
 
 /// Utility for easier handling of a GPU buffer.
+///
+/// The buffer remembers the type of element it holds, so [`Buffer::len`] counts
+/// elements, like the vertices of a vertex buffer or the indices of an index
+/// buffer, while [`Buffer::byte_len`] counts bytes.
 pub struct Buffer {
     inner: wgpu::Buffer,
+    /// Number of elements.
     len: u32,
+    /// Size in bytes.
+    size: wgpu::BufferAddress,
+    usage: wgpu::BufferUsages,
+    /// Kept for the buffer created when [`Buffer::replace`] grows it.
+    label: Option<String>,
 }
 
 impl Buffer {
@@ -34,21 +44,293 @@ impl Buffer {
         Self {
             inner: device.create_buffer_init(&desc.as_raw()),
             len: desc.contents.len() as u32,
+            size: std::mem::size_of_val(desc.contents) as wgpu::BufferAddress,
+            usage: desc.usage,
+            label: desc.label.map(str::to_owned),
         }
     }
 
+    /// Create a buffer holding raw bytes, like serialized mesh data loaded from a file.
+    ///
+    /// Elements of the buffer are the bytes themselves, so [`Buffer::len`] is
+    /// its size in bytes.
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        label: wgpu::Label<'_>,
+        usage: wgpu::BufferUsages,
+        contents: &[u8],
+    ) -> Self {
+        Self {
+            inner: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label,
+                usage,
+                contents,
+            }),
+            len: contents.len() as u32,
+            size: contents.len() as wgpu::BufferAddress,
+            usage,
+            label: label.map(str::to_owned),
+        }
+    }
+
+    /// Create a zero-initialized buffer with room for `len` elements of type `A`.
+    pub fn zeroed<A: bytemuck::NoUninit>(
+        device: &wgpu::Device,
+        label: wgpu::Label<'_>,
+        len: u32,
+        usage: wgpu::BufferUsages,
+    ) -> Self {
+        let size = len as wgpu::BufferAddress * std::mem::size_of::<A>() as wgpu::BufferAddress;
+        Self {
+            // wgpu guarantees that new buffers are zeroed
+            inner: device.create_buffer(&wgpu::BufferDescriptor {
+                label,
+                // Padded like `create_buffer_init` does, so the whole buffer can be copied
+                size: aligned_size(size),
+                usage,
+                mapped_at_creation: false,
+            }),
+            len,
+            size,
+            usage,
+            label: label.map(str::to_owned),
+        }
+    }
+
+    /// Overwrite the contents of this buffer starting at `offset` bytes.
+    ///
+    /// The buffer must have been created with [`wgpu::BufferUsages::COPY_DST`].
+    /// Writes can't grow the buffer, so [`Buffer::len`] is unchanged.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `data` would be written past the end of the buffer.
+    pub fn write<A: bytemuck::NoUninit>(
+        &self,
+        queue: &wgpu::Queue,
+        offset: wgpu::BufferAddress,
+        data: &[A],
+    ) {
+        let end = offset + std::mem::size_of_val(data) as wgpu::BufferAddress;
+        assert!(
+            end <= self.size,
+            "write of {end} bytes overflows buffer of {} bytes",
+            self.size
+        );
+        queue.write_buffer(&self.inner, offset, bytemuck::cast_slice(data));
+    }
+
+    /// Replace the contents of this buffer with `data`, which may be of a new
+    /// length.
+    ///
+    /// The buffer is recreated with the same label and usage if `data` doesn't
+    /// fit, and [`Buffer::len`] becomes the length of `data` either way. Like [`Buffer::write`], it must have been
+    /// created with [`wgpu::BufferUsages::COPY_DST`].
+    pub fn replace<A: bytemuck::NoUninit>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[A],
+    ) {
+        let size = std::mem::size_of_val(data) as wgpu::BufferAddress;
+        if size > self.size {
+            *self = Self::new(
+                device,
+                &BufferInitDescriptor {
+                    label: self.label.as_deref(),
+                    usage: self.usage,
+                    contents: data,
+                },
+            );
+            return;
+        }
+
+        queue.write_buffer(&self.inner, 0, bytemuck::cast_slice(data));
+        self.len = data.len() as u32;
+    }
+
+    /// Grow this buffer to hold at least `len` elements of type `A`, keeping its
+    /// contents.
+    ///
+    /// The contents are copied into the new buffer on the GPU, so it must have
+    /// been created with [`wgpu::BufferUsages::COPY_SRC`]. The buffer at least
+    /// doubles in size, so growing it a little at a time stays cheap. Does
+    /// nothing if it is large enough already.
+    pub fn grow<A: bytemuck::NoUninit>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        len: u32,
+    ) {
+        let size = len as wgpu::BufferAddress * std::mem::size_of::<A>() as wgpu::BufferAddress;
+        if size <= self.size {
+            return;
+        }
+
+        let doubled = 2 * self.size / std::mem::size_of::<A>() as wgpu::BufferAddress;
+        let len = len.max(doubled as u32);
+        let grown = Self::zeroed::<A>(device, self.label.as_deref(), len, self.usage);
+        if self.size > 0 {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Buffer Grow Encoder"),
+            });
+            // Both buffers are padded, so the old one can be copied whole
+            encoder.copy_buffer_to_buffer(&self.inner, 0, &grown.inner, 0, aligned_size(self.size));
+            queue.submit([encoder.finish()]);
+        }
+        *self = grown;
+    }
+
+    /// Copy the contents of this buffer back to the CPU.
+    ///
+    /// Blocks until the GPU has finished all submitted work. Intended for tests
+    /// and diagnostics rather than per-frame use.
+    ///
+    /// Copies must be a multiple of [`wgpu::COPY_BUFFER_ALIGNMENT`], so the
+    /// padding at the end of the buffer is read too, and then dropped.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the buffer wasn't created with [`wgpu::BufferUsages::COPY_SRC`],
+    /// if its size isn't a multiple of the size of `A`, or if mapping fails.
+    pub fn read_back<A: bytemuck::Pod>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Vec<A> {
+        assert!(
+            self.usage.contains(wgpu::BufferUsages::COPY_SRC),
+            "buffer must have COPY_SRC usage to be read back"
+        );
+
+        // Buffers can't be both mapped and used on the GPU, so copy into a staging buffer
+        let padded = aligned_size(self.size);
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Read Back Staging Buffer"),
+            size: padded,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Read Back Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&self.inner, 0, &staging, 0, padded);
+        queue.submit([encoder.finish()]);
+
+        // Wait for the copy to complete and the buffer to be mapped
+        let slice = staging.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        receiver
+            .recv()
+            .expect("buffer mapping callback was never called")
+            .expect("failed to map buffer");
+
+        let data = bytemuck::cast_slice(&slice.get_mapped_range()[..self.size as usize]).to_vec();
+        staging.unmap();
+        data
+    }
+
     #[inline]
     pub const fn inner(&self) -> &wgpu::Buffer {
         &self.inner
     }
 
+    /// Number of elements in the buffer, not bytes.
+    ///
+    /// This is the vertex count of a vertex buffer and the index count of an
+    /// index buffer, as passed to draw calls.
     #[inline]
     pub const fn len(&self) -> u32 {
         self.len
     }
 
+    /// Whether the buffer holds no elements.
     #[inline]
     pub const fn is_empty(&self) -> bool {
         self.len == 0
     }
+
+    /// Size of the buffer in bytes.
+    ///
+    /// After [`Buffer::replace`] with shorter data, this is the size of the
+    /// allocation rather than of the elements in use.
+    #[inline]
+    pub const fn byte_len(&self) -> wgpu::BufferAddress {
+        self.size
+    }
+}
+
+/// Round `size` up to a multiple of [`wgpu::COPY_BUFFER_ALIGNMENT`].
+#[inline]
+fn aligned_size(size: wgpu::BufferAddress) -> wgpu::BufferAddress {
+    wgpu::util::align_to(size, wgpu::COPY_BUFFER_ALIGNMENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_device;
+    use super::*;
+
+    #[test]
+    fn reads_back_unaligned_buffers() {
+        let (_, device, queue) = test_device();
+        let usage = wgpu::BufferUsages::COPY_SRC;
+        let bytes = Buffer::from_bytes(&device, None, usage, &[1, 2, 3, 4, 5]);
+        assert_eq!(bytes.read_back::<u8>(&device, &queue), [1, 2, 3, 4, 5]);
+
+        let shorts = Buffer::zeroed::<u16>(&device, None, 3, usage);
+        assert_eq!(shorts.read_back::<u16>(&device, &queue), [0; 3]);
+    }
+
+    #[test]
+    fn replace_grows_and_shrinks() {
+        let (_, device, queue) = test_device();
+        let mut buffer = Buffer::new(
+            &device,
+            &BufferInitDescriptor {
+                label: Some("replaced"),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+                contents: &[1u32, 2],
+            },
+        );
+
+        buffer.replace(&device, &queue, &[3u32, 4, 5, 6]);
+        assert_eq!((buffer.len(), buffer.byte_len()), (4, 16));
+        assert_eq!(buffer.label.as_deref(), Some("replaced"));
+        assert_eq!(buffer.read_back::<u32>(&device, &queue), [3, 4, 5, 6]);
+
+        // Shrinking keeps the allocation, and the old elements after the new ones
+        buffer.replace(&device, &queue, &[7u32]);
+        assert_eq!((buffer.len(), buffer.byte_len()), (1, 16));
+        assert_eq!(buffer.label.as_deref(), Some("replaced"));
+        assert_eq!(buffer.read_back::<u32>(&device, &queue), [7, 4, 5, 6]);
+    }
+
+    #[test]
+    fn grow_keeps_contents() {
+        let (_, device, queue) = test_device();
+        let mut buffer = Buffer::new(
+            &device,
+            &BufferInitDescriptor {
+                label: Some("grown"),
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+                contents: &[1u32, 2, 3],
+            },
+        );
+
+        buffer.grow::<u32>(&device, &queue, 2);
+        assert_eq!(buffer.byte_len(), 12);
+        buffer.grow::<u32>(&device, &queue, 4);
+        assert_eq!((buffer.len(), buffer.byte_len()), (6, 24));
+        assert_eq!(buffer.label.as_deref(), Some("grown"));
+        assert_eq!(buffer.read_back::<u32>(&device, &queue), [1, 2, 3, 0, 0, 0]);
+        buffer.grow::<u32>(&device, &queue, 20);
+        assert_eq!(buffer.len(), 20);
+    }
 }
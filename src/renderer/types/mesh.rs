@@ -0,0 +1,155 @@
+//! Indexed geometry on the GPU.
+
+use nalgebra_glm as glm;
+
+use super::buffer::{Buffer, BufferInitDescriptor};
+use super::Vertex;
+
+/// A vertex buffer and the index buffer drawing it.
+pub struct Mesh {
+    vbo: Buffer,
+    ibo: Buffer,
+    /// Arguments to draw the mesh with, if they are only known on the GPU.
+    indirect: Option<Buffer>,
+    /// World-space (min, max) corners enclosing the geometry.
+    aabb: (glm::Vec3, glm::Vec3),
+}
+
+impl Mesh {
+    /// Upload geometry to the GPU.
+    ///
+    /// `aabb` is the (min, max) pair of corners enclosing the geometry.
+    pub fn new(
+        device: &wgpu::Device,
+        label: Option<&str>,
+        vertices: &[Vertex],
+        indices: &[u32],
+        aabb: (glm::Vec3, glm::Vec3),
+    ) -> Self {
+        let vbo_label = label.map(|x| format!("{x}_vertices"));
+        let vbo = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: vbo_label.as_deref(),
+                usage: wgpu::BufferUsages::VERTEX,
+                contents: vertices,
+            },
+        );
+
+        let ibo_label = label.map(|x| format!("{x}_indices"));
+        let ibo = Buffer::new(
+            device,
+            &BufferInitDescriptor {
+                label: ibo_label.as_deref(),
+                usage: wgpu::BufferUsages::INDEX,
+                contents: indices,
+            },
+        );
+
+        Self::from_buffers(vbo, ibo, None, aabb)
+    }
+
+    /// Create a mesh with no geometry, like that of a chunk full of air.
+    ///
+    /// Drawing it does nothing.
+    pub fn empty(device: &wgpu::Device, label: Option<&str>, aabb: (glm::Vec3, glm::Vec3)) -> Self {
+        Self::new(device, label, &[], &[], aabb)
+    }
+
+    /// Create a mesh from geometry that is already on the GPU.
+    ///
+    /// If `indirect` is given, it must hold a single
+    /// [`DrawIndexedIndirect`](wgpu::util::DrawIndexedIndirect), which is used to
+    /// draw the mesh instead of the length of the index buffer.
+    pub fn from_buffers(
+        vbo: Buffer,
+        ibo: Buffer,
+        indirect: Option<Buffer>,
+        aabb: (glm::Vec3, glm::Vec3),
+    ) -> Self {
+        Self {
+            vbo,
+            ibo,
+            indirect,
+            aabb,
+        }
+    }
+
+    /// Record a draw of this mesh, unless it is empty.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.is_empty() {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vbo.inner().slice(..));
+        render_pass.set_index_buffer(self.ibo.inner().slice(..), wgpu::IndexFormat::Uint32);
+        match &self.indirect {
+            Some(indirect) => render_pass.draw_indexed_indirect(indirect.inner(), 0),
+            None => render_pass.draw_indexed(0..self.ibo.len(), 0, 0..1),
+        }
+    }
+
+    /// Whether the mesh has no indices, and so nothing to draw.
+    ///
+    /// Meshes drawn indirectly have an index buffer sized to fit their
+    /// geometry, so this holds for them too.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ibo.len() == 0
+    }
+
+    /// Get the world-space (min, max) corners enclosing the geometry.
+    #[inline]
+    pub const fn aabb(&self) -> (glm::Vec3, glm::Vec3) {
+        self.aabb
+    }
+
+    /// Memory taken by the mesh's buffers on the GPU, in bytes.
+    pub fn byte_len(&self) -> wgpu::BufferAddress {
+        self.vbo.byte_len()
+            + self.ibo.byte_len()
+            + self.indirect.as_ref().map_or(0, Buffer::byte_len)
+    }
+
+    /// Get the vertex buffer.
+    #[inline]
+    pub const fn vbo(&self) -> &Buffer {
+        &self.vbo
+    }
+
+    /// Get the index buffer.
+    #[inline]
+    pub const fn ibo(&self) -> &Buffer {
+        &self.ibo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::batch::MeshBatch;
+    use super::super::test_device;
+    use super::*;
+
+    #[test]
+    fn empty_meshes_are_skipped() {
+        let (_, device, _) = test_device();
+        let vertices = [bytemuck::Zeroable::zeroed(); 4];
+        let indices = [0, 1, 2, 0, 2, 3];
+        let empty_aabb = (glm::Vec3::zeros(), glm::vec3(1.0, 1.0, 1.0));
+        let full_aabb = (glm::vec3(1.0, 0.0, 0.0), glm::vec3(2.0, 1.0, 1.0));
+
+        assert!(Mesh::empty(&device, None, empty_aabb).is_empty());
+        assert!(!Mesh::new(&device, None, &vertices, &indices, full_aabb).is_empty());
+
+        // Only the chunk with geometry gets a draw
+        let batch = MeshBatch::new(
+            &device,
+            None,
+            [
+                (0, &[][..], &[][..], empty_aabb),
+                (1, &vertices[..], &indices[..], full_aabb),
+            ],
+        );
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.aabbs(), [full_aabb]);
+    }
+}
@@ -1,7 +1,11 @@
 //! Type definitions.
 
+pub mod batch;
 pub mod binding;
 pub mod buffer;
+pub mod mesh;
+pub mod readback;
+pub mod target;
 pub mod texture;
 
 /// Describes a point in 3D space.
@@ -10,11 +14,43 @@ pub mod texture;
 pub struct Vertex {
     pub position: [f32; 3],
     pub texture: [f32; 2],
+    /// How strongly the grass color tints this vertex, from 0 to 1.
+    pub tint: f32,
+    /// Direction the surface faces.
+    pub normal: [f32; 3],
+    /// Layer of the block texture array to sample.
+    pub layer: u32,
+    /// Direction along which the U texture coordinate increases.
+    ///
+    /// `w` is the handedness of the tangent frame: the direction of increasing
+    /// V is `cross(normal, tangent) * w`.
+    pub tangent: [f32; 4],
+    /// Brightness of the light this vertex gives off, added after lighting.
+    pub emissive: f32,
+    /// Block light reaching the surface, from 0 to 1.
+    pub light: f32,
+    /// Skylight reaching the surface, from 0 to 1.
+    ///
+    /// Lets the sun and the sky's ambient light in, so it dims at night.
+    pub sky_light: f32,
+    /// Fixed brightness of the direction the face points in, multiplied into
+    /// its lighting so that faces of a flat scene stand apart.
+    pub shade: f32,
 }
 
 impl Vertex {
-    const ATTRS: [wgpu::VertexAttribute; 2] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+    const ATTRS: [wgpu::VertexAttribute; 10] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x2,
+        2 => Float32,
+        3 => Float32x3,
+        4 => Uint32,
+        5 => Float32x4,
+        6 => Float32,
+        7 => Float32,
+        8 => Float32,
+        9 => Float32,
+    ];
 
     pub const BUFFER_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
         array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
@@ -22,3 +58,148 @@ impl Vertex {
         attributes: &Self::ATTRS,
     };
 }
+
+/// Uniform data of a single chunk mesh, bound with a dynamic offset before the
+/// chunk is drawn.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ChunkUniform {
+    /// Transform from the mesh's vertex positions to world space.
+    pub model: [[f32; 4]; 4],
+    /// Debug color the chunk is tinted with.
+    pub tint: [f32; 4],
+}
+
+/// Uniform data shared by all shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Globals {
+    /// Combined view and projection transform of the camera.
+    pub view_proj: [[f32; 4]; 4],
+    /// View transform of the camera.
+    pub view: [[f32; 4]; 4],
+    /// Color that distant fragments fade into.
+    pub fog_color: [f32; 4],
+    /// View-space depth at which fog starts.
+    pub fog_start: f32,
+    /// View-space depth at which fog is opaque.
+    pub fog_end: f32,
+    /// How strongly each chunk is tinted by its debug color, from 0 to 1.
+    pub chunk_tint: f32,
+    /// Seconds since the renderer started, for animation.
+    pub time: f32,
+    /// Color multiplied into tinted faces, like the top of grass.
+    pub grass_color: [f32; 4],
+    /// Direction towards the sun, with the strength of sunlight from 0 to 1 in `w`.
+    pub sun: [f32; 4],
+    /// 1 if the target format lacks sRGB encoding, so shaders encode colors
+    /// themselves, otherwise 0.
+    pub encode_srgb: f32,
+    /// Mip levels added when sampling block textures.
+    pub mip_bias: f32,
+    /// Fraction of daylight reaching faces turned away from the sun.
+    pub ambient: f32,
+    pub _padding: f32,
+}
+
+/// Maximum number of point lights, so that their uniform has a fixed size.
+pub const MAX_POINT_LIGHTS: usize = 16;
+
+/// A light shining in all directions from a point.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    /// Distance at which the light fades out completely.
+    pub range: f32,
+    /// Linear color of the light. Alpha is unused.
+    pub color: [f32; 4],
+}
+
+/// Uniform data holding every point light.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLights {
+    /// Number of lights in use, at the front of `lights`.
+    pub count: u32,
+    pub _padding: [u32; 3],
+    pub lights: [PointLight; MAX_POINT_LIGHTS],
+}
+
+/// Maximum number of layers in the block texture array, so that the uniform
+/// animating them has a fixed size.
+pub const MAX_TEXTURE_LAYERS: usize = 64;
+
+/// How a layer of the block texture array cycles through animation frames.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextureAnimation {
+    /// Number of frames, in consecutive layers starting at this one.
+    ///
+    /// Layers with fewer than two frames aren't animated.
+    pub frames: u32,
+    /// Frames shown per second.
+    pub fps: f32,
+    pub _padding: [u32; 2],
+}
+
+/// Uniform data holding the animation of every block texture layer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextureAnimations {
+    pub layers: [TextureAnimation; MAX_TEXTURE_LAYERS],
+}
+
+/// Distance fog settings.
+///
+/// Shaders divide by the length of the fog's range, so `end` must stay above
+/// `start`. [`Fog::new`] makes sure it does.
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub color: wgpu::Color,
+    /// View-space depth at which fog starts.
+    pub start: f32,
+    /// View-space depth at which fog is opaque.
+    pub end: f32,
+}
+
+impl Fog {
+    /// Shortest distance over which fog thickens, so it never divides by zero.
+    pub const MIN_RANGE: f32 = 0.01;
+
+    /// Fog from `start` to `end`, moving `end` past `start` if it isn't.
+    pub fn new(color: wgpu::Color, start: f32, end: f32) -> Self {
+        Self {
+            color,
+            start,
+            end: end.max(start + Self::MIN_RANGE),
+        }
+    }
+}
+
+/// Open a device on the first adapter found, for tests that need a GPU.
+///
+/// ## Panics
+///
+/// Panics if there is no adapter, so that GPU tests fail instead of passing
+/// without having run.
+#[cfg(test)]
+pub(crate) fn test_device() -> (wgpu::Adapter, wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::LowPower,
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }))
+    .expect("no graphics adapter available");
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("Test Device"),
+            features: wgpu::Features::empty(),
+            limits: adapter.limits(),
+        },
+        None,
+    ))
+    .expect("failed to open a device on the adapter");
+    (adapter, device, queue)
+}
@@ -0,0 +1,176 @@
+//! Reading results back from the GPU without stalling.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Ready for a new copy.
+    Idle,
+    /// A copy was recorded, but not submitted yet.
+    Recorded,
+    /// Waiting for the copy to be mapped.
+    Mapping,
+}
+
+/// Values of [`AsyncReadback::mapped`], written by the `map_async` callback.
+const MAP_PENDING: u8 = 0;
+const MAP_DONE: u8 = 1;
+const MAP_FAILED: u8 = 2;
+
+/// A pair of buffers reading data written on the GPU, like resolved queries,
+/// back to the CPU a frame or more later.
+///
+/// Data is written into [`AsyncReadback::source`], copied out with
+/// [`AsyncReadback::copy`], and collected with [`AsyncReadback::poll`] once
+/// mapped. Only one copy is in flight at a time.
+pub struct AsyncReadback {
+    label: String,
+    /// Written on the GPU, like by `resolve_query_set`.
+    source: wgpu::Buffer,
+    /// CPU-readable copy of `source`.
+    readback: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    /// Number of bytes copied by the copy in flight.
+    copied: wgpu::BufferAddress,
+    /// Outcome of mapping `readback`, one of the `MAP_*` values.
+    mapped: Arc<AtomicU8>,
+    state: State,
+}
+
+impl AsyncReadback {
+    /// Create the buffers for reading back up to `size` bytes.
+    pub fn new(device: &wgpu::Device, label: &str, size: wgpu::BufferAddress) -> Self {
+        let source = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{label}_resolve")),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&format!("{label}_readback")),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            label: label.to_owned(),
+            source,
+            readback,
+            size,
+            copied: 0,
+            mapped: Arc::new(AtomicU8::new(MAP_PENDING)),
+            state: State::Idle,
+        }
+    }
+
+    /// Whether no copy is in flight, so new data can be written and copied.
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.state == State::Idle
+    }
+
+    /// Get the buffer to write the data into on the GPU.
+    #[inline]
+    pub const fn source(&self) -> &wgpu::Buffer {
+        &self.source
+    }
+
+    /// Copy the first `size` bytes of the source into the readback buffer,
+    /// unless a previous copy is still in flight.
+    pub fn copy(&mut self, encoder: &mut wgpu::CommandEncoder, size: wgpu::BufferAddress) {
+        if self.state != State::Idle {
+            return;
+        }
+
+        encoder.copy_buffer_to_buffer(&self.source, 0, &self.readback, 0, size);
+        self.copied = size;
+        self.state = State::Recorded;
+    }
+
+    /// Start mapping the copied data.
+    ///
+    /// Must be called after the encoder passed to [`AsyncReadback::copy`] is
+    /// submitted.
+    pub fn submitted(&mut self) {
+        if self.state != State::Recorded {
+            return;
+        }
+
+        let mapped = self.mapped.clone();
+        self.readback
+            .slice(..self.copied)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let outcome = if result.is_ok() { MAP_DONE } else { MAP_FAILED };
+                mapped.store(outcome, Ordering::Release);
+            });
+        self.state = State::Mapping;
+    }
+
+    /// Read the copied data with `read`, if it has been mapped.
+    ///
+    /// Returns `None` while it is still in flight, or if mapping it failed, in
+    /// which case the data is dropped and a new copy can be made.
+    pub fn poll<T>(&mut self, device: &wgpu::Device, read: impl FnOnce(&[u8]) -> T) -> Option<T> {
+        if self.state != State::Mapping {
+            return None;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        match self.mapped.swap(MAP_PENDING, Ordering::Acquire) {
+            MAP_DONE => {}
+            MAP_FAILED => {
+                // A failed map leaves the buffer unmapped, so just try again
+                // on a later frame instead of waiting forever.
+                tracing::warn!("failed to map {} results", self.label);
+                self.state = State::Idle;
+                return None;
+            }
+            _ => return None,
+        }
+
+        let result = read(&self.readback.slice(..self.copied).get_mapped_range());
+        self.readback.unmap();
+        self.state = State::Idle;
+        Some(result)
+    }
+
+    /// Memory taken by both buffers on the GPU, in bytes.
+    #[inline]
+    pub const fn byte_len(&self) -> wgpu::BufferAddress {
+        2 * self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_device;
+    use super::*;
+
+    #[test]
+    fn reads_back_one_copy_at_a_time() {
+        let (_, device, queue) = test_device();
+        let mut readback = AsyncReadback::new(&device, "test", 16);
+        queue.write_buffer(readback.source(), 0, bytemuck::cast_slice(&[1u32, 2, 3, 4]));
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        readback.copy(&mut encoder, 8);
+        assert!(!readback.is_idle());
+        // Only the first copy is made until it has been read
+        readback.copy(&mut encoder, 16);
+        queue.submit([encoder.finish()]);
+        readback.submitted();
+
+        let read = |data: &[u8]| bytemuck::cast_slice::<u8, u32>(data).to_vec();
+        let data = loop {
+            if let Some(data) = readback.poll(&device, read) {
+                break data;
+            }
+            device.poll(wgpu::Maintain::Wait);
+        };
+        assert_eq!(data, [1, 2]);
+        assert!(readback.is_idle());
+        assert_eq!(readback.poll(&device, read), None);
+    }
+}
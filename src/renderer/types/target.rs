@@ -0,0 +1,217 @@
+//! Offscreen render targets.
+
+use super::texture::Texture;
+
+/// Identifies a render target created with
+/// [`Renderer::create_render_target`](crate::renderer::Renderer::create_render_target).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTargetId(pub(crate) u32);
+
+/// Describes a [`RenderTarget`].
+pub struct RenderTargetDescriptor<'a> {
+    pub label: wgpu::Label<'a>,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    /// Whether to create a depth buffer alongside the color buffer.
+    pub depth: bool,
+}
+
+/// A texture that can be rendered to instead of a surface.
+///
+/// The color buffer can be sampled by later passes or copied back to the CPU.
+pub struct RenderTarget {
+    color: Texture,
+    depth: Option<Texture>,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    label: Option<String>,
+}
+
+impl RenderTarget {
+    pub fn new(device: &wgpu::Device, desc: &RenderTargetDescriptor<'_>) -> Self {
+        let label = desc.label.map(str::to_owned);
+        let (color, depth) = Self::create_textures(
+            device,
+            label.as_deref(),
+            desc.width,
+            desc.height,
+            desc.format,
+            desc.depth,
+        );
+
+        Self {
+            color,
+            depth,
+            format: desc.format,
+            width: desc.width,
+            height: desc.height,
+            label,
+        }
+    }
+
+    fn create_textures(
+        device: &wgpu::Device,
+        label: Option<&str>,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        depth: bool,
+    ) -> (Texture, Option<Texture>) {
+        let color_label = label.map(|x| format!("{x}_color"));
+        let color = Texture::render_target(device, width, height, format, color_label.as_deref());
+
+        let depth = depth.then(|| {
+            let depth_label = label.map(|x| format!("{x}_depth"));
+            Texture::depth(device, width, height, 1, depth_label.as_deref())
+        });
+
+        (color, depth)
+    }
+
+    /// Recreate the target's textures at a new size.
+    ///
+    /// Does nothing if the size is unchanged or either dimension is zero.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) || width == 0 || height == 0 {
+            return;
+        }
+
+        (self.color, self.depth) = Self::create_textures(
+            device,
+            self.label.as_deref(),
+            width,
+            height,
+            self.format,
+            self.depth.is_some(),
+        );
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Describe the color buffer as an attachment of a render pass.
+    #[inline]
+    pub fn color_attachment(
+        &self,
+        ops: wgpu::Operations<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'_> {
+        wgpu::RenderPassColorAttachment {
+            view: self.color.view(),
+            resolve_target: None,
+            ops,
+        }
+    }
+
+    /// Describe the depth buffer as an attachment of a render pass, if there is one.
+    #[inline]
+    pub fn depth_attachment(
+        &self,
+        ops: wgpu::Operations<f32>,
+    ) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+        self.depth
+            .as_ref()
+            .map(|depth| wgpu::RenderPassDepthStencilAttachment {
+                view: depth.view(),
+                depth_ops: Some(ops),
+                stencil_ops: None,
+            })
+    }
+
+    /// Copy the color buffer back to the CPU.
+    ///
+    /// Blocks until the GPU has finished all submitted work, including rendering
+    /// to this target.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the format isn't 8-bit RGBA or BGRA, or if mapping fails.
+    pub fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> image::RgbaImage {
+        use wgpu::TextureFormat as F;
+        let bgra = match self.format {
+            F::Rgba8Unorm | F::Rgba8UnormSrgb => false,
+            F::Bgra8Unorm | F::Bgra8UnormSrgb => true,
+            format => panic!("can't read back pixels of format {format:?}"),
+        };
+
+        // Rows of a copy must be aligned, so pad them and strip the padding after
+        let row_bytes = self.width * 4;
+        let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Read Pixels Staging Buffer"),
+            size: padded_row_bytes as wgpu::BufferAddress * self.height as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Read Pixels Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.color.inner().as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &staging,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_row_bytes),
+                    rows_per_image: std::num::NonZeroU32::new(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("buffer mapping callback was never called")
+            .expect("failed to map buffer");
+
+        let mut pixels = Vec::with_capacity((row_bytes * self.height) as usize);
+        for row in slice
+            .get_mapped_range()
+            .chunks_exact(padded_row_bytes as usize)
+        {
+            pixels.extend_from_slice(&row[..row_bytes as usize]);
+        }
+        staging.unmap();
+
+        if bgra {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        image::RgbaImage::from_raw(self.width, self.height, pixels)
+            .expect("pixel data matches the target size")
+    }
+
+    #[inline]
+    pub const fn color(&self) -> &Texture {
+        &self.color
+    }
+
+    #[inline]
+    pub const fn depth(&self) -> Option<&Texture> {
+        self.depth.as_ref()
+    }
+
+    #[inline]
+    pub const fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Get the (width, height) of the target in pixels.
+    #[inline]
+    pub const fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
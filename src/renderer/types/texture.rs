@@ -2,22 +2,46 @@
 
 use image::GenericImageView;
 
+/// Image types used by the texture API, so callers don't need their own `image` dependency.
+pub use image::{DynamicImage, Rgba, RgbaImage};
+
+/// Colors of the squares of [`checkerboard_image`], picked to stand out from
+/// any real texture.
+pub const CHECKERBOARD_COLORS: [image::Rgba<u8>; 2] =
+    [image::Rgba([255, 0, 255, 255]), image::Rgba([0, 0, 0, 255])];
+
+/// Side of each square of [`checkerboard_image`], in pixels.
+pub const CHECKERBOARD_SQUARE: u32 = 8;
+
+/// A magenta and black checkerboard, shown in place of textures that failed
+/// to load so that missing assets are obvious without being fatal.
+pub fn checkerboard_image(width: u32, height: u32) -> image::RgbaImage {
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let square = (x / CHECKERBOARD_SQUARE + y / CHECKERBOARD_SQUARE) % 2;
+        CHECKERBOARD_COLORS[square as usize]
+    })
+}
+
 pub struct TextureDescriptor<'a> {
     pub label: wgpu::Label<'a>,
     pub mip_level_count: u32,
     pub sample_count: u32,
-    pub image: &'a image::DynamicImage,
+    /// Format of the texture, which must be an 8-bit RGBA format.
+    ///
+    /// Images with other layouts are converted to RGBA8 before being uploaded.
+    pub format: wgpu::TextureFormat,
+    pub image: &'a DynamicImage,
 }
 
 impl<'a> TextureDescriptor<'a> {
     #[inline]
-    pub fn as_raw(&self) -> wgpu::TextureDescriptor {
+    pub fn as_raw(&self) -> wgpu::TextureDescriptor<'_> {
         wgpu::TextureDescriptor {
             label: self.label,
             mip_level_count: self.mip_level_count,
             sample_count: self.sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: self.format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             size: self.size(),
         }
@@ -34,58 +58,480 @@ impl<'a> TextureDescriptor<'a> {
     }
 }
 
+/// How a texture is filtered and addressed when sampled.
+///
+/// Defaults to nearest filtering with repeating coordinates, which keeps block
+/// textures crisp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerOptions {
+    /// Filter used when magnifying and minifying.
+    pub filter: wgpu::FilterMode,
+    /// Filter used between mip levels.
+    pub mipmap_filter: wgpu::FilterMode,
+    pub address_mode: wgpu::AddressMode,
+    /// Most detailed mip level that may be sampled.
+    pub lod_min_clamp: f32,
+    /// Least detailed mip level that may be sampled.
+    pub lod_max_clamp: f32,
+    /// Mip levels added to the one picked by the GPU. Positive values sample
+    /// blurrier levels, which reduces shimmering on distant pixel art.
+    ///
+    /// Samplers can't bias the level themselves, so shaders must pass this to
+    /// `textureSampleBias`.
+    pub mip_bias: f32,
+}
+
+impl Default for SamplerOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::nearest()
+    }
+}
+
+impl SamplerOptions {
+    /// Sharp, pixelated sampling.
+    #[inline]
+    pub const fn nearest() -> Self {
+        Self {
+            filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            address_mode: wgpu::AddressMode::Repeat,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+            mip_bias: 0.0,
+        }
+    }
+
+    /// Smooth sampling, for UI and effects.
+    #[inline]
+    pub const fn linear() -> Self {
+        Self {
+            filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            address_mode: wgpu::AddressMode::ClampToEdge,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: f32::MAX,
+            mip_bias: 0.0,
+        }
+    }
+
+    #[inline]
+    pub fn as_raw<'a>(&self, label: wgpu::Label<'a>) -> wgpu::SamplerDescriptor<'a> {
+        wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.filter,
+            min_filter: self.filter,
+            mipmap_filter: self.mipmap_filter,
+            lod_min_clamp: self.lod_min_clamp,
+            lod_max_clamp: self.lod_max_clamp,
+            ..Default::default()
+        }
+    }
+}
+
 pub struct Texture {
     inner: wgpu::Texture,
     view: wgpu::TextureView,
     sampler: wgpu::Sampler,
+    /// Estimated memory taken by the texture, in bytes.
+    byte_size: u64,
 }
 
 impl Texture {
+    /// Create a texture, along with an estimate of the memory it takes in bytes.
+    ///
+    /// Counts every mip level, layer and sample. Drivers may pad textures, so
+    /// the real size can be larger.
+    fn create(device: &wgpu::Device, desc: &wgpu::TextureDescriptor) -> (wgpu::Texture, u64) {
+        let info = desc.format.describe();
+        let (block_width, block_height) = info.block_dimensions;
+        let byte_size = (0..desc.mip_level_count)
+            .filter_map(|level| desc.mip_level_size(level))
+            .map(|size| {
+                let blocks_wide = size.width.div_ceil(block_width as u32) as u64;
+                let blocks_high = size.height.div_ceil(block_height as u32) as u64;
+                blocks_wide * blocks_high * info.block_size as u64
+            })
+            .sum::<u64>()
+            * desc.array_layer_count() as u64
+            * desc.sample_count as u64;
+        (device.create_texture(desc), byte_size)
+    }
+
+    /// Create a texture from an image.
+    ///
+    /// Sampled with [`SamplerOptions::default`] unless a sampler is given.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the descriptor's format doesn't have 4 bytes per pixel.
     pub fn new(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         desc: &TextureDescriptor<'_>,
         sampler_desc: Option<&wgpu::SamplerDescriptor>,
     ) -> Self {
-        let inner = device.create_texture(&desc.as_raw());
+        assert_eq!(
+            desc.format.describe().block_size,
+            4,
+            "texture format must have 4 bytes per pixel"
+        );
 
-        let size = desc.size();
+        let (inner, byte_size) = Self::create(device, &desc.as_raw());
+        // Grayscale, RGB or 16-bit images would otherwise be uploaded with the wrong layout
+        let pixels = desc.image.to_rgba8();
+        Self::write_base_level(queue, &inner, &pixels, desc.size(), 4);
 
+        let view = inner.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = match sampler_desc {
+            Some(s) => device.create_sampler(s),
+            None => device.create_sampler(&SamplerOptions::default().as_raw(desc.label)),
+        };
+
+        Self {
+            inner,
+            view,
+            sampler,
+            byte_size,
+        }
+    }
+
+    /// Create a texture from raw pixel data, without going through the `image` crate.
+    ///
+    /// `data` holds `height` tightly packed rows of `width` pixels in `format`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `data` isn't exactly the size of the texture, or `format` is compressed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_raw(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sampler: SamplerOptions,
+        label: wgpu::Label<'_>,
+    ) -> Self {
+        let info = format.describe();
+        assert_eq!(
+            info.block_dimensions,
+            (1, 1),
+            "compressed formats are unsupported"
+        );
+        let bytes_per_pixel = info.block_size as u32;
+        assert_eq!(
+            data.len(),
+            (width * height * bytes_per_pixel) as usize,
+            "pixel data doesn't match the texture size"
+        );
+
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let (inner, byte_size) = Self::create(
+            device,
+            &wgpu::TextureDescriptor {
+                label,
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            },
+        );
+        Self::write_base_level(queue, &inner, data, size, bytes_per_pixel);
+
+        let view = inner.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&sampler.as_raw(label));
+
+        Self {
+            inner,
+            view,
+            sampler,
+            byte_size,
+        }
+    }
+
+    /// Create a `size` by `size` texture of [`checkerboard_image`], to bind in
+    /// place of a texture that failed to load.
+    pub fn debug_checkerboard(device: &wgpu::Device, queue: &wgpu::Queue, size: u32) -> Self {
+        let image = checkerboard_image(size, size);
+        Self::from_raw(
+            device,
+            queue,
+            &image,
+            size,
+            size,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            SamplerOptions::nearest(),
+            Some("debug_checkerboard"),
+        )
+    }
+
+    /// Upload the full-size mip level of a 2D texture.
+    fn write_base_level(
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        data: &[u8],
+        size: wgpu::Extent3d,
+        bytes_per_pixel: u32,
+    ) {
         queue.write_texture(
             wgpu::ImageCopyTexture {
-                texture: &inner,
+                texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            desc.image.as_bytes(),
+            data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row: std::num::NonZeroU32::new(4 * size.width),
+                bytes_per_row: std::num::NonZeroU32::new(bytes_per_pixel * size.width),
                 rows_per_image: std::num::NonZeroU32::new(size.height),
             },
             size,
         );
+    }
+
+    /// Create a 2D texture array with one layer per image, and a full mip chain.
+    ///
+    /// Mipmaps are generated per layer, so unlike an atlas, neighbouring images
+    /// never bleed into each other at a distance.
+    ///
+    /// Images are expected to be normalized to the same size first, like by an
+    /// [`AtlasBuilder`](crate::renderer::atlas::AtlasBuilder).
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `images` is empty, or the images differ in size.
+    pub fn array(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[image::RgbaImage],
+        format: wgpu::TextureFormat,
+        sampler: SamplerOptions,
+        label: wgpu::Label<'_>,
+    ) -> Self {
+        assert!(!images.is_empty(), "texture array needs at least one image");
+        let (width, height) = images[0].dimensions();
+        assert!(
+            images.iter().all(|i| i.dimensions() == (width, height)),
+            "texture array images must all be the same size"
+        );
+
+        let mip_level_count = 32 - width.min(height).leading_zeros();
+        let (inner, byte_size) = Self::create(
+            device,
+            &wgpu::TextureDescriptor {
+                label,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: images.len() as u32,
+                },
+                mip_level_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            },
+        );
+
+        for (layer, image) in images.iter().enumerate() {
+            let mut mip = image.clone();
+            for level in 0..mip_level_count {
+                if level > 0 {
+                    mip = image::imageops::resize(
+                        &mip,
+                        (mip.width() / 2).max(1),
+                        (mip.height() / 2).max(1),
+                        image::imageops::FilterType::Triangle,
+                    );
+                }
+
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &inner,
+                        mip_level: level,
+                        origin: wgpu::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer as u32,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &mip,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: std::num::NonZeroU32::new(4 * mip.width()),
+                        rows_per_image: std::num::NonZeroU32::new(mip.height()),
+                    },
+                    wgpu::Extent3d {
+                        width: mip.width(),
+                        height: mip.height(),
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+
+        let view = inner.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let sampler = device.create_sampler(&sampler.as_raw(label));
+
+        Self {
+            inner,
+            view,
+            sampler,
+            byte_size,
+        }
+    }
+
+    /// Format used for depth buffers.
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// Create a depth buffer.
+    pub fn depth(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        label: wgpu::Label<'_>,
+    ) -> Self {
+        let (inner, byte_size) = Self::create(
+            device,
+            &wgpu::TextureDescriptor {
+                label,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format: Self::DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        );
 
         let view = inner.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let sampler = match sampler_desc {
-            Some(s) => device.create_sampler(s),
-            None => device.create_sampler(&wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::Repeat,
-                address_mode_v: wgpu::AddressMode::Repeat,
-                address_mode_w: wgpu::AddressMode::Repeat,
-                mag_filter: wgpu::FilterMode::Nearest,
-                min_filter: wgpu::FilterMode::Nearest,
-                mipmap_filter: wgpu::FilterMode::Nearest,
-                ..Default::default()
-            }),
-        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
 
         Self {
             inner,
             view,
             sampler,
+            byte_size,
+        }
+    }
+
+    /// Create a multisampled color buffer.
+    ///
+    /// Rendered to in place of the surface and resolved into it when MSAA is enabled.
+    pub fn multisampled(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        label: wgpu::Label<'_>,
+    ) -> Self {
+        let (inner, byte_size) = Self::create(
+            device,
+            &wgpu::TextureDescriptor {
+                label,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            },
+        );
+
+        let view = inner.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        Self {
+            inner,
+            view,
+            sampler,
+            byte_size,
+        }
+    }
+
+    /// Create a color buffer that can be rendered to, sampled and copied from.
+    pub fn render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: wgpu::Label<'_>,
+    ) -> Self {
+        let (inner, byte_size) = Self::create(
+            device,
+            &wgpu::TextureDescriptor {
+                label,
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        );
+
+        let view = inner.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            inner,
+            view,
+            sampler,
+            byte_size,
         }
     }
 
@@ -103,4 +549,10 @@ impl Texture {
     pub const fn sampler(&self) -> &wgpu::Sampler {
         &self.sampler
     }
+
+    /// Estimated memory taken by the texture on the GPU, in bytes.
+    #[inline]
+    pub const fn byte_size(&self) -> u64 {
+        self.byte_size
+    }
 }
@@ -0,0 +1,291 @@
+//! Screen-space overlay, like the crosshair.
+
+use wgpu::util::DeviceExt;
+
+use super::shader;
+use super::types::texture::Texture;
+
+/// A corner of a UI shape.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct UiVertex {
+    /// Position in UI units, relative to the center of the screen, with Y up.
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl UiVertex {
+    const ATTRS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4];
+
+    pub const BUFFER_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &Self::ATTRS,
+    };
+}
+
+/// Maps UI units to normalized device coordinates.
+///
+/// A UI unit is one physical pixel at a scale of 1, on both axes, so shapes
+/// keep their proportions at any aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiTransform {
+    width: u32,
+    height: u32,
+    /// Combined window scale factor and user UI scale.
+    scale: f32,
+}
+
+impl UiTransform {
+    pub fn new(width: u32, height: u32, scale: f32) -> Self {
+        Self {
+            width: width.max(1),
+            height: height.max(1),
+            scale,
+        }
+    }
+
+    /// Factors multiplying UI units into normalized device coordinates.
+    #[inline]
+    pub fn ndc_scale(&self) -> [f32; 2] {
+        // NDC spans 2 units across the surface on each axis
+        [
+            2.0 * self.scale / self.width as f32,
+            2.0 * self.scale / self.height as f32,
+        ]
+    }
+}
+
+/// Uniform data for the UI shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct UiGlobals {
+    scale: [f32; 2],
+    /// 1 if the shader must encode colors to sRGB itself, otherwise 0.
+    encode_srgb: f32,
+    _padding: f32,
+}
+
+/// Draws the crosshair on top of the scene.
+pub struct UiRenderer {
+    pipeline: wgpu::RenderPipeline,
+    globals: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    crosshair: wgpu::Buffer,
+    crosshair_vertices: u32,
+    transform: UiTransform,
+    /// Window scale factor, from the platform.
+    scale_factor: f32,
+    /// User preference multiplied into the scale factor.
+    ui_scale: f32,
+    /// Whether the target format lacks sRGB encoding, so the shader does it.
+    encode_srgb: bool,
+}
+
+impl UiRenderer {
+    /// Half the length of a crosshair arm, in UI units.
+    const CROSSHAIR_SIZE: f32 = 10.0;
+    /// Half the thickness of a crosshair arm, in UI units.
+    const CROSSHAIR_THICKNESS: f32 = 1.0;
+
+    /// Create a UI renderer drawing into targets of the given format and size.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        (width, height): (u32, u32),
+        scale_factor: f32,
+        ui_scale: f32,
+    ) -> Self {
+        let shader = shader::create(device, "ui.wgsl", include_str!("../../res/shaders/ui.wgsl"));
+
+        let transform = UiTransform::new(width, height, scale_factor * ui_scale);
+        let encode_srgb = !format.describe().srgb;
+        let globals = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("UI Globals Buffer"),
+            contents: bytemuck::bytes_of(&UiGlobals {
+                scale: transform.ndc_scale(),
+                encode_srgb: if encode_srgb { 1.0 } else { 0.0 },
+                _padding: 0.0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("UI Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("UI Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: globals.as_entire_binding(),
+            }],
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("UI Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("UI Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[UiVertex::BUFFER_LAYOUT],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            // Drawn in the scene's pass, but always on top of it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let vertices = Self::crosshair_vertices([1.0, 1.0, 1.0, 0.8]);
+        let crosshair = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Crosshair Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Self {
+            pipeline,
+            globals,
+            bind_group,
+            crosshair,
+            crosshair_vertices: vertices.len() as u32,
+            transform,
+            scale_factor,
+            ui_scale,
+            encode_srgb,
+        }
+    }
+
+    /// Build a plus sign out of a horizontal and a vertical bar.
+    fn crosshair_vertices(color: [f32; 4]) -> Vec<UiVertex> {
+        let rect = |w: f32, h: f32| {
+            let corners = [[-w, -h], [w, -h], [w, h], [-w, -h], [w, h], [-w, h]];
+            corners.map(|position| UiVertex { position, color })
+        };
+
+        let (size, thickness) = (Self::CROSSHAIR_SIZE, Self::CROSSHAIR_THICKNESS);
+        let mut vertices = rect(size, thickness).to_vec();
+        // Split the vertical bar so it doesn't overlap the horizontal one and
+        // double its alpha in the middle
+        for sign in [-1.0, 1.0] {
+            let offset = sign * (size + thickness) / 2.0;
+            vertices.extend(rect(thickness, (size - thickness) / 2.0).map(|mut v| {
+                v.position[1] += offset;
+                v
+            }));
+        }
+        vertices
+    }
+
+    /// Update the transform after the surface is resized.
+    pub fn resize(&mut self, queue: &wgpu::Queue, width: u32, height: u32) {
+        self.update_transform(queue, width, height);
+    }
+
+    /// Update the transform after the window moves to a display with a different scale factor.
+    pub fn set_scale_factor(&mut self, queue: &wgpu::Queue, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+        let (width, height) = (self.transform.width, self.transform.height);
+        self.update_transform(queue, width, height);
+    }
+
+    /// Set the user's UI scale, multiplied into the window scale factor.
+    pub fn set_ui_scale(&mut self, queue: &wgpu::Queue, ui_scale: f32) {
+        self.ui_scale = ui_scale;
+        let (width, height) = (self.transform.width, self.transform.height);
+        self.update_transform(queue, width, height);
+    }
+
+    fn update_transform(&mut self, queue: &wgpu::Queue, width: u32, height: u32) {
+        self.transform = UiTransform::new(width, height, self.scale_factor * self.ui_scale);
+        let globals = UiGlobals {
+            scale: self.transform.ndc_scale(),
+            encode_srgb: if self.encode_srgb { 1.0 } else { 0.0 },
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.globals, 0, bytemuck::bytes_of(&globals));
+    }
+
+    #[inline]
+    pub const fn transform(&self) -> &UiTransform {
+        &self.transform
+    }
+
+    /// Draw the crosshair.
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.crosshair.slice(..));
+        render_pass.draw(0..self.crosshair_vertices, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Size in pixels of something `units` UI units across, on each axis.
+    fn pixel_size(transform: &UiTransform, width: u32, height: u32, units: f32) -> [f32; 2] {
+        let [x, y] = transform.ndc_scale();
+        // NDC spans 2 units across the surface
+        [
+            units * x * width as f32 / 2.0,
+            units * y * height as f32 / 2.0,
+        ]
+    }
+
+    #[test]
+    fn keeps_pixel_size_at_any_aspect_ratio() {
+        // 16:9, ultrawide 21:9, and a portrait 9:16
+        for (width, height) in [(1920, 1080), (2560, 1080), (1080, 1920)] {
+            for scale in [1.0, 1.5] {
+                let transform = UiTransform::new(width, height, scale);
+                let [w, h] = pixel_size(&transform, width, height, 20.0);
+                assert!((w - 20.0 * scale).abs() < 1e-3, "{width}x{height}: {w}");
+                assert!((h - 20.0 * scale).abs() < 1e-3, "{width}x{height}: {h}");
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Spreading mesh uploads over frames.
+
+use std::collections::VecDeque;
+
+use crate::world::mesh_pool::ChunkMesh;
+
+/// Chunk meshes built on the CPU, waiting to be uploaded to the GPU.
+///
+/// At most a budget of meshes is taken each frame, so a burst of finished
+/// meshes is spread over several frames instead of stalling one. Meshes over
+/// budget carry over to the next frame, in the order they arrived.
+pub struct MeshUploadQueue {
+    pending: VecDeque<ChunkMesh>,
+    /// Meshes taken per frame.
+    budget: usize,
+}
+
+impl MeshUploadQueue {
+    /// Create a queue uploading up to `budget` meshes per frame, at least one.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            budget: budget.max(1),
+        }
+    }
+
+    /// Queue a mesh for upload.
+    ///
+    /// A mesh still waiting for the same chunk is replaced, keeping its place in
+    /// the queue, so a chunk is never uploaded with stale geometry.
+    pub fn push(&mut self, mesh: ChunkMesh) {
+        match self.pending.iter_mut().find(|m| m.pos == mesh.pos) {
+            Some(pending) => *pending = mesh,
+            None => self.pending.push_back(mesh),
+        }
+    }
+
+    /// Take the meshes to upload this frame, oldest first.
+    pub fn take_frame(&mut self) -> impl Iterator<Item = ChunkMesh> + '_ {
+        let count = self.budget.min(self.pending.len());
+        self.pending.drain(..count)
+    }
+
+    /// Discard every mesh waiting for upload.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Number of meshes waiting for upload.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+impl Extend<ChunkMesh> for MeshUploadQueue {
+    fn extend<T: IntoIterator<Item = ChunkMesh>>(&mut self, meshes: T) {
+        for mesh in meshes {
+            self.push(mesh);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::ChunkCoord;
+
+    fn mesh(x: i32, indices: usize) -> ChunkMesh {
+        ChunkMesh {
+            pos: ChunkCoord::new(x, 0, 0),
+            vertices: Vec::new(),
+            indices: vec![0; indices],
+            translucent_vertices: Vec::new(),
+            translucent_indices: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn uploads_over_budget_carry_over() {
+        let mut queue = MeshUploadQueue::new(2);
+        queue.extend((0..5).map(|x| mesh(x, 0)));
+
+        let first: Vec<_> = queue.take_frame().map(|m| m.pos.x).collect();
+        assert_eq!(first, [0, 1]);
+        let second: Vec<_> = queue.take_frame().map(|m| m.pos.x).collect();
+        assert_eq!(second, [2, 3]);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn newer_mesh_replaces_pending_one() {
+        let mut queue = MeshUploadQueue::new(8);
+        queue.extend([mesh(0, 3), mesh(1, 0), mesh(0, 6)]);
+
+        let taken: Vec<_> = queue
+            .take_frame()
+            .map(|m| (m.pos.x, m.indices.len()))
+            .collect();
+        assert_eq!(taken, [(0, 6), (1, 0)]);
+    }
+}
@@ -0,0 +1,26 @@
+//! Additional surfaces showing the world from their own cameras.
+
+use super::camera::Camera;
+use super::types::{binding, buffer::Buffer, texture::Texture};
+
+/// Identifies a viewport added with
+/// [`Renderer::add_viewport`](super::Renderer::add_viewport).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ViewportId(pub(super) u32);
+
+/// A secondary surface, like another window, sharing the renderer's device,
+/// pipelines and world.
+pub(super) struct Viewport {
+    pub surface: wgpu::Surface,
+    pub config: wgpu::SurfaceConfiguration,
+    /// The viewpoint this viewport is rendered from.
+    pub camera: Camera,
+    /// Depth buffer, matching the size of the surface.
+    pub depth_texture: Texture,
+    /// Color buffer rendered to when MSAA is enabled.
+    pub msaa_texture: Option<Texture>,
+    /// Uniform buffer holding the globals seen from `camera`.
+    pub globals: Buffer,
+    /// Bind group for `globals`, compatible with the block pipelines.
+    pub globals_bind_group: binding::Group,
+}
@@ -0,0 +1,580 @@
+//! Block definitions.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Neg;
+use std::sync::OnceLock;
+
+use super::{light, mesher};
+
+/// Identifies a kind of block.
+///
+/// [`BlockId::AIR`] is empty space, and is the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockId(pub u16);
+
+impl Default for BlockId {
+    #[inline]
+    fn default() -> Self {
+        Self::AIR
+    }
+}
+
+impl BlockId {
+    /// Empty space.
+    pub const AIR: BlockId = BlockId(0);
+    pub const DIRT: BlockId = BlockId(1);
+    pub const GRASS: BlockId = BlockId(2);
+    pub const STONE: BlockId = BlockId(3);
+    pub const FLOWER: BlockId = BlockId(4);
+    pub const LOG: BlockId = BlockId(5);
+    pub const WATER: BlockId = BlockId(6);
+    pub const LAVA: BlockId = BlockId(7);
+    /// The unbreakable floor of the world.
+    pub const BEDROCK: BlockId = BlockId(8);
+
+    /// Whether this is empty space.
+    #[inline]
+    pub const fn is_air(self) -> bool {
+        self.0 == Self::AIR.0
+    }
+}
+
+/// Extra data stored alongside each block, like its orientation.
+///
+/// What the value means depends on the block. Cube blocks read it as a
+/// [`Facing`], and the default state leaves them upright.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BlockState(pub u8);
+
+impl BlockState {
+    /// A state orienting a block so that its top points towards `facing`.
+    #[inline]
+    pub const fn facing(facing: Facing) -> Self {
+        Self(facing as u8)
+    }
+
+    /// The direction the top of the block points in.
+    ///
+    /// Values that aren't a valid [`Facing`] are treated as [`Facing::Up`].
+    #[inline]
+    pub const fn to_facing(self) -> Facing {
+        match self.0 {
+            1 => Facing::Down,
+            2 => Facing::East,
+            3 => Facing::West,
+            4 => Facing::South,
+            5 => Facing::North,
+            _ => Facing::Up,
+        }
+    }
+}
+
+/// Direction the top of a block points in, for blocks like logs that can be
+/// placed sideways.
+///
+/// East is +X and south is +Z.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Facing {
+    #[default]
+    Up,
+    Down,
+    East,
+    West,
+    South,
+    North,
+}
+
+impl Facing {
+    /// Rotate a vector relative to the center of a block, turning +Y towards this facing.
+    #[inline]
+    pub fn rotate<T: Copy + Neg<Output = T>>(self, [x, y, z]: [T; 3]) -> [T; 3] {
+        match self {
+            Facing::Up => [x, y, z],
+            Facing::Down => [x, -y, -z],
+            Facing::East => [y, -x, z],
+            Facing::West => [-y, x, z],
+            Facing::South => [x, -z, y],
+            Facing::North => [x, z, -y],
+        }
+    }
+
+    /// Get the face a face of an upright block ends up as once rotated.
+    #[inline]
+    pub fn rotate_face(self, face: Face) -> Face {
+        Face::from_normal(self.rotate(face.normal())).expect("rotations map faces to faces")
+    }
+}
+
+/// One of the six faces of a cube.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+    PosZ,
+    NegZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::PosX,
+        Face::NegX,
+        Face::PosY,
+        Face::NegY,
+        Face::PosZ,
+        Face::NegZ,
+    ];
+
+    /// Unit vector pointing out of this face.
+    pub const fn normal(self) -> [i32; 3] {
+        match self {
+            Face::PosX => [1, 0, 0],
+            Face::NegX => [-1, 0, 0],
+            Face::PosY => [0, 1, 0],
+            Face::NegY => [0, -1, 0],
+            Face::PosZ => [0, 0, 1],
+            Face::NegZ => [0, 0, -1],
+        }
+    }
+
+    /// Get the face pointing along a unit vector.
+    pub const fn from_normal(normal: [i32; 3]) -> Option<Face> {
+        Some(match normal {
+            [1, 0, 0] => Face::PosX,
+            [-1, 0, 0] => Face::NegX,
+            [0, 1, 0] => Face::PosY,
+            [0, -1, 0] => Face::NegY,
+            [0, 0, 1] => Face::PosZ,
+            [0, 0, -1] => Face::NegZ,
+            _ => return None,
+        })
+    }
+}
+
+/// The shape of a block's geometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelKind {
+    /// A full cube, hiding the faces of its neighbours.
+    Cube,
+    /// Two diagonal quads crossing through the block, like plants.
+    ///
+    /// Uses the `sides` tile and doesn't hide its neighbours.
+    Cross,
+    /// A translucent cube with a lowered top, like water.
+    ///
+    /// Drawn after everything else, and only hides faces of the same liquid.
+    Liquid,
+}
+
+/// Which faces of a block are tinted by the grass color.
+///
+/// Tinted faces use grayscale tiles, which are multiplied by the tint color in
+/// the shader so that they can be recolored without new textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tint {
+    None,
+    Top,
+    All,
+}
+
+impl Tint {
+    /// Whether a face is tinted.
+    #[inline]
+    pub const fn applies_to(self, face: Face) -> bool {
+        match self {
+            Tint::None => false,
+            Tint::Top => matches!(face, Face::PosY),
+            Tint::All => true,
+        }
+    }
+}
+
+/// Describes how a kind of block looks.
+///
+/// Each face refers to a tile in the block texture atlas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockType {
+    pub top: u32,
+    pub bottom: u32,
+    pub sides: u32,
+    pub model: ModelKind,
+    pub tint: Tint,
+    /// Brightness of the light the block's faces give off, from 0 to 1.
+    ///
+    /// Added on top of the lighting, so emissive blocks stay bright at night.
+    pub emissive: f32,
+    /// Level of block light the block gives off, up to [`MAX_LIGHT`](super::light::MAX_LIGHT).
+    ///
+    /// Unlike `emissive`, this lights up the blocks around it.
+    pub light: u8,
+    /// How hard the block is to mine, which scales the time it takes to break.
+    ///
+    /// `f32::INFINITY` for blocks that can't be broken at all.
+    pub hardness: f32,
+}
+
+impl BlockType {
+    /// A cube with the same tile on every face.
+    pub const fn uniform(tile: u32) -> Self {
+        Self {
+            top: tile,
+            bottom: tile,
+            sides: tile,
+            model: ModelKind::Cube,
+            tint: Tint::None,
+            emissive: 0.0,
+            light: 0,
+            hardness: 1.0,
+        }
+    }
+
+    /// A cross-shaped plant using a single tile.
+    pub const fn cross(tile: u32) -> Self {
+        Self {
+            model: ModelKind::Cross,
+            ..Self::uniform(tile)
+        }
+    }
+
+    /// A translucent liquid using a single tile, which can't be mined.
+    pub const fn liquid(tile: u32) -> Self {
+        Self {
+            model: ModelKind::Liquid,
+            hardness: f32::INFINITY,
+            ..Self::uniform(tile)
+        }
+    }
+
+    /// The same block, glowing with the given brightness.
+    pub const fn with_emissive(self, emissive: f32) -> Self {
+        Self { emissive, ..self }
+    }
+
+    /// The same block, lighting up its surroundings with the given light level.
+    pub const fn with_light(self, light: u8) -> Self {
+        Self { light, ..self }
+    }
+
+    /// The same block, taking longer or shorter to mine.
+    pub const fn with_hardness(self, hardness: f32) -> Self {
+        Self { hardness, ..self }
+    }
+
+    /// Whether the block can be broken by mining it.
+    #[inline]
+    pub fn is_breakable(&self) -> bool {
+        self.hardness.is_finite()
+    }
+
+    /// Whether this block completely hides the faces of its neighbours.
+    #[inline]
+    pub const fn is_opaque(&self) -> bool {
+        matches!(self.model, ModelKind::Cube)
+    }
+
+    /// Get the atlas tile used for a face.
+    pub const fn tile(&self, face: Face) -> u32 {
+        match face {
+            Face::PosY => self.top,
+            Face::NegY => self.bottom,
+            _ => self.sides,
+        }
+    }
+}
+
+/// Blocks that are always registered, with the names and ids they are saved under.
+const BUILTIN: &[(&str, BlockId, BlockType)] = &[
+    (
+        "mixcraft:dirt",
+        BlockId::DIRT,
+        BlockType::uniform(0).with_hardness(0.4),
+    ),
+    (
+        "mixcraft:grass",
+        BlockId::GRASS,
+        BlockType {
+            top: 1,
+            bottom: 0,
+            sides: 2,
+            model: ModelKind::Cube,
+            tint: Tint::Top,
+            emissive: 0.0,
+            light: 0,
+            hardness: 0.4,
+        },
+    ),
+    (
+        "mixcraft:stone",
+        BlockId::STONE,
+        BlockType::uniform(3).with_hardness(1.2),
+    ),
+    // Broken instantly
+    (
+        "mixcraft:flower",
+        BlockId::FLOWER,
+        BlockType::cross(4).with_hardness(0.0),
+    ),
+    // Rings on its ends
+    (
+        "mixcraft:log",
+        BlockId::LOG,
+        BlockType {
+            top: 5,
+            bottom: 5,
+            sides: 6,
+            model: ModelKind::Cube,
+            tint: Tint::None,
+            emissive: 0.0,
+            light: 0,
+            hardness: 0.8,
+        },
+    ),
+    ("mixcraft:water", BlockId::WATER, BlockType::liquid(7)),
+    // Flowing through the frames of its animated texture, and glowing
+    (
+        "mixcraft:lava",
+        BlockId::LAVA,
+        BlockType::uniform(mesher::ANIMATED_LAYERS)
+            .with_emissive(1.0)
+            .with_light(light::MAX_LIGHT),
+    ),
+    // Can't be broken, so the world always has a floor
+    (
+        "mixcraft:bedrock",
+        BlockId::BEDROCK,
+        BlockType::uniform(8).with_hardness(f32::INFINITY),
+    ),
+];
+
+/// Name of [`BlockId::AIR`], which has no block type.
+pub const AIR_NAME: &str = "mixcraft:air";
+
+/// Error registering a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// Another block is already registered under the name.
+    DuplicateName(String),
+    /// Another block already has the id.
+    IdTaken {
+        id: BlockId,
+        /// Name of the block holding the id.
+        name: String,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateName(name) => write!(f, "block {name} is already registered"),
+            Self::IdTaken { id, name } => write!(f, "block id {} is taken by {name}", id.0),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Block types, with the names and ids they are known by.
+///
+/// Ids are chosen explicitly when registering, so they don't depend on the
+/// order blocks are registered in. Saves should still refer to blocks by name,
+/// through [`BlockRegistry::palette`] and [`BlockRegistry::remap`], so that ids
+/// can change between versions.
+#[derive(Debug, Clone)]
+pub struct BlockRegistry {
+    /// Names of the registered blocks, indexed by id.
+    names: Vec<Option<String>>,
+    /// Block types, indexed by id. Air and unused ids have none.
+    types: Vec<Option<BlockType>>,
+    ids: HashMap<String, BlockId>,
+}
+
+impl Default for BlockRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BlockRegistry {
+    /// Create a registry holding only air, named [`AIR_NAME`].
+    pub fn new() -> Self {
+        Self {
+            names: vec![Some(AIR_NAME.to_owned())],
+            types: vec![None],
+            ids: HashMap::from([(AIR_NAME.to_owned(), BlockId::AIR)]),
+        }
+    }
+
+    /// Get the registry of the built-in blocks, which the world is made of.
+    pub fn builtin() -> &'static Self {
+        static BUILTIN_REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+        BUILTIN_REGISTRY.get_or_init(|| {
+            let mut registry = Self::new();
+            for (name, id, block_type) in BUILTIN {
+                registry
+                    .register(*name, *id, *block_type)
+                    .expect("built-in blocks have unique names and ids");
+            }
+            registry
+        })
+    }
+
+    /// Register a block type under a name like `"mixcraft:dirt"`, and an id.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        id: BlockId,
+        block_type: BlockType,
+    ) -> Result<BlockId, RegistryError> {
+        let name = name.into();
+        if self.ids.contains_key(&name) {
+            return Err(RegistryError::DuplicateName(name));
+        }
+        if let Some(Some(taken)) = self.names.get(id.0 as usize) {
+            return Err(RegistryError::IdTaken {
+                id,
+                name: taken.clone(),
+            });
+        }
+
+        let index = id.0 as usize;
+        if index >= self.names.len() {
+            self.names.resize(index + 1, None);
+            self.types.resize(index + 1, None);
+        }
+        self.names[index] = Some(name.clone());
+        self.types[index] = Some(block_type);
+        self.ids.insert(name, id);
+        Ok(id)
+    }
+
+    /// Get the id of the block registered under a name.
+    #[inline]
+    pub fn id(&self, name: &str) -> Option<BlockId> {
+        self.ids.get(name).copied()
+    }
+
+    /// Get the name a block is registered under.
+    #[inline]
+    pub fn name(&self, id: BlockId) -> Option<&str> {
+        self.names.get(id.0 as usize)?.as_deref()
+    }
+
+    /// Get the block type of an id, if it has one.
+    ///
+    /// Empty space and unknown ids have no block type.
+    #[inline]
+    pub fn block_type(&self, id: BlockId) -> Option<&BlockType> {
+        self.types.get(id.0 as usize)?.as_ref()
+    }
+
+    /// Get every block type, indexed by [`BlockId`].
+    #[inline]
+    pub fn types(&self) -> &[Option<BlockType>] {
+        &self.types
+    }
+
+    /// Get the name of every id, for a save to store alongside raw block ids.
+    #[inline]
+    pub fn palette(&self) -> &[Option<String>] {
+        &self.names
+    }
+
+    /// Map the block ids of a save onto this registry, given the
+    /// [`palette`](Self::palette) it was saved with.
+    ///
+    /// The result is indexed by the saved ids. Blocks that are no longer
+    /// registered become air.
+    pub fn remap(&self, palette: &[Option<String>]) -> Vec<BlockId> {
+        palette
+            .iter()
+            .map(|name| {
+                name.as_deref()
+                    .and_then(|name| self.id(name))
+                    .unwrap_or(BlockId::AIR)
+            })
+            .collect()
+    }
+}
+
+/// Get every built-in block type, indexed by [`BlockId`].
+#[inline]
+pub fn registry() -> &'static [Option<BlockType>] {
+    BlockRegistry::builtin().types()
+}
+
+/// Get the built-in block type of an id, if it has one.
+///
+/// Empty space and unknown ids have no block type.
+#[inline]
+pub fn block_type(id: BlockId) -> Option<&'static BlockType> {
+    BlockRegistry::builtin().block_type(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm as glm;
+
+    use super::*;
+
+    #[test]
+    fn facings_turn_the_top_towards_them() {
+        let facings = [
+            (Facing::Up, Face::PosY),
+            (Facing::Down, Face::NegY),
+            (Facing::East, Face::PosX),
+            (Facing::West, Face::NegX),
+            (Facing::South, Face::PosZ),
+            (Facing::North, Face::NegZ),
+        ];
+        for (facing, top) in facings {
+            assert_eq!(facing.rotate(Face::PosY.normal()), top.normal());
+            assert_eq!(facing.rotate_face(Face::PosY), top);
+            assert_eq!(BlockState::facing(facing).to_facing(), facing);
+
+            // Rotations, not reflections, so faces keep their winding
+            let [x, y, z] = [Face::PosX, Face::PosY, Face::PosZ]
+                .map(|face| glm::Vec3::from(facing.rotate(face.normal()).map(|c| c as f32)));
+            assert_eq!(x.cross(&y), z);
+            // Every face goes somewhere different
+            let mut rotated = Face::ALL.map(|face| facing.rotate_face(face).normal());
+            rotated.sort();
+            rotated.windows(2).for_each(|w| assert_ne!(w[0], w[1]));
+        }
+    }
+
+    #[test]
+    fn saves_are_remapped_by_name() {
+        let mut old = BlockRegistry::new();
+        old.register("test:a", BlockId(1), BlockType::uniform(0))
+            .unwrap();
+        old.register("test:b", BlockId(2), BlockType::uniform(1))
+            .unwrap();
+        old.register("test:gone", BlockId(3), BlockType::uniform(2))
+            .unwrap();
+
+        // A later version renumbered its blocks, and dropped one
+        let mut new = BlockRegistry::new();
+        new.register("test:b", BlockId(1), BlockType::uniform(1))
+            .unwrap();
+        new.register("test:a", BlockId(5), BlockType::uniform(0))
+            .unwrap();
+
+        assert_eq!(
+            new.remap(old.palette()),
+            [BlockId::AIR, BlockId(5), BlockId(1), BlockId::AIR]
+        );
+        assert_eq!(
+            new.register("test:a", BlockId(6), BlockType::uniform(0)),
+            Err(RegistryError::DuplicateName("test:a".to_owned()))
+        );
+        assert_eq!(
+            new.register("test:c", BlockId(5), BlockType::uniform(0)),
+            Err(RegistryError::IdTaken {
+                id: BlockId(5),
+                name: "test:a".to_owned()
+            })
+        );
+    }
+}
@@ -0,0 +1,520 @@
+//! Fixed-size cubes of blocks.
+//!
+//! Worlds taller than a chunk stack chunks into columns. Each chunk is meshed on
+//! its own, so an edit only remeshes the chunks it touches however tall the
+//! world is.
+
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use nalgebra_glm as glm;
+
+use super::blocks::{BlockId, BlockState};
+
+/// Width of a chunk along each axis, in blocks.
+pub const CHUNK_SIZE: usize = 16;
+
+/// Number of blocks in a chunk.
+pub const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+/// Position of a chunk, in units of whole chunks.
+///
+/// The chunk at `(0, 0, 0)` spans world positions `0..CHUNK_SIZE` on each axis,
+/// and the chunk at `(-1, 0, 0)` spans `-CHUNK_SIZE..0` along X.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl ChunkCoord {
+    #[inline]
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Get the coordinate of the chunk containing a world-space block position.
+    #[inline]
+    pub const fn from_block(x: i32, y: i32, z: i32) -> Self {
+        let size = CHUNK_SIZE as i32;
+        Self::new(x.div_euclid(size), y.div_euclid(size), z.div_euclid(size))
+    }
+
+    /// Get the coordinate of the chunk containing a world-space position.
+    #[inline]
+    pub fn from_world_pos(pos: glm::Vec3) -> Self {
+        let size = CHUNK_SIZE as f32;
+        Self::new(
+            (pos.x / size).floor() as i32,
+            (pos.y / size).floor() as i32,
+            (pos.z / size).floor() as i32,
+        )
+    }
+
+    /// World-space position of the chunk's minimum corner.
+    #[inline]
+    pub fn to_world_origin(self) -> glm::Vec3 {
+        let size = CHUNK_SIZE as i32;
+        glm::vec3(
+            (self.x * size) as f32,
+            (self.y * size) as f32,
+            (self.z * size) as f32,
+        )
+    }
+
+    /// Get the world-space bounding box of the chunk.
+    ///
+    /// Returns a (min, max) pair of corners.
+    #[inline]
+    pub fn bounds(self) -> (glm::Vec3, glm::Vec3) {
+        let min = self.to_world_origin();
+        (min, min.add_scalar(CHUNK_SIZE as f32))
+    }
+
+    /// Get the coordinate `dx`, `dy` and `dz` chunks away from this one.
+    #[inline]
+    pub const fn offset(self, dx: i32, dy: i32, dz: i32) -> Self {
+        Self::new(self.x + dx, self.y + dy, self.z + dz)
+    }
+
+    /// Get the six chunks sharing a face with this one.
+    pub const fn neighbors(self) -> [Self; 6] {
+        [
+            self.offset(1, 0, 0),
+            self.offset(-1, 0, 0),
+            self.offset(0, 1, 0),
+            self.offset(0, -1, 0),
+            self.offset(0, 0, 1),
+            self.offset(0, 0, -1),
+        ]
+    }
+
+    /// Get the chunks whose meshes depend on the block at a world-space block
+    /// position: the one containing it, and any neighbour it borders.
+    pub fn touching_block(x: i32, y: i32, z: i32) -> Vec<Self> {
+        let size = CHUNK_SIZE as i32;
+        let pos = Self::from_block(x, y, z);
+        let mut chunks = vec![pos];
+        for (axis, v) in [x, y, z].into_iter().enumerate() {
+            let mut offset = [0; 3];
+            offset[axis] = match v.rem_euclid(size) {
+                0 => -1,
+                local if local == size - 1 => 1,
+                _ => continue,
+            };
+            chunks.push(pos.offset(offset[0], offset[1], offset[2]));
+        }
+        chunks
+    }
+
+    /// Get the column of `height` chunks stacked upwards from this one, including
+    /// itself.
+    pub fn column(self, height: u32) -> impl Iterator<Item = Self> {
+        (0..height as i32).map(move |dy| self.offset(0, dy, 0))
+    }
+
+    /// Get the chunks in a horizontal square around this one, including itself.
+    ///
+    /// `radius` is the number of chunks on each side.
+    pub fn horizontal_square(self, radius: i32) -> impl Iterator<Item = Self> {
+        (-radius..=radius)
+            .flat_map(move |dx| (-radius..=radius).map(move |dz| self.offset(dx, 0, dz)))
+    }
+}
+
+impl fmt::Display for ChunkCoord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+impl Add for ChunkCoord {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        self.offset(rhs.x, rhs.y, rhs.z)
+    }
+}
+
+impl Sub for ChunkCoord {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        self.offset(-rhs.x, -rhs.y, -rhs.z)
+    }
+}
+
+impl From<[i32; 3]> for ChunkCoord {
+    #[inline]
+    fn from([x, y, z]: [i32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<ChunkCoord> for [i32; 3] {
+    #[inline]
+    fn from(pos: ChunkCoord) -> Self {
+        [pos.x, pos.y, pos.z]
+    }
+}
+
+/// Get the world-space bounding box of the chunk at a chunk coordinate.
+///
+/// Returns a (min, max) pair of corners.
+#[inline]
+pub fn bounds(pos: impl Into<ChunkCoord>) -> (glm::Vec3, glm::Vec3) {
+    pos.into().bounds()
+}
+
+/// A cube of [`CHUNK_SIZE`]³ blocks.
+#[derive(Clone)]
+pub struct Chunk {
+    blocks: Box<[BlockId; CHUNK_VOLUME]>,
+    /// State of each block, in the same order as `blocks`.
+    states: Box<[BlockState; CHUNK_VOLUME]>,
+    /// Light of each block, in the same order as `blocks`: block light in the
+    /// low four bits, and skylight in the high four.
+    ///
+    /// Kept up to date by the [`World`](super::World) the chunk is in.
+    light: Box<[u8; CHUNK_VOLUME]>,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chunk {
+    /// Create a chunk filled with air.
+    pub fn new() -> Self {
+        Self {
+            blocks: Box::new([BlockId::AIR; CHUNK_VOLUME]),
+            states: Box::new([BlockState::default(); CHUNK_VOLUME]),
+            light: Box::new([0; CHUNK_VOLUME]),
+        }
+    }
+
+    #[inline]
+    const fn index(x: usize, y: usize, z: usize) -> usize {
+        (y * CHUNK_SIZE + z) * CHUNK_SIZE + x
+    }
+
+    /// Get the block at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> BlockId {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        self.blocks[Self::index(x, y, z)]
+    }
+
+    /// Get the state of the block at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn state(&self, x: usize, y: usize, z: usize) -> BlockState {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        self.states[Self::index(x, y, z)]
+    }
+
+    /// Set the block at a position local to this chunk, with the default state.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
+        self.set_with_state(x, y, z, block, BlockState::default());
+    }
+
+    /// Set the block and its state at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn set_with_state(
+        &mut self,
+        x: usize,
+        y: usize,
+        z: usize,
+        block: BlockId,
+        state: BlockState,
+    ) {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        let i = Self::index(x, y, z);
+        self.blocks[i] = block;
+        self.states[i] = state;
+    }
+
+    /// Get the block light level at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn light(&self, x: usize, y: usize, z: usize) -> u8 {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        self.light[Self::index(x, y, z)] & 0x0f
+    }
+
+    /// Set the block light level at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn set_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        let light = &mut self.light[Self::index(x, y, z)];
+        *light = (*light & 0xf0) | (level & 0x0f);
+    }
+
+    /// Get the skylight level at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn sky_light(&self, x: usize, y: usize, z: usize) -> u8 {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        self.light[Self::index(x, y, z)] >> 4
+    }
+
+    /// Set the skylight level at a position local to this chunk.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if any coordinate is outside the chunk.
+    #[inline]
+    pub fn set_sky_light(&mut self, x: usize, y: usize, z: usize, level: u8) {
+        assert!(x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE);
+        let light = &mut self.light[Self::index(x, y, z)];
+        *light = (*light & 0x0f) | (level << 4);
+    }
+
+    /// Darken every block of the chunk, of both block light and skylight.
+    #[inline]
+    pub fn clear_light(&mut self) {
+        self.light.fill(0);
+    }
+
+    /// Iterate over the blocks that aren't air, with their local positions.
+    ///
+    /// Blocks are visited in storage order, which is X fastest, then Z, then Y.
+    pub fn iter_solid(&self) -> impl Iterator<Item = (glm::UVec3, BlockId)> + '_ {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| !block.is_air())
+            .map(|(i, &block)| {
+                let size = CHUNK_SIZE as u32;
+                let i = i as u32;
+                let pos = glm::vec3(i % size, i / (size * size), i / size % size);
+                (pos, block)
+            })
+    }
+
+    /// Serialize the blocks as little-endian IDs, in storage order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.blocks.iter().flat_map(|b| b.0.to_le_bytes()).collect()
+    }
+
+    /// Get the block at a position that may lie outside this chunk.
+    ///
+    /// Positions outside the chunk are treated as air.
+    #[inline]
+    pub fn get_or_empty(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let range = 0..CHUNK_SIZE as i32;
+        if range.contains(&x) && range.contains(&y) && range.contains(&z) {
+            self.blocks[Self::index(x as usize, y as usize, z as usize)]
+        } else {
+            BlockId::AIR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_at_origin() {
+        let (min, max) = bounds([0, 0, 0]);
+        assert_eq!(min, glm::vec3(0.0, 0.0, 0.0));
+        assert_eq!(max, glm::vec3(16.0, 16.0, 16.0));
+    }
+
+    #[test]
+    fn bounds_are_scaled_by_chunk_size() {
+        let (min, max) = bounds([2, 1, 3]);
+        assert_eq!(min, glm::vec3(32.0, 16.0, 48.0));
+        assert_eq!(max, glm::vec3(48.0, 32.0, 64.0));
+    }
+
+    #[test]
+    fn bounds_of_negative_chunks() {
+        let (min, max) = bounds([-1, 0, -2]);
+        assert_eq!(min, glm::vec3(-16.0, 0.0, -32.0));
+        assert_eq!(max, glm::vec3(0.0, 16.0, -16.0));
+    }
+
+    #[test]
+    fn world_pos_at_origin() {
+        assert_eq!(
+            ChunkCoord::from_world_pos(glm::vec3(0.0, 0.0, 0.0)),
+            ChunkCoord::new(0, 0, 0)
+        );
+        assert_eq!(
+            ChunkCoord::from_world_pos(glm::vec3(15.99, 15.99, 15.99)),
+            ChunkCoord::new(0, 0, 0)
+        );
+        assert_eq!(
+            ChunkCoord::from_world_pos(glm::vec3(16.0, 16.0, 16.0)),
+            ChunkCoord::new(1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn world_pos_on_negative_axes() {
+        // Just below zero belongs to the chunk below, not the one at the origin
+        assert_eq!(
+            ChunkCoord::from_world_pos(glm::vec3(-0.01, -0.01, -0.01)),
+            ChunkCoord::new(-1, -1, -1)
+        );
+        assert_eq!(
+            ChunkCoord::from_world_pos(glm::vec3(-16.0, 0.0, -16.01)),
+            ChunkCoord::new(-1, 0, -2)
+        );
+        assert_eq!(
+            ChunkCoord::from_world_pos(glm::vec3(-17.0, 5.0, 20.0)),
+            ChunkCoord::new(-2, 0, 1)
+        );
+    }
+
+    #[test]
+    fn block_pos_on_negative_axes() {
+        assert_eq!(ChunkCoord::from_block(0, 0, 0), ChunkCoord::new(0, 0, 0));
+        assert_eq!(
+            ChunkCoord::from_block(-1, 15, 16),
+            ChunkCoord::new(-1, 0, 1)
+        );
+        assert_eq!(
+            ChunkCoord::from_block(-16, -17, -32),
+            ChunkCoord::new(-1, -2, -2)
+        );
+        assert_eq!(ChunkCoord::from_block(-33, 0, 0), ChunkCoord::new(-3, 0, 0));
+    }
+
+    #[test]
+    fn world_origin_round_trips() {
+        for pos in [
+            ChunkCoord::new(0, 0, 0),
+            ChunkCoord::new(3, -1, 2),
+            ChunkCoord::new(-1, -1, -1),
+            ChunkCoord::new(-5, 2, -7),
+        ] {
+            let origin = pos.to_world_origin();
+            assert_eq!(ChunkCoord::from_world_pos(origin), pos);
+            // The far corner belongs to the next chunk over
+            let inside = origin.add_scalar(CHUNK_SIZE as f32 - 0.5);
+            assert_eq!(ChunkCoord::from_world_pos(inside), pos);
+        }
+    }
+
+    #[test]
+    fn neighbors_surround_chunk() {
+        let pos = ChunkCoord::new(-1, 0, 2);
+        let neighbors = pos.neighbors();
+        assert!(neighbors.contains(&ChunkCoord::new(0, 0, 2)));
+        assert!(neighbors.contains(&ChunkCoord::new(-2, 0, 2)));
+        assert!(neighbors.contains(&ChunkCoord::new(-1, 1, 2)));
+        assert!(neighbors.contains(&ChunkCoord::new(-1, -1, 2)));
+        assert!(neighbors.contains(&ChunkCoord::new(-1, 0, 3)));
+        assert!(neighbors.contains(&ChunkCoord::new(-1, 0, 1)));
+        assert!(!neighbors.contains(&pos));
+    }
+
+    #[test]
+    fn horizontal_square_covers_radius() {
+        let center = ChunkCoord::new(-1, 2, 0);
+        let square: Vec<_> = center.horizontal_square(1).collect();
+        assert_eq!(square.len(), 9);
+        assert!(square.contains(&center));
+        assert!(square.contains(&ChunkCoord::new(-2, 2, -1)));
+        assert!(square.contains(&ChunkCoord::new(0, 2, 1)));
+        assert!(square.iter().all(|c| c.y == center.y));
+    }
+
+    #[test]
+    fn column_stacks_upwards() {
+        let base = ChunkCoord::new(2, -1, 5);
+        let column: Vec<_> = base.column(3).collect();
+        assert_eq!(
+            column,
+            [base, ChunkCoord::new(2, 0, 5), ChunkCoord::new(2, 1, 5)]
+        );
+    }
+
+    #[test]
+    fn touching_block_includes_bordered_neighbours() {
+        assert_eq!(
+            ChunkCoord::touching_block(5, 8, 9),
+            [ChunkCoord::new(0, 0, 0)]
+        );
+        assert_eq!(
+            ChunkCoord::touching_block(-1, 16, 7),
+            [
+                ChunkCoord::new(-1, 1, 0),
+                ChunkCoord::new(0, 1, 0),
+                ChunkCoord::new(-1, 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_solid_skips_air() {
+        let mut chunk = Chunk::new();
+        assert_eq!(chunk.iter_solid().count(), 0);
+
+        chunk.set(0, 0, 0, BlockId::STONE);
+        chunk.set(15, 2, 7, BlockId::DIRT);
+        chunk.set(3, 15, 15, BlockId::GRASS);
+        chunk.set(4, 4, 4, BlockId::FLOWER);
+        // Replacing a block with air removes it again
+        chunk.set(4, 4, 4, BlockId::AIR);
+
+        let solid: Vec<_> = chunk.iter_solid().collect();
+        assert_eq!(
+            solid,
+            [
+                (glm::vec3(0, 0, 0), BlockId::STONE),
+                (glm::vec3(15, 2, 7), BlockId::DIRT),
+                (glm::vec3(3, 15, 15), BlockId::GRASS),
+            ]
+        );
+        for (pos, block) in solid {
+            assert_eq!(
+                chunk.get(pos.x as usize, pos.y as usize, pos.z as usize),
+                block
+            );
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = ChunkCoord::new(1, -2, 3);
+        let b = ChunkCoord::new(-4, 5, -6);
+        assert_eq!(a + b, ChunkCoord::new(-3, 3, -3));
+        assert_eq!(a - b, ChunkCoord::new(5, -7, 9));
+        assert_eq!(a + b - b, a);
+    }
+}
@@ -0,0 +1,95 @@
+//! Finding what the camera is inside of, for screen overlays.
+
+use nalgebra_glm as glm;
+
+use super::blocks::{self, BlockId, ModelKind};
+use super::mesher::LIQUID_HEIGHT;
+use super::World;
+use crate::renderer::camera::Camera;
+
+/// What the camera's eye is inside of, returned by [`World::immersion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Immersion {
+    /// Nothing the view should be covered by, including plants.
+    Air,
+    /// Under the surface of a liquid, like water, which tints the view.
+    Liquid(BlockId),
+    /// Inside a solid block, which hides the view behind its texture.
+    Solid(BlockId),
+}
+
+impl World {
+    /// Get the block containing a world-space position.
+    ///
+    /// Positions in chunks that aren't loaded are treated as air.
+    #[inline]
+    pub fn block_at(&self, position: glm::Vec3) -> BlockId {
+        let [x, y, z] = [position.x, position.y, position.z].map(|c| c.floor() as i32);
+        self.block(x, y, z)
+    }
+
+    /// Get the block containing the camera's eye.
+    #[inline]
+    pub fn block_at_eye(&self, camera: &Camera) -> BlockId {
+        self.block_at(camera.position)
+    }
+
+    /// Find what the camera's eye is inside of.
+    ///
+    /// The top of a liquid is lowered to [`LIQUID_HEIGHT`] unless more of it
+    /// is above, so the eye is only under it below the drawn surface.
+    pub fn immersion(&self, camera: &Camera) -> Immersion {
+        let position = camera.position;
+        let id = self.block_at(position);
+        let Some(block) = blocks::block_type(id) else {
+            return Immersion::Air;
+        };
+
+        if block.is_opaque() {
+            return Immersion::Solid(id);
+        }
+        if block.model == ModelKind::Liquid {
+            let above = self.block_at(position + glm::Vec3::y());
+            if above == id || position.y - position.y.floor() < LIQUID_HEIGHT {
+                return Immersion::Liquid(id);
+            }
+        }
+        Immersion::Air
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::{Chunk, ChunkCoord};
+
+    fn camera_at(x: f32, y: f32, z: f32) -> Camera {
+        Camera::new(glm::vec3(x, y, z), 0.0, 0.0, 70.0, 1.0)
+    }
+
+    #[test]
+    fn finds_what_the_eye_is_in() {
+        let mut world = World::new();
+        world.chunks.insert(ChunkCoord::new(0, 0, 0), Chunk::new());
+        world.set_block(1, 1, 1, BlockId::STONE);
+        world.set_block(2, 1, 1, BlockId::WATER);
+        world.set_block(3, 1, 1, BlockId::WATER);
+        world.set_block(3, 2, 1, BlockId::WATER);
+        world.set_block(4, 1, 1, BlockId::FLOWER);
+
+        let camera = camera_at(1.5, 1.5, 1.5);
+        assert_eq!(world.block_at_eye(&camera), BlockId::STONE);
+        assert_eq!(world.immersion(&camera), Immersion::Solid(BlockId::STONE));
+
+        // Above the lowered surface of water, but not of water under more water
+        let water = Immersion::Liquid(BlockId::WATER);
+        assert_eq!(world.immersion(&camera_at(2.5, 1.5, 1.5)), water);
+        assert_eq!(world.immersion(&camera_at(2.5, 1.95, 1.5)), Immersion::Air);
+        assert_eq!(world.immersion(&camera_at(3.5, 1.95, 1.5)), water);
+
+        assert_eq!(world.immersion(&camera_at(4.5, 1.5, 1.5)), Immersion::Air);
+        // Outside the loaded chunks
+        assert_eq!(world.block_at_eye(&camera_at(-0.5, 1.5, 1.5)), BlockId::AIR);
+        assert_eq!(world.immersion(&camera_at(-0.5, 1.5, 1.5)), Immersion::Air);
+    }
+}
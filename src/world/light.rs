@@ -0,0 +1,357 @@
+//! Block light and skylight, spreading to the blocks around their sources.
+//!
+//! Each block stores a level from 0 to [`MAX_LIGHT`] of both kinds of light.
+//! Light spreads from its sources, losing a level with each step to a
+//! neighbouring block, through anything that isn't opaque. Block light comes
+//! from blocks that give it off. Skylight enters the top of the world at full
+//! strength, and keeps it while falling straight down, so anything under a roof
+//! only gets what spreads in from the sides. Light crosses chunk boundaries,
+//! but never into chunks that aren't loaded.
+
+use std::collections::{HashSet, VecDeque};
+
+use super::blocks::{self, BlockId, Face};
+use super::chunk::{ChunkCoord, CHUNK_SIZE};
+use super::World;
+
+/// Highest light level, given off by the brightest blocks and the open sky.
+pub const MAX_LIGHT: u8 = 15;
+
+/// The kinds of light stored in each block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LightKind {
+    Block,
+    Sky,
+}
+
+impl LightKind {
+    const ALL: [Self; 2] = [Self::Block, Self::Sky];
+}
+
+/// Level of block light given off by a block.
+fn emission(block: BlockId) -> u8 {
+    blocks::block_type(block).map_or(0, |b| b.light)
+}
+
+/// Whether light spreads through a block.
+fn is_transparent(block: BlockId) -> bool {
+    !blocks::block_type(block).is_some_and(|b| b.is_opaque())
+}
+
+/// Whether replacing `old` with `new` can change the light around it.
+pub(super) fn affects_light(old: BlockId, new: BlockId) -> bool {
+    emission(old) != emission(new) || is_transparent(old) != is_transparent(new)
+}
+
+/// Positions of the six blocks sharing a face with a block, and the faces
+/// they are through.
+fn adjacent([x, y, z]: [i32; 3]) -> impl Iterator<Item = (Face, [i32; 3])> {
+    Face::ALL.into_iter().map(move |face| {
+        let [dx, dy, dz] = face.normal();
+        (face, [x + dx, y + dy, z + dz])
+    })
+}
+
+/// Level light reaches after a step through `face` from a block lit at `level`.
+///
+/// Skylight at full strength doesn't fade while falling straight down.
+fn step_level(kind: LightKind, face: Face, level: u8) -> u8 {
+    if kind == LightKind::Sky && face == Face::NegY && level == MAX_LIGHT {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+impl World {
+    /// Get the block light level at a world-space block position.
+    ///
+    /// Positions in chunks that aren't loaded are dark.
+    #[inline]
+    pub fn light(&self, x: i32, y: i32, z: i32) -> u8 {
+        self.light_of(LightKind::Block, [x, y, z])
+    }
+
+    /// Get the skylight level at a world-space block position.
+    ///
+    /// Positions in chunks that aren't loaded are dark.
+    #[inline]
+    pub fn sky_light(&self, x: i32, y: i32, z: i32) -> u8 {
+        self.light_of(LightKind::Sky, [x, y, z])
+    }
+
+    fn light_of(&self, kind: LightKind, [x, y, z]: [i32; 3]) -> u8 {
+        let size = CHUNK_SIZE as i32;
+        let Some(chunk) = self.chunks.get(&ChunkCoord::from_block(x, y, z)) else {
+            return 0;
+        };
+        let [x, y, z] = [x, y, z].map(|v| v.rem_euclid(size) as usize);
+        match kind {
+            LightKind::Block => chunk.light(x, y, z),
+            LightKind::Sky => chunk.sky_light(x, y, z),
+        }
+    }
+
+    /// Set a light level at a world-space block position, remembering the
+    /// chunks that need to be remeshed.
+    ///
+    /// Returns `false` if the position isn't in a loaded chunk.
+    fn set_light_of(&mut self, kind: LightKind, [x, y, z]: [i32; 3], level: u8) -> bool {
+        let size = CHUNK_SIZE as i32;
+        let Some(chunk) = self.chunks.get_mut(&ChunkCoord::from_block(x, y, z)) else {
+            return false;
+        };
+        let [lx, ly, lz] = [x, y, z].map(|v| v.rem_euclid(size) as usize);
+        match kind {
+            LightKind::Block => chunk.set_light(lx, ly, lz, level),
+            LightKind::Sky => chunk.set_sky_light(lx, ly, lz, level),
+        }
+        self.relit.extend(ChunkCoord::touching_block(x, y, z));
+        true
+    }
+
+    /// Level of light a block is lit at by itself, regardless of its
+    /// surroundings.
+    ///
+    /// Transparent blocks with no loaded chunk above them are open to the sky.
+    fn source_level(&self, kind: LightKind, [x, y, z]: [i32; 3]) -> u8 {
+        let block = self.block(x, y, z);
+        match kind {
+            LightKind::Block => emission(block),
+            LightKind::Sky
+                if is_transparent(block)
+                    && !self
+                        .chunks
+                        .contains_key(&ChunkCoord::from_block(x, y + 1, z)) =>
+            {
+                MAX_LIGHT
+            }
+            LightKind::Sky => 0,
+        }
+    }
+
+    /// Recompute the light of every loaded chunk from scratch.
+    ///
+    /// Chunks aren't remembered as relit, since every one of them may have
+    /// changed. After a block changes, light is updated around it instead.
+    pub fn flood_fill_light(&mut self) {
+        let size = CHUNK_SIZE as i32;
+        for chunk in self.chunks.values_mut() {
+            chunk.clear_light();
+        }
+
+        let positions: Vec<_> = self.chunks.keys().copied().collect();
+        for kind in LightKind::ALL {
+            let mut sources = VecDeque::new();
+            for pos in &positions {
+                // Only the top layer of a chunk can be open to the sky
+                let ys = match kind {
+                    LightKind::Block => 0..size,
+                    LightKind::Sky => size - 1..size,
+                };
+                for y in ys {
+                    for z in 0..size {
+                        for x in 0..size {
+                            let block = [pos.x * size + x, pos.y * size + y, pos.z * size + z];
+                            let level = self.source_level(kind, block);
+                            if level > 0 {
+                                self.set_light_of(kind, block, level);
+                                sources.push_back(block);
+                            }
+                        }
+                    }
+                }
+            }
+            self.spread_light(kind, sources);
+        }
+        self.relit.clear();
+    }
+
+    /// Update the light around a block that was just replaced.
+    pub(super) fn update_light(&mut self, pos: [i32; 3]) {
+        for kind in LightKind::ALL {
+            self.update_light_of(kind, pos);
+        }
+    }
+
+    /// Update one kind of light around a block that was just replaced.
+    ///
+    /// Light that reached the block's surroundings through it, or from it, is
+    /// taken away first, then spread back in from the lit blocks around the
+    /// darkened area and from the sources in it.
+    fn update_light_of(&mut self, kind: LightKind, pos: [i32; 3]) {
+        let mut darken = VecDeque::from([(pos, self.light_of(kind, pos))]);
+        self.set_light_of(kind, pos, 0);
+
+        let mut relight = VecDeque::new();
+        let mut sources = vec![pos];
+        while let Some((from, level)) = darken.pop_front() {
+            for (face, next) in adjacent(from) {
+                let light = self.light_of(kind, next);
+                if light == 0 {
+                    continue;
+                }
+                // Lit no brighter than it would have been from `from`
+                if light < level || light == step_level(kind, face, level) {
+                    self.set_light_of(kind, next, 0);
+                    darken.push_back((next, light));
+                    sources.push(next);
+                } else {
+                    // Lit from elsewhere, spreads back into the darkened area
+                    relight.push_back(next);
+                }
+            }
+        }
+
+        for block in sources {
+            let level = self.source_level(kind, block);
+            if level > 0 {
+                self.set_light_of(kind, block, level);
+                relight.push_back(block);
+            }
+        }
+        self.spread_light(kind, relight);
+    }
+
+    /// Spread light outwards from lit blocks, breadth first.
+    fn spread_light(&mut self, kind: LightKind, mut queue: VecDeque<[i32; 3]>) {
+        while let Some(from) = queue.pop_front() {
+            let level = self.light_of(kind, from);
+            for (face, next) in adjacent(from) {
+                let [x, y, z] = next;
+                let reached = step_level(kind, face, level);
+                if self.light_of(kind, next) < reached
+                    && is_transparent(self.block(x, y, z))
+                    && self.set_light_of(kind, next, reached)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+
+    /// Take the chunks whose light changed since the last call, including
+    /// neighbours of changed blocks on their edges, which need to be remeshed.
+    pub fn take_relit_chunks(&mut self) -> HashSet<ChunkCoord> {
+        std::mem::take(&mut self.relit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::chunk::Chunk;
+
+    /// A lit world of two empty chunks side by side along X.
+    fn two_chunks() -> World {
+        let mut world = World::new();
+        for pos in [ChunkCoord::new(0, 0, 0), ChunkCoord::new(1, 0, 0)] {
+            world.chunks.insert(pos, Chunk::new());
+        }
+        world.flood_fill_light();
+        world
+    }
+
+    /// Every block light and skylight level of the world, in a stable order.
+    fn light_levels(world: &World) -> Vec<(ChunkCoord, Vec<(u8, u8)>)> {
+        let mut levels: Vec<_> = world
+            .chunks()
+            .map(|(&pos, chunk)| {
+                let mut light = Vec::new();
+                for y in 0..CHUNK_SIZE {
+                    for z in 0..CHUNK_SIZE {
+                        for x in 0..CHUNK_SIZE {
+                            light.push((chunk.light(x, y, z), chunk.sky_light(x, y, z)));
+                        }
+                    }
+                }
+                (pos, light)
+            })
+            .collect();
+        levels.sort_by_key(|(pos, _)| (pos.x, pos.y, pos.z));
+        levels
+    }
+
+    #[test]
+    fn light_fades_across_chunk_boundaries() {
+        let mut world = two_chunks();
+        world.set_block(14, 8, 8, BlockId::LAVA);
+
+        assert_eq!(world.light(14, 8, 8), MAX_LIGHT);
+        assert_eq!(world.light(15, 8, 8), MAX_LIGHT - 1);
+        assert_eq!(world.light(17, 8, 8), MAX_LIGHT - 3);
+        assert_eq!(world.light(14, 10, 9), MAX_LIGHT - 3);
+        // Not loaded
+        assert_eq!(world.light(-1, 8, 8), 0);
+        assert!(world
+            .take_relit_chunks()
+            .contains(&ChunkCoord::new(1, 0, 0)));
+
+        world.set_block(14, 8, 8, BlockId::AIR);
+        assert!(light_levels(&world)
+            .iter()
+            .all(|(_, light)| light.iter().all(|&(block, _)| block == 0)));
+    }
+
+    #[test]
+    fn opaque_blocks_cast_shadows() {
+        let mut world = two_chunks();
+        world.set_block(4, 8, 8, BlockId::LAVA);
+        // Wall off the +X side of the lava
+        for y in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                world.set_block(5, y, z, BlockId::STONE);
+            }
+        }
+
+        assert_eq!(world.light(5, 8, 8), 0);
+        assert_eq!(world.light(6, 8, 8), 0);
+        assert_eq!(world.light(3, 8, 8), MAX_LIGHT - 1);
+    }
+
+    #[test]
+    fn roofs_shade_the_blocks_below() {
+        let mut world = two_chunks();
+        assert_eq!(world.sky_light(8, 0, 8), MAX_LIGHT);
+
+        // A 5x5 roof, with the sky still reaching in from the sides
+        for x in 6..11 {
+            for z in 6..11 {
+                world.set_block(x, 10, z, BlockId::STONE);
+            }
+        }
+        assert_eq!(world.sky_light(8, 11, 8), MAX_LIGHT);
+        assert_eq!(world.sky_light(8, 10, 8), 0);
+        assert_eq!(world.sky_light(8, 9, 8), MAX_LIGHT - 3);
+        assert_eq!(world.sky_light(6, 9, 8), MAX_LIGHT - 1);
+        assert_eq!(world.sky_light(12, 9, 8), MAX_LIGHT);
+    }
+
+    #[test]
+    fn edits_match_a_full_flood_fill() {
+        let mut world = two_chunks();
+        let edits = [
+            ([10, 4, 4], BlockId::LAVA),
+            ([20, 4, 6], BlockId::LAVA),
+            ([15, 4, 5], BlockId::STONE),
+            ([15, 5, 5], BlockId::STONE),
+            ([10, 4, 4], BlockId::AIR),
+            ([16, 4, 5], BlockId::FLOWER),
+            ([15, 4, 5], BlockId::AIR),
+            ([12, 4, 4], BlockId::LAVA),
+            ([12, 15, 4], BlockId::STONE),
+            ([13, 15, 4], BlockId::STONE),
+            ([12, 15, 4], BlockId::AIR),
+        ];
+        for ([x, y, z], block) in edits {
+            world.set_block(x, y, z, block);
+            let incremental = light_levels(&world);
+            world.flood_fill_light();
+            assert_eq!(
+                incremental,
+                light_levels(&world),
+                "after setting {x} {y} {z}"
+            );
+        }
+    }
+}
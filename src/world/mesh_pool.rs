@@ -0,0 +1,201 @@
+//! Meshing chunks on background threads.
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::renderer::types::Vertex;
+
+use super::chunk::{Chunk, ChunkCoord};
+use super::mesher;
+
+/// A chunk to be meshed.
+struct Job {
+    pos: ChunkCoord,
+    version: u64,
+    chunk: Chunk,
+    /// Copies of the loaded neighbours, ordered like [`mesher::Neighbours`].
+    neighbours: [Option<Chunk>; 6],
+}
+
+/// Geometry built for a chunk by a [`MeshPool`].
+pub struct ChunkMesh {
+    pub pos: ChunkCoord,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Geometry drawn translucent, from [`mesher::build_translucent_mesh`].
+    pub translucent_vertices: Vec<Vertex>,
+    pub translucent_indices: Vec<u32>,
+}
+
+/// Builds chunk meshes with [`mesher::build_mesh_with_neighbours`] on a pool of
+/// worker threads.
+///
+/// Chunks and their neighbours are copied when queued, so the world can keep
+/// changing while they are meshed. Every job is versioned, and only the result
+/// of the latest job for a chunk is returned, so a chunk queued again before its
+/// mesh is done never ends up with stale geometry.
+pub struct MeshPool {
+    jobs: Option<mpsc::Sender<Job>>,
+    results: mpsc::Receiver<(Job, ChunkMesh)>,
+    workers: Vec<JoinHandle<()>>,
+    /// Version of the latest job queued for each chunk that isn't done yet.
+    latest: HashMap<ChunkCoord, u64>,
+    next_version: u64,
+}
+
+impl MeshPool {
+    /// Start a pool with `threads` workers.
+    pub fn new(threads: usize) -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        let (result_sender, results) = mpsc::channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..threads.max(1))
+            .map(|i| {
+                let jobs = job_receiver.clone();
+                let results = result_sender.clone();
+                std::thread::Builder::new()
+                    .name(format!("mesher-{i}"))
+                    .spawn(move || {
+                        profiling::register_thread!();
+                        loop {
+                            // Release the lock before meshing, so workers run in parallel
+                            let job = match jobs.lock().unwrap().recv() {
+                                Ok(job) => job,
+                                // The pool was dropped
+                                Err(_) => break,
+                            };
+
+                            profiling::scope!("build chunk mesh");
+                            let origin = job.pos.to_world_origin();
+                            let neighbours = job.neighbours.each_ref().map(Option::as_ref);
+                            let (vertices, indices) = mesher::build_mesh_with_neighbours(
+                                &job.chunk,
+                                &neighbours,
+                                origin.into(),
+                            );
+                            let (translucent_vertices, translucent_indices) =
+                                mesher::build_translucent_mesh(
+                                    &job.chunk,
+                                    &neighbours,
+                                    origin.into(),
+                                );
+                            let mesh = ChunkMesh {
+                                pos: job.pos,
+                                vertices,
+                                indices,
+                                translucent_vertices,
+                                translucent_indices,
+                            };
+                            if results.send((job, mesh)).is_err() {
+                                break;
+                            }
+                        }
+                    })
+                    .expect("failed to spawn mesher thread")
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            results,
+            workers,
+            latest: HashMap::new(),
+            next_version: 0,
+        }
+    }
+
+    /// Start a pool using all but one of the available cores, leaving one for rendering.
+    pub fn with_available_threads() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(cores.saturating_sub(1))
+    }
+
+    /// Queue a chunk to be meshed, replacing any job already queued for it.
+    ///
+    /// Faces on the chunk's edges are culled against `neighbours`.
+    pub fn queue(&mut self, pos: ChunkCoord, chunk: &Chunk, neighbours: mesher::Neighbours) {
+        let version = self.next_version;
+        self.next_version += 1;
+        self.latest.insert(pos, version);
+
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send(Job {
+                pos,
+                version,
+                chunk: chunk.clone(),
+                neighbours: neighbours.map(|n| n.cloned()),
+            });
+        }
+    }
+
+    /// Discard the results of every job queued so far.
+    pub fn cancel_all(&mut self) {
+        self.latest.clear();
+    }
+
+    /// Number of chunks waiting for a mesh.
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.latest.len()
+    }
+
+    /// Collect the meshes finished since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<ChunkMesh> {
+        let mut done = Vec::new();
+        for (job, mesh) in self.results.try_iter() {
+            if self.latest.get(&job.pos) == Some(&job.version) {
+                self.latest.remove(&job.pos);
+                done.push(mesh);
+            }
+        }
+        done
+    }
+}
+
+impl Drop for MeshPool {
+    fn drop(&mut self) {
+        // Closing the channel stops the workers once they finish their current job
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::blocks::BlockId;
+
+    #[test]
+    fn only_the_newest_mesh_of_a_requeued_chunk_is_returned() {
+        let mut pool = MeshPool::new(2);
+        let pos = ChunkCoord::new(0, 0, 0);
+        let mut chunk = Chunk::new();
+        chunk.set(1, 1, 1, BlockId::STONE);
+        pool.queue(pos, &chunk, [None; 6]);
+
+        // Edited and queued again, likely while the first job is still running
+        chunk.set(5, 5, 5, BlockId::STONE);
+        pool.queue(pos, &chunk, [None; 6]);
+        assert_eq!(pool.pending(), 1);
+
+        let mut meshes = Vec::new();
+        let start = std::time::Instant::now();
+        while pool.pending() > 0 {
+            assert!(start.elapsed().as_secs() < 10, "meshing timed out");
+            meshes.extend(pool.poll());
+            std::thread::yield_now();
+        }
+        // Anything left over from the first job is dropped
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        meshes.extend(pool.poll());
+
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].pos, pos);
+        // Both cubes, with all of their faces
+        assert_eq!(meshes[0].vertices.len(), 2 * 6 * 4);
+    }
+}
@@ -0,0 +1,764 @@
+//! Conversion of chunks into renderable geometry.
+
+use nalgebra_glm as glm;
+
+use crate::renderer::types::Vertex;
+
+use super::blocks::{self, BlockId, Face, Facing, ModelKind, Tint};
+use super::chunk::{Chunk, CHUNK_SIZE};
+use super::light::MAX_LIGHT;
+
+/// Number of tiles along each side of the block texture atlas.
+///
+/// The atlas is split into one texture array layer per tile, numbered row by row.
+pub const ATLAS_TILES: u32 = 4;
+
+/// Texture array layer holding the first frame of the first animated texture.
+///
+/// Frames of animated textures are stored after the atlas tiles, one layer each.
+pub const ANIMATED_LAYERS: u32 = ATLAS_TILES * ATLAS_TILES;
+
+/// Height of the top surface of a liquid that has no more liquid above it.
+pub const LIQUID_HEIGHT: f32 = 0.875;
+
+/// Corners of a face, as offsets from the block's minimum corner.
+///
+/// Corners are ordered top-left, bottom-left, bottom-right, top-right when
+/// viewed from outside the block, so they wind counter-clockwise.
+const fn face_corners(face: Face) -> [[f32; 3]; 4] {
+    match face {
+        Face::PosX => [[1., 1., 1.], [1., 0., 1.], [1., 0., 0.], [1., 1., 0.]],
+        Face::NegX => [[0., 1., 0.], [0., 0., 0.], [0., 0., 1.], [0., 1., 1.]],
+        Face::PosY => [[0., 1., 0.], [0., 1., 1.], [1., 1., 1.], [1., 1., 0.]],
+        Face::NegY => [[0., 0., 1.], [0., 0., 0.], [1., 0., 0.], [1., 0., 1.]],
+        Face::PosZ => [[0., 1., 1.], [0., 0., 1.], [1., 0., 1.], [1., 1., 1.]],
+        Face::NegZ => [[1., 1., 0.], [1., 0., 0.], [0., 0., 0.], [0., 1., 0.]],
+    }
+}
+
+/// Brightness of faces pointing through `face`, for [`Vertex::shade`].
+///
+/// Tops are brightest and bottoms darkest, with the sides in between, so that
+/// the shape of the terrain reads even where the lighting is flat.
+const fn face_shade(face: Face) -> f32 {
+    match face {
+        Face::PosY => 1.0,
+        Face::PosZ | Face::NegZ => 0.8,
+        Face::PosX | Face::NegX => 0.6,
+        Face::NegY => 0.5,
+    }
+}
+
+/// Rotate a corner of a block's model around the center of the block.
+#[inline]
+fn rotate_corner(facing: Facing, corner: [f32; 3]) -> [f32; 3] {
+    facing.rotate(corner.map(|c| c - 0.5)).map(|c| c + 0.5)
+}
+
+/// Corners of the quads of a [`ModelKind::Cross`] block.
+///
+/// Plants are seen from all around, so each of the two diagonal quads is
+/// followed by a copy facing the other way, which back-face culling keeps when
+/// seen from behind. Each quad is ordered like [`face_corners`] from the side it
+/// faces, so the texture isn't mirrored on the back.
+const CROSS_CORNERS: [[[f32; 3]; 4]; 4] = [
+    [[0., 1., 0.], [0., 0., 0.], [1., 0., 1.], [1., 1., 1.]],
+    [[1., 1., 1.], [1., 0., 1.], [0., 0., 0.], [0., 1., 0.]],
+    [[1., 1., 0.], [1., 0., 0.], [0., 0., 1.], [0., 1., 1.]],
+    [[0., 1., 1.], [0., 0., 1.], [1., 0., 0.], [1., 1., 0.]],
+];
+
+/// Texture coordinates of a quad, in the same order as [`face_corners`].
+const QUAD_UVS: [[f32; 2]; 4] = [[0., 0.], [0., 1.], [1., 1.], [1., 0.]];
+
+/// Normal given to every quad of a [`ModelKind::Cross`] block.
+///
+/// Plants are lit as if they faced upwards, so they match the ground they
+/// stand on.
+const CROSS_NORMAL: [f32; 3] = [0., 1., 0.];
+
+/// The chunks sharing a face with the chunk being meshed, ordered like [`Face::ALL`].
+///
+/// Neighbours that aren't loaded are `None`, and faces towards them are kept.
+pub type Neighbours<'a> = [Option<&'a Chunk>; 6];
+
+/// Find the block one step through `face` from a block of `chunk`, as the
+/// chunk holding it and its position in that chunk.
+///
+/// Positions outside the chunk are looked up in the neighbour on that side.
+/// Returns `None` if it isn't loaded.
+fn step_towards<'a>(
+    chunk: &'a Chunk,
+    neighbours: &Neighbours<'a>,
+    face: Face,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> Option<(&'a Chunk, [usize; 3])> {
+    let [nx, ny, nz] = face.normal();
+    let (x, y, z) = (x as i32 + nx, y as i32 + ny, z as i32 + nz);
+    let size = CHUNK_SIZE as i32;
+    let range = 0..size;
+    if range.contains(&x) && range.contains(&y) && range.contains(&z) {
+        return Some((chunk, [x as usize, y as usize, z as usize]));
+    }
+
+    let local = [x, y, z].map(|v| v.rem_euclid(size) as usize);
+    neighbours[face as usize].map(|n| (n, local))
+}
+
+/// Get the block one step through `face` from a block of `chunk`.
+///
+/// Blocks in neighbours that aren't loaded are treated as air.
+fn block_towards(
+    chunk: &Chunk,
+    neighbours: &Neighbours,
+    face: Face,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> BlockId {
+    step_towards(chunk, neighbours, face, x, y, z)
+        .map_or(BlockId::AIR, |(c, [x, y, z])| c.get(x, y, z))
+}
+
+/// Light reaching a surface, for [`Vertex::light`] and [`Vertex::sky_light`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct FaceLight {
+    /// Block light, from 0 to 1.
+    block: f32,
+    /// Skylight, from 0 to 1.
+    sky: f32,
+}
+
+impl FaceLight {
+    /// Light beyond the loaded chunks, which nothing shades from the sky.
+    const OPEN_SKY: Self = Self {
+        block: 0.0,
+        sky: 1.0,
+    };
+
+    /// Light of a block of `chunk`.
+    fn of(chunk: &Chunk, x: usize, y: usize, z: usize) -> Self {
+        let brightness = |level: u8| level as f32 / MAX_LIGHT as f32;
+        Self {
+            block: brightness(chunk.light(x, y, z)),
+            sky: brightness(chunk.sky_light(x, y, z)),
+        }
+    }
+}
+
+/// Get the light one step through `face` from a block of `chunk`, which lights
+/// the face of the block on that side.
+fn light_towards(
+    chunk: &Chunk,
+    neighbours: &Neighbours,
+    face: Face,
+    x: usize,
+    y: usize,
+    z: usize,
+) -> FaceLight {
+    step_towards(chunk, neighbours, face, x, y, z).map_or(FaceLight::OPEN_SKY, |(c, [x, y, z])| {
+        FaceLight::of(c, x, y, z)
+    })
+}
+
+/// Get the tangent of a quad ordered like [`face_corners`], for [`Vertex::tangent`].
+///
+/// Texture U increases from the first to the last corner, and V from the first
+/// to the second, so the tangent is exact for any quad laid out like a face.
+fn quad_tangent(corners: [[f32; 3]; 4], normal: [f32; 3]) -> [f32; 4] {
+    let origin = glm::Vec3::from(corners[0]);
+    let u = (glm::Vec3::from(corners[3]) - origin).normalize();
+    let v = glm::Vec3::from(corners[1]) - origin;
+    let handedness = if glm::Vec3::from(normal).cross(&u).dot(&v) >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    };
+    [u.x, u.y, u.z, handedness]
+}
+
+/// Append a quad to a mesh.
+///
+/// `offset` is added to every corner.
+#[allow(clippy::too_many_arguments)]
+fn push_quad(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    offset: [f32; 3],
+    corners: [[f32; 3]; 4],
+    tile: u32,
+    normal: [f32; 3],
+    tinted: bool,
+    emissive: f32,
+    light: FaceLight,
+    shade: f32,
+) {
+    let base = vertices.len() as u32;
+    let tangent = quad_tangent(corners, normal);
+    for (corner, uv) in corners.into_iter().zip(QUAD_UVS) {
+        vertices.push(Vertex {
+            position: [
+                offset[0] + corner[0],
+                offset[1] + corner[1],
+                offset[2] + corner[2],
+            ],
+            texture: uv,
+            tint: if tinted { 1.0 } else { 0.0 },
+            normal,
+            layer: tile,
+            tangent,
+            emissive,
+            light: light.block,
+            sky_light: light.sky,
+            shade,
+        });
+    }
+    indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+/// Build the geometry for a chunk.
+///
+/// Faces hidden by an opaque block of the chunk are skipped. `origin` is the
+/// world-space position of the chunk's minimum corner.
+///
+/// Returns a (vertices, indices) pair.
+#[inline]
+pub fn build_mesh(chunk: &Chunk, origin: [f32; 3]) -> (Vec<Vertex>, Vec<u32>) {
+    build_mesh_with_neighbours(chunk, &[None; 6], origin)
+}
+
+/// Build the geometry for a chunk, also skipping faces on its edges that are
+/// hidden by an opaque block of a neighbouring chunk.
+///
+/// Quads are emitted in a fixed order, so the same chunk always gives the same
+/// buffers byte for byte: blocks by increasing Y, then Z, then X, and the
+/// quads of each block in the order of [`Face::ALL`] on the upright model, or
+/// of [`CROSS_CORNERS`] for plants.
+///
+/// Returns a (vertices, indices) pair.
+pub fn build_mesh_with_neighbours(
+    chunk: &Chunk,
+    neighbours: &Neighbours,
+    origin: [f32; 3],
+) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for y in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let id = chunk.get(x, y, z);
+                if id.is_air() {
+                    continue;
+                }
+                // Unknown blocks have no geometry either
+                let block = match blocks::block_type(id) {
+                    Some(b) => b,
+                    None => continue,
+                };
+
+                let offset = [
+                    origin[0] + x as f32,
+                    origin[1] + y as f32,
+                    origin[2] + z as f32,
+                ];
+
+                match block.model {
+                    ModelKind::Cube => {
+                        let facing = chunk.state(x, y, z).to_facing();
+                        // `face` is a face of the upright model, which may be
+                        // rotated to point elsewhere
+                        for face in Face::ALL {
+                            let rotated = facing.rotate_face(face);
+                            let neighbour = block_towards(chunk, neighbours, rotated, x, y, z);
+                            if blocks::block_type(neighbour).is_some_and(|b| b.is_opaque()) {
+                                continue;
+                            }
+
+                            push_quad(
+                                &mut vertices,
+                                &mut indices,
+                                offset,
+                                face_corners(face).map(|c| rotate_corner(facing, c)),
+                                block.tile(face),
+                                rotated.normal().map(|n| n as f32),
+                                block.tint.applies_to(face),
+                                block.emissive,
+                                light_towards(chunk, neighbours, rotated, x, y, z),
+                                face_shade(rotated),
+                            );
+                        }
+                    }
+                    ModelKind::Cross => {
+                        let tinted = block.tint != Tint::None;
+                        // Plants let light through, so they hold their own light level
+                        let light = FaceLight::of(chunk, x, y, z);
+                        for corners in CROSS_CORNERS {
+                            push_quad(
+                                &mut vertices,
+                                &mut indices,
+                                offset,
+                                corners,
+                                block.sides,
+                                CROSS_NORMAL,
+                                tinted,
+                                block.emissive,
+                                light,
+                                // Shaded like the top, matching their normal
+                                face_shade(Face::PosY),
+                            );
+                        }
+                    }
+                    // Built separately by `build_translucent_mesh`
+                    ModelKind::Liquid => {}
+                }
+            }
+        }
+    }
+
+    (vertices, indices)
+}
+
+/// Build a cube around the block at world-space `position`, with `layer` on every
+/// face, like the cracks on a block being broken.
+///
+/// The cube is enlarged by `inflate` on each side so that it is drawn over the
+/// block's own faces.
+///
+/// Returns a (vertices, indices) pair.
+pub fn build_overlay_cube(position: [i32; 3], layer: u32, inflate: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    let offset = position.map(|p| p as f32 - inflate);
+    for face in Face::ALL {
+        push_quad(
+            &mut vertices,
+            &mut indices,
+            offset,
+            face_corners(face).map(|c| c.map(|c| c * (1.0 + 2.0 * inflate))),
+            layer,
+            face.normal().map(|n| n as f32),
+            false,
+            0.0,
+            FaceLight::default(),
+            face_shade(face),
+        );
+    }
+    (vertices, indices)
+}
+
+/// Build the geometry for the liquids of a chunk, which is drawn translucent
+/// after everything else.
+///
+/// Faces towards opaque blocks or more of the same liquid are skipped, so only
+/// the outer surface of a body of liquid is drawn. Its top is lowered to
+/// [`LIQUID_HEIGHT`] unless more of the liquid is above.
+///
+/// Quads are emitted in the same fixed order as [`build_mesh_with_neighbours`].
+///
+/// Returns a (vertices, indices) pair.
+pub fn build_translucent_mesh(
+    chunk: &Chunk,
+    neighbours: &Neighbours,
+    origin: [f32; 3],
+) -> (Vec<Vertex>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for y in 0..CHUNK_SIZE {
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let id = chunk.get(x, y, z);
+                let block = match blocks::block_type(id) {
+                    Some(b) if b.model == ModelKind::Liquid => b,
+                    _ => continue,
+                };
+
+                let offset = [
+                    origin[0] + x as f32,
+                    origin[1] + y as f32,
+                    origin[2] + z as f32,
+                ];
+                let surface = block_towards(chunk, neighbours, Face::PosY, x, y, z) != id;
+
+                for face in Face::ALL {
+                    let neighbour = block_towards(chunk, neighbours, face, x, y, z);
+                    if neighbour == id {
+                        continue;
+                    }
+                    // A lowered top doesn't touch the block above it
+                    let lowered_top = surface && face == Face::PosY;
+                    if !lowered_top && blocks::block_type(neighbour).is_some_and(|b| b.is_opaque())
+                    {
+                        continue;
+                    }
+
+                    let mut corners = face_corners(face);
+                    if surface {
+                        for corner in &mut corners {
+                            corner[1] = corner[1].min(LIQUID_HEIGHT);
+                        }
+                    }
+
+                    push_quad(
+                        &mut vertices,
+                        &mut indices,
+                        offset,
+                        corners,
+                        block.tile(face),
+                        face.normal().map(|n| n as f32),
+                        block.tint.applies_to(face),
+                        block.emissive,
+                        light_towards(chunk, neighbours, face, x, y, z),
+                        face_shade(face),
+                    );
+                }
+            }
+        }
+    }
+
+    (vertices, indices)
+}
+
+/// Derive normals from the winding of each triangle, and assign them to its vertices.
+///
+/// Triangles are expected to wind counter-clockwise when viewed from the front.
+/// Vertices shared by triangles facing different ways end up with the normal of
+/// the last of them, and degenerate triangles are skipped.
+pub fn compute_flat_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]]
+            .map(|i| glm::Vec3::from(vertices[i as usize].position));
+
+        let normal = (b - a).cross(&(c - a));
+        if normal.norm_squared() <= f32::EPSILON {
+            continue;
+        }
+        let normal = normal.normalize();
+
+        for &i in triangle {
+            vertices[i as usize].normal = normal.into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::blocks::BlockState;
+
+    fn vertex(position: [f32; 3]) -> Vertex {
+        Vertex {
+            position,
+            texture: [0.0; 2],
+            tint: 0.0,
+            normal: [0.0; 3],
+            layer: 0,
+            tangent: [0.0; 4],
+            emissive: 0.0,
+            light: 0.0,
+            sky_light: 0.0,
+            shade: 0.0,
+        }
+    }
+
+    /// Assert that a lone cube has all six faces, each wound counter-clockwise
+    /// when seen from outside, so that back-face culling keeps them.
+    fn assert_cube_wound_outwards(vertices: &[Vertex], indices: &[u32], center: glm::Vec3) {
+        let mut faces = Vec::new();
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] =
+                [0, 1, 2].map(|i| glm::Vec3::from(vertices[triangle[i] as usize].position));
+            let front = (b - a).cross(&(c - a));
+            let outwards = (a + b + c) / 3.0 - center;
+            assert!(
+                front.dot(&outwards) > 0.0,
+                "triangle {triangle:?} faces into the cube"
+            );
+
+            let face = Face::from_normal(front.normalize().map(|n| n.round() as i32).into());
+            assert!(face.is_some(), "triangle {triangle:?} isn't axis-aligned");
+            if !faces.contains(&face) {
+                faces.push(face);
+            }
+        }
+        assert_eq!(faces.len(), 6, "expected every face of the cube");
+    }
+
+    #[test]
+    fn cube_faces_wind_counter_clockwise_from_outside() {
+        let center = glm::vec3(1.5, 1.5, 1.5);
+
+        for facing in [
+            Facing::Up,
+            Facing::Down,
+            Facing::East,
+            Facing::West,
+            Facing::South,
+            Facing::North,
+        ] {
+            let mut chunk = Chunk::new();
+            chunk.set_with_state(1, 1, 1, BlockId::LOG, blocks::BlockState::facing(facing));
+            let (vertices, indices) = build_mesh(&chunk, [0.0; 3]);
+            assert_cube_wound_outwards(&vertices, &indices, center);
+        }
+
+        let mut chunk = Chunk::new();
+        chunk.set(1, 1, 1, BlockId::WATER);
+        let (vertices, indices) = build_translucent_mesh(&chunk, &[None; 6], [0.0; 3]);
+        assert_cube_wound_outwards(&vertices, &indices, center);
+
+        let (vertices, indices) = build_overlay_cube([1, 1, 1], 0, 0.002);
+        assert_cube_wound_outwards(&vertices, &indices, center);
+    }
+
+    #[test]
+    fn counter_clockwise_triangle_faces_viewer() {
+        let mut vertices = [
+            vertex([0.0, 0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0]),
+            vertex([0.0, 1.0, 0.0]),
+        ];
+        compute_flat_normals(&mut vertices, &[0, 1, 2]);
+        for v in &vertices {
+            assert_eq!(v.normal, [0.0, 0.0, 1.0]);
+        }
+
+        // Reversing the winding flips the normal
+        compute_flat_normals(&mut vertices, &[0, 2, 1]);
+        for v in &vertices {
+            assert_eq!(v.normal, [0.0, 0.0, -1.0]);
+        }
+    }
+
+    #[test]
+    fn normals_are_unit_length() {
+        let mut vertices = [
+            vertex([0.0, 0.0, 0.0]),
+            vertex([0.0, 0.0, 4.0]),
+            vertex([3.0, 0.0, 0.0]),
+        ];
+        compute_flat_normals(&mut vertices, &[0, 1, 2]);
+        assert_eq!(vertices[0].normal, [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_skipped() {
+        let mut vertices = [
+            vertex([0.0, 0.0, 0.0]),
+            vertex([1.0, 1.0, 1.0]),
+            vertex([2.0, 2.0, 2.0]),
+        ];
+        vertices[0].normal = [1.0, 0.0, 0.0];
+        compute_flat_normals(&mut vertices, &[0, 1, 2]);
+        assert_eq!(vertices[0].normal, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn culls_faces_hidden_by_neighbours() {
+        let mut chunk = Chunk::new();
+        chunk.set(CHUNK_SIZE - 1, 0, 0, BlockId::STONE);
+        let mut neighbour = Chunk::new();
+        neighbour.set(0, 0, 0, BlockId::STONE);
+
+        let (vertices, _) = build_mesh(&chunk, [0.0; 3]);
+        assert_eq!(vertices.len(), 6 * 4);
+
+        let mut neighbours: Neighbours = [None; 6];
+        neighbours[Face::PosX as usize] = Some(&neighbour);
+        let (vertices, _) = build_mesh_with_neighbours(&chunk, &neighbours, [0.0; 3]);
+        assert_eq!(vertices.len(), 5 * 4);
+        assert!(vertices.iter().all(|v| v.normal != [1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn tangents_follow_texture_coordinates() {
+        let mut chunk = Chunk::new();
+        chunk.set(0, 0, 0, BlockId::STONE);
+        let (vertices, _) = build_mesh(&chunk, [0.0; 3]);
+
+        let tangent_of = |normal: [f32; 3]| {
+            vertices
+                .iter()
+                .find(|v| v.normal == normal)
+                .map(|v| v.tangent)
+                .unwrap()
+        };
+        // U runs along +X on top, and towards -Z on the east face
+        assert_eq!(tangent_of([0.0, 1.0, 0.0]), [1.0, 0.0, 0.0, -1.0]);
+        assert_eq!(tangent_of([1.0, 0.0, 0.0]), [0.0, 0.0, -1.0, -1.0]);
+
+        // The bitangent rebuilt from the handedness points along +V
+        for quad in vertices.chunks(4) {
+            let [t0, t1] = [quad[0].texture, quad[1].texture];
+            let [p0, p1] = [quad[0].position, quad[1].position].map(glm::Vec3::from);
+            let [x, y, z, w] = quad[0].tangent;
+            let bitangent = glm::Vec3::from(quad[0].normal).cross(&glm::vec3(x, y, z)) * w;
+            assert!(t1[1] > t0[1]);
+            assert_eq!(bitangent, p1 - p0);
+        }
+    }
+
+    #[test]
+    fn tops_are_brightest_and_bottoms_darkest() {
+        let mut chunk = Chunk::new();
+        chunk.set(1, 1, 1, BlockId::STONE);
+        let (vertices, _) = build_mesh(&chunk, [0.0; 3]);
+
+        let shade_of = |normal: [f32; 3]| {
+            let mut shades = vertices
+                .iter()
+                .filter(|v| v.normal == normal)
+                .map(|v| v.shade);
+            let shade = shades.next().unwrap();
+            assert!(shades.all(|s| s == shade));
+            shade
+        };
+        let top = shade_of([0.0, 1.0, 0.0]);
+        let bottom = shade_of([0.0, -1.0, 0.0]);
+        for side in [
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+        ] {
+            assert!(bottom < shade_of(side) && shade_of(side) < top);
+        }
+    }
+
+    #[test]
+    fn rotated_logs_stay_inside_their_block() {
+        let facings = [
+            Facing::Up,
+            Facing::Down,
+            Facing::East,
+            Facing::West,
+            Facing::South,
+            Facing::North,
+        ];
+        for facing in facings {
+            let mut chunk = Chunk::new();
+            chunk.set_with_state(1, 1, 1, BlockId::LOG, BlockState::facing(facing));
+            let (vertices, _) = build_mesh(&chunk, [0.0; 3]);
+
+            assert_eq!(vertices.len(), 24);
+            for v in &vertices {
+                assert!(v.position.iter().all(|&c| (1.0..=2.0).contains(&c)));
+            }
+            // The rings on the log's ends face along its length
+            let top = blocks::block_type(BlockId::LOG).unwrap().top;
+            let normal = facing.rotate([0.0, 1.0, 0.0]);
+            assert!(vertices
+                .iter()
+                .filter(|v| v.normal == normal)
+                .all(|v| v.layer == top));
+        }
+    }
+
+    #[test]
+    fn plants_are_visible_from_both_sides() {
+        let mut chunk = Chunk::new();
+        chunk.set(0, 0, 0, BlockId::FLOWER);
+        let (vertices, indices) = build_mesh(&chunk, [0.0; 3]);
+
+        // Every front facing triangle has a back facing one in the same plane
+        let fronts: Vec<_> = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] =
+                    [0, 1, 2].map(|i| glm::Vec3::from(vertices[t[i] as usize].position));
+                (b - a).cross(&(c - a)).normalize()
+            })
+            .collect();
+        assert_eq!(fronts.len(), 8);
+        for front in &fronts {
+            assert!(fronts.iter().any(|back| (front + back).norm() < 1e-6));
+        }
+    }
+
+    /// A chunk with a bit of everything: cubes, rotated cubes, plants and
+    /// liquids, scattered by a fixed hash.
+    fn mixed_chunk() -> Chunk {
+        let blocks = [
+            BlockId::AIR,
+            BlockId::STONE,
+            BlockId::GRASS,
+            BlockId::FLOWER,
+            BlockId::LOG,
+            BlockId::WATER,
+        ];
+        let mut chunk = Chunk::new();
+        for i in 0..CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE {
+            let hash = (i as u32).wrapping_mul(0x9e37_79b9) >> 24;
+            let block = blocks[hash as usize % blocks.len()];
+            // One of the six facings
+            let state = blocks::BlockState(hash as u8 % 6);
+            let (x, y, z) = (
+                i % CHUNK_SIZE,
+                i / (CHUNK_SIZE * CHUNK_SIZE),
+                i / CHUNK_SIZE % CHUNK_SIZE,
+            );
+            chunk.set_with_state(x, y, z, block, state);
+        }
+        chunk
+    }
+
+    #[test]
+    fn output_is_reproducible() {
+        let chunk = mixed_chunk();
+        let neighbours = [Some(&chunk), None, Some(&chunk), None, None, Some(&chunk)];
+        let build = || {
+            let (vertices, indices) =
+                build_mesh_with_neighbours(&chunk, &neighbours, [16.0, 0.0, -32.0]);
+            let (translucent_vertices, translucent_indices) =
+                build_translucent_mesh(&chunk, &neighbours, [16.0, 0.0, -32.0]);
+            [
+                bytemuck::cast_slice::<_, u8>(&vertices).to_vec(),
+                bytemuck::cast_slice(&indices).to_vec(),
+                bytemuck::cast_slice(&translucent_vertices).to_vec(),
+                bytemuck::cast_slice(&translucent_indices).to_vec(),
+            ]
+        };
+        assert_eq!(build(), build());
+    }
+
+    #[test]
+    fn quads_follow_block_order() {
+        let (vertices, _) = build_mesh(&mixed_chunk(), [0.0; 3]);
+        assert!(!vertices.is_empty());
+
+        // The block a quad belongs to is half a block behind its center
+        let block_of = |quad: &[Vertex]| {
+            let center = quad
+                .iter()
+                .map(|v| glm::Vec3::from(v.position))
+                .sum::<glm::Vec3>()
+                / 4.0;
+            let [x, y, z] = (center - glm::Vec3::from(quad[0].normal) * 0.5)
+                .map(|c| c.floor() as i32)
+                .into();
+            (y, z, x)
+        };
+        let blocks: Vec<_> = vertices.chunks_exact(4).map(block_of).collect();
+        assert!(blocks.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn matches_mesher_normals() {
+        let mut chunk = Chunk::new();
+        chunk.set(1, 1, 1, BlockId::STONE);
+        let (vertices, indices) = build_mesh(&chunk, [0.0; 3]);
+
+        let mut computed = vertices.clone();
+        for v in &mut computed {
+            v.normal = [0.0; 3];
+        }
+        compute_flat_normals(&mut computed, &indices);
+
+        for (expected, actual) in vertices.iter().zip(&computed) {
+            assert_eq!(expected.normal, actual.normal);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! The voxel world.
+
+pub mod blocks;
+pub mod chunk;
+pub mod immersion;
+pub mod light;
+pub mod mesh_pool;
+pub mod mesher;
+pub mod player;
+pub mod raycast;
+pub mod seed;
+pub mod vox;
+
+use std::collections::{HashMap, HashSet};
+
+use blocks::{BlockId, BlockState};
+use chunk::{Chunk, ChunkCoord, CHUNK_SIZE};
+use seed::WorldSeed;
+
+/// Salts separating the random streams used by generation.
+const TERRAIN_SALT: u64 = 1;
+const DETAIL_SALT: u64 = 2;
+const FLOWER_SALT: u64 = 3;
+const LAVA_SALT: u64 = 4;
+
+/// Height below which empty space in generated terrain is filled with water.
+const WATER_LEVEL: i32 = 6;
+
+/// A collection of chunks, keyed by chunk coordinate.
+#[derive(Default)]
+pub struct World {
+    seed: WorldSeed,
+    chunks: HashMap<ChunkCoord, Chunk>,
+    /// Chunks whose light changed since they were last taken, see
+    /// [`World::take_relit_chunks`].
+    relit: HashSet<ChunkCoord>,
+}
+
+impl World {
+    /// Create an empty world.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generate a square of chunk columns around the origin.
+    ///
+    /// `radius` is the number of columns on each side of the origin, and `height`
+    /// the number of chunks stacked in each, starting at Y = 0. Each chunk only
+    /// depends on the seed and its own coordinate, and is lit once they are all
+    /// generated.
+    pub fn generate(seed: WorldSeed, radius: i32, height: u32) -> Self {
+        let mut world = Self {
+            seed,
+            ..Self::default()
+        };
+        for base in ChunkCoord::default().horizontal_square(radius) {
+            for pos in base.column(height) {
+                world.chunks.insert(pos, Self::generate_chunk(seed, pos));
+            }
+        }
+        world.flood_fill_light();
+        world
+    }
+
+    /// Generate rolling terrain for the chunk at a chunk coordinate.
+    pub(crate) fn generate_chunk(seed: WorldSeed, pos: ChunkCoord) -> Chunk {
+        let mut chunk = Chunk::new();
+        let size = CHUNK_SIZE as i32;
+
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let wx = pos.x * size + x as i32;
+                let wz = pos.z * size + z as i32;
+                let hills = seed.derive(TERRAIN_SALT).value_noise(wx, wz, 24);
+                let detail = seed.derive(DETAIL_SALT).value_noise(wx, wz, 6);
+                let height = (3.0 + 8.0 * hills + 2.0 * detail) as i32;
+                // Rare pools of lava in place of the grass, above the water
+                let lava = height >= WATER_LEVEL && seed.derive(LAVA_SALT).random(wx, wz) < 0.004;
+
+                for y in 0..CHUNK_SIZE {
+                    let wy = pos.y * size + y as i32;
+                    let block = match height - wy {
+                        _ if wy == 0 => BlockId::BEDROCK,
+                        i32::MIN..=0 if wy < WATER_LEVEL => BlockId::WATER,
+                        i32::MIN..=0 => continue,
+                        1 if lava => BlockId::LAVA,
+                        1 => BlockId::GRASS,
+                        2..=3 => BlockId::DIRT,
+                        _ => BlockId::STONE,
+                    };
+                    chunk.set(x, y, z, block);
+                }
+
+                // Scatter flowers on top of the grass, above the water
+                let flower_y = height - pos.y * size;
+                if height >= WATER_LEVEL
+                    && !lava
+                    && (0..size).contains(&flower_y)
+                    && seed.derive(FLOWER_SALT).random(wx, wz) < 0.02
+                {
+                    chunk.set(x, flower_y as usize, z, BlockId::FLOWER);
+                }
+            }
+        }
+
+        chunk
+    }
+
+    /// Get the seed the world was generated from.
+    #[inline]
+    pub const fn seed(&self) -> WorldSeed {
+        self.seed
+    }
+
+    /// Get the chunk at a chunk coordinate.
+    #[inline]
+    pub fn chunk(&self, pos: ChunkCoord) -> Option<&Chunk> {
+        self.chunks.get(&pos)
+    }
+
+    /// Get the six chunks sharing a face with the chunk at a chunk coordinate.
+    #[inline]
+    pub fn neighbours(&self, pos: ChunkCoord) -> mesher::Neighbours<'_> {
+        pos.neighbors().map(|n| self.chunks.get(&n))
+    }
+
+    /// Get the block at a world-space block position.
+    ///
+    /// Positions in chunks that aren't loaded are treated as air.
+    pub fn block(&self, x: i32, y: i32, z: i32) -> BlockId {
+        let size = CHUNK_SIZE as i32;
+        match self.chunks.get(&ChunkCoord::from_block(x, y, z)) {
+            Some(chunk) => chunk.get(
+                x.rem_euclid(size) as usize,
+                y.rem_euclid(size) as usize,
+                z.rem_euclid(size) as usize,
+            ),
+            None => BlockId::AIR,
+        }
+    }
+
+    /// Get the state of the block at a world-space block position.
+    ///
+    /// Positions in chunks that aren't loaded have the default state.
+    pub fn block_state(&self, x: i32, y: i32, z: i32) -> BlockState {
+        let size = CHUNK_SIZE as i32;
+        match self.chunks.get(&ChunkCoord::from_block(x, y, z)) {
+            Some(chunk) => chunk.state(
+                x.rem_euclid(size) as usize,
+                y.rem_euclid(size) as usize,
+                z.rem_euclid(size) as usize,
+            ),
+            None => BlockState::default(),
+        }
+    }
+
+    /// Set the block at a world-space block position, with the default state.
+    ///
+    /// Returns the coordinate of the chunk that changed, or `None` if the
+    /// position isn't in a loaded chunk.
+    #[inline]
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockId) -> Option<ChunkCoord> {
+        self.set_block_state(x, y, z, block, BlockState::default())
+    }
+
+    /// Set the block and its state at a world-space block position.
+    ///
+    /// Light around the block is updated if it changed, and the chunks it
+    /// reached can be taken with [`World::take_relit_chunks`].
+    ///
+    /// Returns the coordinate of the chunk that changed, or `None` if the
+    /// position isn't in a loaded chunk.
+    pub fn set_block_state(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: BlockId,
+        state: BlockState,
+    ) -> Option<ChunkCoord> {
+        let size = CHUNK_SIZE as i32;
+        let pos = ChunkCoord::from_block(x, y, z);
+        let old = self.block(x, y, z);
+        self.chunks.get_mut(&pos)?.set_with_state(
+            x.rem_euclid(size) as usize,
+            y.rem_euclid(size) as usize,
+            z.rem_euclid(size) as usize,
+            block,
+            state,
+        );
+        if light::affects_light(old, block) {
+            self.update_light([x, y, z]);
+        }
+        Some(pos)
+    }
+
+    /// Whether the block at a world-space block position can be collided with.
+    #[inline]
+    pub fn is_solid(&self, x: i32, y: i32, z: i32) -> bool {
+        blocks::block_type(self.block(x, y, z)).is_some_and(|b| b.is_opaque())
+    }
+
+    /// Iterate over all loaded chunks and their coordinates.
+    #[inline]
+    pub fn chunks(&self) -> impl Iterator<Item = (&ChunkCoord, &Chunk)> {
+        self.chunks.iter()
+    }
+}
@@ -0,0 +1,216 @@
+//! A physically simulated player.
+
+use nalgebra_glm as glm;
+
+use super::World;
+
+/// Downward acceleration, in blocks per second squared.
+const GRAVITY: f32 = 28.0;
+
+/// Fastest speed the player can fall at, in blocks per second.
+const TERMINAL_VELOCITY: f32 = 60.0;
+
+/// Largest distance moved along an axis in one collision step.
+///
+/// Keeping this below a block's width means no block can be skipped over.
+const MAX_STEP: f32 = 0.45;
+
+/// Tolerance used so that touching a block doesn't count as overlapping it.
+const EPSILON: f32 = 1e-4;
+
+/// A player that walks through the world and collides with solid blocks.
+pub struct Player {
+    /// Position of the center of the player's feet.
+    pub position: glm::Vec3,
+    /// Velocity in blocks per second.
+    pub velocity: glm::Vec3,
+    /// Width and depth of the bounding box.
+    pub width: f32,
+    /// Height of the bounding box.
+    pub height: f32,
+    /// Height of the eyes above the feet.
+    pub eye_height: f32,
+    /// Position before the last physics step, to interpolate from.
+    previous_position: glm::Vec3,
+    on_ground: bool,
+}
+
+impl Player {
+    pub fn new(position: glm::Vec3) -> Self {
+        Self {
+            position,
+            velocity: glm::Vec3::zeros(),
+            width: 0.6,
+            height: 1.8,
+            eye_height: 1.62,
+            previous_position: position,
+            on_ground: false,
+        }
+    }
+
+    /// Position of the player's eyes, where the camera should be.
+    #[inline]
+    pub fn eye_position(&self) -> glm::Vec3 {
+        self.position + glm::vec3(0.0, self.eye_height, 0.0)
+    }
+
+    /// Position of the player's eyes a fraction `alpha` of the way through the
+    /// last physics step, from 0 at its start to 1 at its end.
+    #[inline]
+    pub fn interpolated_eye_position(&self, alpha: f32) -> glm::Vec3 {
+        glm::lerp(&self.previous_position, &self.position, alpha)
+            + glm::vec3(0.0, self.eye_height, 0.0)
+    }
+
+    /// Move the player to `position`, without interpolating from where they were.
+    pub fn teleport(&mut self, position: glm::Vec3) {
+        self.position = position;
+        self.previous_position = position;
+    }
+
+    /// Whether the player is standing on a solid block.
+    #[inline]
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// Get the (min, max) corners of the player's bounding box.
+    pub fn aabb(&self) -> (glm::Vec3, glm::Vec3) {
+        let half = self.width / 2.0;
+        (
+            self.position - glm::vec3(half, 0.0, half),
+            self.position + glm::vec3(half, self.height, half),
+        )
+    }
+
+    /// Launch the player upwards if they are standing on the ground.
+    pub fn jump(&mut self, speed: f32) {
+        if self.on_ground {
+            self.velocity.y = speed;
+            self.on_ground = false;
+        }
+    }
+
+    /// Apply gravity and move the player, resolving collisions with the world.
+    ///
+    /// Each axis is moved separately, in steps small enough that fast movement
+    /// can't pass through a block.
+    pub fn physics_step(&mut self, dt: f32, world: &World) {
+        self.previous_position = self.position;
+        self.velocity.y = (self.velocity.y - GRAVITY * dt).max(-TERMINAL_VELOCITY);
+
+        let delta = self.velocity * dt;
+        let steps = (delta.abs().max() / MAX_STEP).ceil().max(1.0) as u32;
+        let step = delta / steps as f32;
+
+        self.on_ground = false;
+        for _ in 0..steps {
+            // Vertical first, so that landing is resolved before sliding along the ground
+            for axis in [1, 0, 2] {
+                if step[axis] != 0.0 && self.velocity[axis] != 0.0 {
+                    self.move_axis(axis, step[axis], world);
+                }
+            }
+        }
+    }
+
+    /// Move along a single axis, stopping at the first solid block in the way.
+    fn move_axis(&mut self, axis: usize, distance: f32, world: &World) {
+        self.position[axis] += distance;
+
+        if !self.collides(world) {
+            return;
+        }
+
+        // Steps are shorter than a block, so only the first layer of blocks
+        // entered can be in the way. Push back out to its boundary.
+        let (min, max) = self.aabb();
+        if distance > 0.0 {
+            self.position[axis] -= max[axis] - max[axis].floor();
+        } else {
+            self.position[axis] += min[axis].floor() + 1.0 - min[axis];
+            if axis == 1 {
+                self.on_ground = true;
+            }
+        }
+        self.velocity[axis] = 0.0;
+    }
+
+    /// Whether the bounding box overlaps any solid block.
+    fn collides(&self, world: &World) -> bool {
+        let (min, max) = self.aabb();
+        let lo = min.map(|x| (x + EPSILON).floor() as i32);
+        let hi = max.map(|x| (x - EPSILON).floor() as i32);
+
+        (lo.x..=hi.x).any(|x| (lo.y..=hi.y).any(|y| (lo.z..=hi.z).any(|z| world.is_solid(x, y, z))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::blocks::BlockId;
+    use crate::world::chunk::{Chunk, ChunkCoord, CHUNK_SIZE};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    /// Two chunks stacked on each other, with a floor whose top is at y = 3.
+    fn floor_world() -> World {
+        let mut world = World::new();
+        world.chunks.insert(ChunkCoord::new(0, 0, 0), Chunk::new());
+        world.chunks.insert(ChunkCoord::new(0, 1, 0), Chunk::new());
+        for x in 0..CHUNK_SIZE as i32 {
+            for z in 0..CHUNK_SIZE as i32 {
+                world.set_block(x, 2, z, BlockId::STONE);
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn lands_on_top_of_blocks() {
+        let world = floor_world();
+        let mut player = Player::new(glm::vec3(8.5, 6.0, 8.5));
+
+        for _ in 0..120 {
+            player.physics_step(DT, &world);
+        }
+        assert!(player.on_ground());
+        assert!((player.position.y - 3.0).abs() < 1e-3);
+        assert_eq!(player.velocity.y, 0.0);
+    }
+
+    #[test]
+    fn is_stopped_by_walls() {
+        let mut world = floor_world();
+        for y in 3..6 {
+            for z in 0..CHUNK_SIZE as i32 {
+                world.set_block(10, y, z, BlockId::STONE);
+            }
+        }
+        let mut player = Player::new(glm::vec3(8.5, 3.0, 8.5));
+
+        for _ in 0..120 {
+            player.velocity.x = 5.0;
+            player.physics_step(DT, &world);
+        }
+        // Pressed against the wall's face, still standing on the floor
+        let (_, max) = player.aabb();
+        assert!((max.x - 10.0).abs() < 1e-3);
+        assert!(player.on_ground());
+    }
+
+    #[test]
+    fn does_not_tunnel_when_falling_fast() {
+        let world = floor_world();
+        let mut player = Player::new(glm::vec3(8.5, 30.0, 8.5));
+        player.velocity.y = -TERMINAL_VELOCITY;
+
+        // Long ticks move the player several blocks each
+        for _ in 0..10 {
+            player.physics_step(0.1, &world);
+        }
+        assert!(player.on_ground());
+        assert!((player.position.y - 3.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,261 @@
+//! Finding the block a ray points at.
+
+use nalgebra_glm as glm;
+
+use super::{blocks, chunk::ChunkCoord, World};
+
+/// A block hit by [`World::raycast`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// World-space position of the block that was hit.
+    pub block: [i32; 3],
+    /// Normal of the face the ray entered through.
+    ///
+    /// Zero if the ray started inside the block.
+    pub normal: [i32; 3],
+    /// Distance along the ray to the hit.
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// Position of the empty block in front of the face that was hit, where a
+    /// new block would be placed.
+    #[inline]
+    pub fn adjacent(&self) -> [i32; 3] {
+        [
+            self.block[0] + self.normal[0],
+            self.block[1] + self.normal[1],
+            self.block[2] + self.normal[2],
+        ]
+    }
+}
+
+impl World {
+    /// Find the first block hit by a ray, within `max_distance`.
+    ///
+    /// Any block that isn't empty space or liquid can be hit, including ones
+    /// without collision. `direction` doesn't need to be normalized.
+    pub fn raycast(
+        &self,
+        origin: glm::Vec3,
+        direction: glm::Vec3,
+        max_distance: f32,
+    ) -> Option<RaycastHit> {
+        let direction = direction.try_normalize(f32::EPSILON)?;
+
+        // Step through the grid one block boundary at a time
+        // See "A Fast Voxel Traversal Algorithm" by Amanatides and Woo
+        let mut block = [
+            origin.x.floor() as i32,
+            origin.y.floor() as i32,
+            origin.z.floor() as i32,
+        ];
+        let mut step = [0; 3];
+        // Distance along the ray to the next boundary on each axis
+        let mut next = [f32::INFINITY; 3];
+        // Distance along the ray between boundaries on each axis
+        let mut delta = [f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            let d = direction[axis];
+            if d == 0.0 {
+                continue;
+            }
+            step[axis] = if d > 0.0 { 1 } else { -1 };
+            delta[axis] = 1.0 / d.abs();
+            let boundary = if d > 0.0 {
+                block[axis] as f32 + 1.0
+            } else {
+                block[axis] as f32
+            };
+            next[axis] = (boundary - origin[axis]) / d;
+        }
+
+        let mut normal = [0; 3];
+        let mut distance = 0.0;
+        while distance <= max_distance {
+            let hit = blocks::block_type(self.block(block[0], block[1], block[2]))
+                .is_some_and(|ty| ty.model != blocks::ModelKind::Liquid);
+            if hit {
+                return Some(RaycastHit {
+                    block,
+                    normal,
+                    distance,
+                });
+            }
+
+            let axis = (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap();
+            if next[axis].is_infinite() {
+                return None;
+            }
+
+            distance = next[axis];
+            next[axis] += delta[axis];
+            block[axis] += step[axis];
+            normal = [0; 3];
+            normal[axis] = -step[axis];
+        }
+
+        None
+    }
+
+    /// Find where a block would be placed along a ray, to preview it.
+    ///
+    /// This is the empty or liquid block in front of the face hit by [`World::raycast`].
+    /// Returns `None` if nothing is hit, the ray starts inside a block, or the
+    /// position is occupied or outside the loaded chunks.
+    pub fn raycast_place_preview(
+        &self,
+        origin: glm::Vec3,
+        direction: glm::Vec3,
+        max_distance: f32,
+    ) -> Option<[i32; 3]> {
+        let hit = self.raycast(origin, direction, max_distance)?;
+        if hit.normal == [0; 3] {
+            return None;
+        }
+
+        let [x, y, z] = hit.adjacent();
+        let loaded = self.chunk(ChunkCoord::from_block(x, y, z)).is_some();
+        // Liquids are replaced by placed blocks
+        let free = blocks::block_type(self.block(x, y, z))
+            .is_none_or(|ty| ty.model == blocks::ModelKind::Liquid);
+        (loaded && free).then_some([x, y, z])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::blocks::BlockId;
+    use crate::world::chunk::Chunk;
+
+    /// A world with the chunks around the origin loaded, and no blocks.
+    fn empty_world() -> World {
+        let mut world = World::new();
+        for x in -1..=0 {
+            for y in -1..=0 {
+                for z in -1..=0 {
+                    world.chunks.insert(ChunkCoord::new(x, y, z), Chunk::new());
+                }
+            }
+        }
+        world
+    }
+
+    #[test]
+    fn hits_along_axes() {
+        let mut world = empty_world();
+        world.set_block(5, 1, 1, BlockId::STONE);
+        world.set_block(1, -4, 1, BlockId::DIRT);
+
+        let hit = world
+            .raycast(glm::vec3(1.5, 1.5, 1.5), glm::Vec3::x(), 10.0)
+            .unwrap();
+        assert_eq!(hit.block, [5, 1, 1]);
+        assert_eq!(hit.normal, [-1, 0, 0]);
+        // The hit is on the face, not the centre of the block
+        assert!((hit.distance - 3.5).abs() < 1e-5);
+
+        let hit = world
+            .raycast(glm::vec3(1.5, 1.5, 1.5), -glm::Vec3::y(), 10.0)
+            .unwrap();
+        assert_eq!(hit.block, [1, -4, 1]);
+        assert_eq!(hit.normal, [0, 1, 0]);
+        assert!((hit.distance - 4.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hits_diagonally() {
+        let mut world = empty_world();
+        world.set_block(3, 3, 3, BlockId::STONE);
+
+        let hit = world
+            .raycast(glm::vec3(0.4, 0.5, 0.6), glm::vec3(1.0, 1.0, 1.0), 10.0)
+            .unwrap();
+        assert_eq!(hit.block, [3, 3, 3]);
+        // Entered through the face the ray crosses last, which is on the x axis
+        assert_eq!(hit.normal, [-1, 0, 0]);
+        assert!((hit.distance - 2.6 * 3f32.sqrt()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn hits_at_negative_coordinates() {
+        let mut world = empty_world();
+        world.set_block(-3, -1, -2, BlockId::STONE);
+
+        let hit = world
+            .raycast(glm::vec3(-0.5, -0.5, -1.5), -glm::Vec3::x(), 10.0)
+            .unwrap();
+        assert_eq!(hit.block, [-3, -1, -2]);
+        assert_eq!(hit.normal, [1, 0, 0]);
+        assert!((hit.distance - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn misses_beyond_max_distance() {
+        let mut world = empty_world();
+        world.set_block(5, 1, 1, BlockId::STONE);
+
+        let origin = glm::vec3(1.5, 1.5, 1.5);
+        assert!(world.raycast(origin, glm::Vec3::x(), 3.4).is_none());
+        assert!(world.raycast(origin, glm::Vec3::x(), 3.6).is_some());
+        // Nothing in the way
+        assert!(world.raycast(origin, glm::Vec3::z(), 10.0).is_none());
+    }
+
+    #[test]
+    fn hits_the_block_a_ray_starts_in() {
+        let mut world = empty_world();
+        world.set_block(1, 1, 1, BlockId::STONE);
+
+        let hit = world
+            .raycast(glm::vec3(1.5, 1.5, 1.5), glm::Vec3::x(), 10.0)
+            .unwrap();
+        assert_eq!(hit.block, [1, 1, 1]);
+        assert_eq!(hit.normal, [0; 3]);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn previews_in_front_of_the_hit_face() {
+        let mut world = empty_world();
+        world.set_block(5, 1, 1, BlockId::STONE);
+        world.set_block(4, 1, 1, BlockId::WATER);
+
+        let origin = glm::vec3(1.5, 1.5, 1.5);
+        let hit = world.raycast(origin, glm::Vec3::x(), 10.0).unwrap();
+        let preview = world.raycast_place_preview(origin, glm::Vec3::x(), 10.0);
+        // Placed blocks replace the water in front of the stone
+        assert_eq!(preview, Some(hit.adjacent()));
+        assert_eq!(preview, Some([4, 1, 1]));
+
+        // Nothing is hit
+        assert_eq!(
+            world.raycast_place_preview(origin, glm::Vec3::z(), 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn no_preview_in_occupied_or_unloaded_cells() {
+        let mut world = empty_world();
+        world.set_block(1, 1, 1, BlockId::STONE);
+        world.set_block(1, 1, 15, BlockId::STONE);
+
+        // The ray starts in the stone, so the cell in front of it is taken
+        let origin = glm::vec3(1.5, 1.5, 1.5);
+        assert_eq!(
+            world.raycast_place_preview(origin, glm::Vec3::x(), 10.0),
+            None
+        );
+
+        // The cell in front of the hit face is in a chunk that isn't loaded
+        let origin = glm::vec3(1.5, 1.5, 20.5);
+        assert!(world.raycast(origin, -glm::Vec3::z(), 10.0).is_some());
+        assert_eq!(
+            world.raycast_place_preview(origin, -glm::Vec3::z(), 10.0),
+            None
+        );
+    }
+}
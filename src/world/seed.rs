@@ -0,0 +1,149 @@
+//! Seeds for reproducible world generation.
+
+/// Seed that all world generation is derived from.
+///
+/// Generation only uses integer hashing and basic float arithmetic, so a seed
+/// produces identical terrain across runs and platforms.
+///
+/// Saved as the signed integer with the same bits, since TOML integers are
+/// signed and can't hold seeds above `i64::MAX`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(from = "i64", into = "i64")]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Turn text into a seed, the same way Minecraft does.
+    ///
+    /// Text that is a valid integer is used as-is. Anything else is hashed with
+    /// Java's `String.hashCode`.
+    pub fn from_string(text: &str) -> Self {
+        let text = text.trim();
+        if let Ok(n) = text.parse::<i64>() {
+            return Self(n as u64);
+        }
+
+        let hash = text
+            .encode_utf16()
+            .fold(0i32, |h, c| h.wrapping_mul(31).wrapping_add(c as i32));
+        // Sign extended, like Java's implicit int to long conversion
+        Self(hash as i64 as u64)
+    }
+
+    /// Derive an independent seed, so that separate features of the world don't
+    /// correlate with each other.
+    #[inline]
+    pub fn derive(self, salt: u64) -> Self {
+        Self(mix(self.0 ^ mix(salt)))
+    }
+
+    /// Pseudo-random value in `0.0..1.0` for a world column.
+    #[inline]
+    pub fn random(self, x: i32, z: i32) -> f32 {
+        let h = mix(self.0 ^ mix((x as u32 as u64) << 32 | z as u32 as u64));
+        // The top 24 bits fit exactly in an f32 mantissa
+        (h >> 40) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Smooth noise in `0.0..1.0`, with features roughly `scale` blocks apart.
+    ///
+    /// Interpolates [`WorldSeed::random`] between points on a grid.
+    pub fn value_noise(self, x: i32, z: i32, scale: i32) -> f32 {
+        let (cx, cz) = (x.div_euclid(scale), z.div_euclid(scale));
+        let tx = smoothstep(x.rem_euclid(scale) as f32 / scale as f32);
+        let tz = smoothstep(z.rem_euclid(scale) as f32 / scale as f32);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let top = lerp(self.random(cx, cz), self.random(cx + 1, cz), tx);
+        let bottom = lerp(self.random(cx, cz + 1), self.random(cx + 1, cz + 1), tx);
+        lerp(top, bottom, tz)
+    }
+}
+
+impl From<u64> for WorldSeed {
+    #[inline]
+    fn from(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl From<i64> for WorldSeed {
+    #[inline]
+    fn from(seed: i64) -> Self {
+        Self(seed as u64)
+    }
+}
+
+impl From<WorldSeed> for i64 {
+    #[inline]
+    fn from(seed: WorldSeed) -> Self {
+        seed.0 as i64
+    }
+}
+
+/// SplitMix64 finalizer.
+#[inline]
+const fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+#[inline]
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{chunk::ChunkCoord, World};
+
+    #[test]
+    fn same_seed_generates_identical_chunks() {
+        let seed = WorldSeed::from_string("mixcraft");
+        let pos = ChunkCoord::new(3, 0, -2);
+        let a = World::generate_chunk(seed, pos);
+        let b = World::generate_chunk(seed, pos);
+        assert_eq!(a.to_bytes(), b.to_bytes());
+    }
+
+    #[test]
+    fn different_seeds_generate_different_chunks() {
+        let pos = ChunkCoord::default();
+        let a = World::generate_chunk(WorldSeed(1), pos);
+        let b = World::generate_chunk(WorldSeed(2), pos);
+        assert_ne!(a.to_bytes(), b.to_bytes());
+    }
+
+    #[test]
+    fn numeric_text_is_used_directly() {
+        assert_eq!(WorldSeed::from_string("12345"), WorldSeed(12345));
+        assert_eq!(WorldSeed::from_string("-1"), WorldSeed(u64::MAX));
+    }
+
+    #[test]
+    fn seeds_above_i64_max_survive_saving() {
+        let path = std::env::temp_dir().join(format!("mixcraft-seed-{}.toml", std::process::id()));
+        let config = crate::renderer::RendererConfig {
+            seed: WorldSeed(u64::MAX),
+            ..Default::default()
+        };
+        config.save(&path).unwrap();
+        let loaded = crate::renderer::RendererConfig::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.seed, WorldSeed(u64::MAX));
+    }
+
+    #[test]
+    fn text_is_hashed_like_java() {
+        // "hello".hashCode() == 99162322
+        assert_eq!(WorldSeed::from_string("hello"), WorldSeed(99162322));
+        // Negative hashes are sign extended
+        assert_eq!(
+            WorldSeed::from_string("polygenelubricants"),
+            WorldSeed(i32::MIN as i64 as u64)
+        );
+    }
+}
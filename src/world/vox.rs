@@ -0,0 +1,225 @@
+//! Loading [MagicaVoxel](https://ephtracy.github.io/) `.vox` models.
+//!
+//! See <https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt>
+//! for the format.
+
+use std::fmt;
+use std::path::Path;
+
+use super::blocks::{self, BlockId};
+use super::chunk::{Chunk, CHUNK_SIZE};
+
+const MAGIC: &[u8; 4] = b"VOX ";
+
+/// Error loading a `.vox` model.
+#[derive(Debug)]
+pub enum VoxError {
+    Io(std::io::Error),
+    /// The file doesn't start with the `VOX ` magic number.
+    InvalidMagic,
+    /// The file ended in the middle of a chunk.
+    Truncated,
+    /// A required chunk is missing.
+    MissingChunk(&'static str),
+    /// The model doesn't fit in a single [`Chunk`].
+    TooLarge([u32; 3]),
+}
+
+impl fmt::Display for VoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read vox file: {e}"),
+            Self::InvalidMagic => write!(f, "not a vox file"),
+            Self::Truncated => write!(f, "vox file is truncated"),
+            Self::MissingChunk(id) => write!(f, "vox file has no {id} chunk"),
+            Self::TooLarge([x, y, z]) => write!(
+                f,
+                "vox model is {x}x{y}x{z}, larger than a chunk ({CHUNK_SIZE}³)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VoxError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for VoxError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Load the first model of a `.vox` file into a chunk.
+///
+/// Palette indices are mapped to blocks with [`default_palette`].
+pub fn load_vox(path: impl AsRef<Path>) -> Result<Chunk, VoxError> {
+    let bytes = std::fs::read(path)?;
+    parse_vox(&bytes, default_palette)
+}
+
+/// Parse the first model of a `.vox` file into a chunk.
+///
+/// `palette` maps each voxel's palette index, which is never `0`, to a block.
+/// MagicaVoxel's Z axis points up, so it becomes the chunk's Y axis.
+pub fn parse_vox(bytes: &[u8], palette: impl Fn(u8) -> BlockId) -> Result<Chunk, VoxError> {
+    let mut reader = Reader(bytes);
+    if reader.take(4)? != MAGIC {
+        return Err(VoxError::InvalidMagic);
+    }
+    let _version = reader.u32()?;
+
+    let mut size = None;
+    let mut voxels = None;
+
+    // Children of the MAIN chunk follow its header directly
+    while !reader.0.is_empty() {
+        let id = reader.take(4)?;
+        let content_len = reader.u32()? as usize;
+        let _children_len = reader.u32()?;
+        if id == b"MAIN" {
+            continue;
+        }
+
+        let mut content = Reader(reader.take(content_len)?);
+        match id {
+            b"SIZE" if size.is_none() => {
+                size = Some([content.u32()?, content.u32()?, content.u32()?]);
+            }
+            b"XYZI" if voxels.is_none() => {
+                let count = content.u32()? as usize;
+                voxels = Some(content.take(count.checked_mul(4).ok_or(VoxError::Truncated)?)?);
+            }
+            _ => {}
+        }
+    }
+
+    let size = size.ok_or(VoxError::MissingChunk("SIZE"))?;
+    let voxels = voxels.ok_or(VoxError::MissingChunk("XYZI"))?;
+    if size.iter().any(|&s| s as usize > CHUNK_SIZE) {
+        return Err(VoxError::TooLarge(size));
+    }
+
+    let mut chunk = Chunk::new();
+    for voxel in voxels.chunks_exact(4) {
+        let [x, y, z, index] = [voxel[0], voxel[1], voxel[2], voxel[3]].map(usize::from);
+        // Malformed files may place voxels outside of SIZE
+        if x < CHUNK_SIZE && y < CHUNK_SIZE && z < CHUNK_SIZE {
+            chunk.set(x, z, y, palette(index as u8));
+        }
+    }
+    Ok(chunk)
+}
+
+/// Map palette indices onto the registered blocks, cycling through them in order.
+pub fn default_palette(index: u8) -> BlockId {
+    let ids: Vec<_> = blocks::registry()
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| b.is_some())
+        .map(|(id, _)| BlockId(id as u16))
+        .collect();
+
+    match ids.len() {
+        0 => BlockId::AIR,
+        n => ids[(index as usize).saturating_sub(1) % n],
+    }
+}
+
+/// Reads little-endian values from the front of a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VoxError> {
+        if self.0.len() < len {
+            return Err(VoxError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, VoxError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a `.vox` file with a MAIN chunk holding a SIZE and an XYZI chunk.
+    fn vox(size: [u32; 3], voxels: &[[u8; 4]]) -> Vec<u8> {
+        fn chunk(bytes: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+            bytes.extend_from_slice(id);
+            bytes.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+            bytes.extend_from_slice(content);
+        }
+
+        let mut children = Vec::new();
+        chunk(&mut children, b"SIZE", bytemuck::cast_slice(&size));
+        let mut xyzi = (voxels.len() as u32).to_le_bytes().to_vec();
+        xyzi.extend(voxels.iter().flatten());
+        chunk(&mut children, b"XYZI", &xyzi);
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&150u32.to_le_bytes());
+        bytes.extend_from_slice(b"MAIN");
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        bytes.extend(children);
+        bytes
+    }
+
+    fn palette(index: u8) -> BlockId {
+        match index {
+            1 => BlockId::STONE,
+            2 => BlockId::DIRT,
+            _ => BlockId::GRASS,
+        }
+    }
+
+    #[test]
+    fn parses_voxels_with_z_up() {
+        let bytes = vox([4, 3, 2], &[[0, 0, 0, 1], [3, 2, 1, 2], [1, 2, 0, 7]]);
+        let chunk = parse_vox(&bytes, palette).unwrap();
+
+        // MagicaVoxel's (x, y, z) is the chunk's (x, z, y)
+        assert_eq!(chunk.get(0, 0, 0), BlockId::STONE);
+        assert_eq!(chunk.get(3, 1, 2), BlockId::DIRT);
+        assert_eq!(chunk.get(1, 0, 2), BlockId::GRASS);
+        assert_eq!(chunk.get(3, 2, 1), BlockId::AIR);
+        assert_eq!(chunk.iter_solid().count(), 3);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let bytes = vox([1, 1, 1], &[[0, 0, 0, 1]]);
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[..4].copy_from_slice(b"PNG ");
+        assert!(matches!(
+            parse_vox(&wrong_magic, palette),
+            Err(VoxError::InvalidMagic)
+        ));
+
+        // Cut off in the middle of the XYZI chunk's voxels
+        assert!(matches!(
+            parse_vox(&bytes[..bytes.len() - 2], palette),
+            Err(VoxError::Truncated)
+        ));
+
+        let size = [CHUNK_SIZE as u32 + 1, 1, 1];
+        assert!(matches!(
+            parse_vox(&vox(size, &[]), palette),
+            Err(VoxError::TooLarge(s)) if s == size
+        ));
+    }
+}